@@ -2,42 +2,21 @@
 //!
 //! Target: 3.2x improvement on quantum circuit data
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use quantum_compression::{compress, Config};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use quantum_compression::report::{write_ratio_reports, RatioReport};
+use quantum_compression::huffman::{self, HuffmanTableCache};
+use quantum_compression::simd::TargetPlatform;
+use quantum_compression::testing::quantum_circuit_data;
+use quantum_compression::{compress, decompress, decompress_reader, mps::MPS, Config};
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
 use std::io::Write;
-use rand::Rng;
-
-/// Generate quantum circuit test data
-fn generate_quantum_circuit_data(size: usize) -> Vec<u8> {
-    let mut rng = rand::thread_rng();
-    let mut data = Vec::with_capacity(size);
-    
-    // Quantum circuit patterns: gates, measurements, entanglement
-    for i in 0..size {
-        // Simulate quantum gate matrix elements
-        let real_part = ((i as f64 * 0.05).sin() * 127.0 + 128.0) as u8;
-        let imag_part = ((i as f64 * 0.07).cos() * 127.0 + 128.0) as u8;
-        
-        // Add some structure (repeated patterns)
-        if i % 64 < 8 {
-            data.push(i as u8 % 16);
-        } else if i % 64 < 16 {
-            data.push((real_part ^ imag_part) % 32);
-        } else {
-            data.push(rng.gen_range(real_part.saturating_sub(16)..=real_part.saturating_add(16)));
-        }
-    }
-    
-    data
-}
 
 fn bench_quantum_compression(c: &mut Criterion) {
     let mut group = c.benchmark_group("compression");
     
     for size in [1024, 10240, 102400, 1024000].iter() {
-        let data = generate_quantum_circuit_data(*size);
+        let data = quantum_circuit_data(0, *size);
         let config = Config::default();
         
         group.bench_with_input(
@@ -64,33 +43,105 @@ fn bench_quantum_compression(c: &mut Criterion) {
     group.finish();
 }
 
-fn bench_compression_ratio(c: &mut Criterion) {
+/// Set `QCMP_BENCH_JSON=path` to also write the ratios as a JSON array
+fn bench_compression_ratio(_c: &mut Criterion) {
     let sizes = [1024, 10240, 102400];
     
     println!("\n=== Compression Ratio Analysis ===\n");
     
+    let mut reports = Vec::new();
     for size in sizes {
-        let data = generate_quantum_circuit_data(size);
-        let config = Config::default();
-        
-        // Quantum compression
-        let (compressed, stats) = compress(&data, &config).unwrap();
-        
-        // zlib
-        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all(&data).unwrap();
-        let zlib_compressed = encoder.finish().unwrap();
-        let zlib_ratio = data.len() as f64 / zlib_compressed.len() as f64;
+        let data = quantum_circuit_data(0, size);
+        let report = RatioReport::measure(&data);
         
-        let improvement = stats.compression_ratio / zlib_ratio;
-        
-        println!("Size {}:", size);
-        println!("  Quantum MPS: {:.2}x compression", stats.compression_ratio);
-        println!("  zlib:        {:.2}x compression", zlib_ratio);
-        println!("  Improvement: {:.2}x over zlib", improvement);
+        println!("{}", report);
         println!();
+        reports.push(report);
     }
+    
+    if let Ok(path) = std::env::var("QCMP_BENCH_JSON") {
+        write_ratio_reports(&path, &reports).unwrap();
+        println!("Wrote ratio report to {}", path);
+    }
+}
+
+fn bench_mps_contract(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mps_contract");
+    
+    for size in [1024, 16384, 131072].iter() {
+        let data = quantum_circuit_data(0, *size);
+        let mps = MPS::from_bytes(&data, 16);
+        
+        group.bench_with_input(BenchmarkId::new("contract", size), &mps, |b, mps| {
+            b.iter(|| black_box(mps).contract())
+        });
+    }
+    
+    group.finish();
+}
+
+/// Fixed 1MB input in 32 chunks across thread counts; flat throughput as
+/// threads grow points at contention
+fn bench_parallel_scaling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel_scaling");
+    group.sample_size(10);
+    
+    let data = quantum_circuit_data(0, 1024 * 1024);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    
+    for threads in [1, 2, 4, 8] {
+        let config = Config { chunk_size: 32 * 1024, threads, ..Default::default() };
+        group.bench_with_input(BenchmarkId::new("compress", threads), &data, |b, data| {
+            b.iter(|| compress(black_box(data), &config))
+        });
+    }
+    
+    group.finish();
+}
+
+/// 5MB archive of 20 blocks: parallel block decode against the serial
+/// reader path
+fn bench_parallel_decompress(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel_decompress");
+    group.sample_size(10);
+    
+    let data = quantum_circuit_data(0, 5 * 1024 * 1024);
+    let config = Config { chunk_size: 256 * 1024, ..Default::default() };
+    let (compressed, _) = compress(&data, &config).unwrap();
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    
+    group.bench_function("parallel", |b| b.iter(|| decompress(black_box(&compressed))));
+    group.bench_function("serial", |b| {
+        b.iter(|| decompress_reader(&mut black_box(compressed.as_slice())))
+    });
+    
+    group.finish();
+}
+
+fn bench_huffman_table_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("huffman_table_cache");
+    
+    // A stream of small messages sharing one byte distribution
+    let message = quantum_circuit_data(0, 4 * 1024);
+    let cache = HuffmanTableCache::new(16);
+    
+    group.bench_function("fresh", |b| {
+        b.iter(|| huffman::encode_with_platform(black_box(&message), TargetPlatform::Auto))
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| huffman::encode_cached(black_box(&message), TargetPlatform::Auto, &cache))
+    });
+    
+    group.finish();
 }
 
-criterion_group!(benches, bench_quantum_compression);
+criterion_group!(
+    benches,
+    bench_quantum_compression,
+    bench_compression_ratio,
+    bench_mps_contract,
+    bench_parallel_scaling,
+    bench_parallel_decompress,
+    bench_huffman_table_cache
+);
 criterion_main!(benches);
@@ -0,0 +1,223 @@
+//! Multi-file archives
+//!
+//! Bundles named buffers into one file, tar-style. Each entry is compressed
+//! as its own independent container and located through a file table, so
+//! members can be extracted without touching their neighbours. Entry order
+//! is preserved and names need not be unique: duplicates are kept as
+//! separate members, like repeated tar paths.
+
+use crate::compress::{compress, decompress, MIN_INPUT_SIZE};
+use crate::error::{CompressionError, Result};
+use crate::Config;
+
+/// Magic bytes for archive identification
+const ARCHIVE_MAGIC: &[u8; 4] = b"QCAR";
+/// Version 2 moved the file table ahead of the members
+const ARCHIVE_VERSION: u8 = 2;
+
+/// Entry flag: member is a compressed container rather than raw bytes
+const ENTRY_COMPRESSED: u8 = 0x01;
+
+/// Bundle named buffers into one archive.
+///
+/// Layout: magic + version + entry count, a file table of
+/// (name_len u32 + UTF-8 name + flags u8 + offset u64 + member_len u64)
+/// per entry, then the members back to back; offsets are relative to the
+/// first member. Entries below [`MIN_INPUT_SIZE`] (including empty ones),
+/// and any `config` cannot compress (e.g. an invalid dictionary), are
+/// stored raw.
+pub fn create_archive(entries: &[(String, &[u8])], config: &Config) -> Vec<u8> {
+    let members: Vec<(u8, Vec<u8>)> = entries
+        .iter()
+        .map(|(_, data)| {
+            if data.len() >= MIN_INPUT_SIZE {
+                if let Ok((compressed, _)) = compress(data, config) {
+                    return (ENTRY_COMPRESSED, compressed);
+                }
+            }
+            (0, data.to_vec())
+        })
+        .collect();
+
+    let mut output = Vec::new();
+    output.extend_from_slice(ARCHIVE_MAGIC);
+    output.push(ARCHIVE_VERSION);
+    output.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+
+    let mut offset = 0u64;
+    for ((name, _), (flags, member)) in entries.iter().zip(&members) {
+        output.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        output.extend_from_slice(name.as_bytes());
+        output.push(*flags);
+        output.extend_from_slice(&offset.to_le_bytes());
+        output.extend_from_slice(&(member.len() as u64).to_le_bytes());
+        offset += member.len() as u64;
+    }
+
+    for (_, member) in &members {
+        output.extend_from_slice(member);
+    }
+
+    output
+}
+
+/// Extract every entry of an archive, in archive order
+pub fn extract_archive(compressed: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let (table, members) = parse_table(compressed)?;
+
+    let total = table.iter().try_fold(0usize, |total, e| total.checked_add(e.len));
+    if total != Some(members.len()) {
+        return Err(CompressionError::DecompressionFailed);
+    }
+
+    table
+        .iter()
+        .map(|entry| Ok((entry.name.to_string(), read_member(members, entry)?)))
+        .collect()
+}
+
+/// Extract a single entry by name without decoding any other member.
+///
+/// The file table gives the member's offset, so only that member is read.
+/// With duplicate names the first matching entry is returned; `Ok(None)`
+/// means no entry has this name.
+pub fn extract_entry(compressed: &[u8], name: &str) -> Result<Option<Vec<u8>>> {
+    let (table, members) = parse_table(compressed)?;
+
+    table
+        .iter()
+        .find(|entry| entry.name == name)
+        .map(|entry| read_member(members, entry))
+        .transpose()
+}
+
+/// One file-table record, borrowed from the archive
+struct TableEntry<'a> {
+    name: &'a str,
+    flags: u8,
+    offset: usize,
+    len: usize,
+}
+
+/// Parse the header and file table, returning the table and member section
+fn parse_table(compressed: &[u8]) -> Result<(Vec<TableEntry<'_>>, &[u8])> {
+    if compressed.len() < 9 || &compressed[0..4] != ARCHIVE_MAGIC || compressed[4] != ARCHIVE_VERSION {
+        return Err(CompressionError::DecompressionFailed);
+    }
+
+    let mut pos = 5;
+    let count = u32::from_le_bytes(take(compressed, &mut pos, 4)?.try_into().unwrap());
+    let mut table = Vec::with_capacity((count as usize).min(compressed.len()));
+
+    for _ in 0..count {
+        let name_len = u32::from_le_bytes(take(compressed, &mut pos, 4)?.try_into().unwrap());
+        let name = std::str::from_utf8(take(compressed, &mut pos, name_len as usize)?)
+            .map_err(|_| CompressionError::DecompressionFailed)?;
+        let flags = take(compressed, &mut pos, 1)?[0];
+        let offset = read_usize(take(compressed, &mut pos, 8)?)?;
+        let len = read_usize(take(compressed, &mut pos, 8)?)?;
+
+        table.push(TableEntry { name, flags, offset, len });
+    }
+
+    Ok((table, &compressed[pos..]))
+}
+
+/// Read and decode one member from the member section
+fn read_member(members: &[u8], entry: &TableEntry) -> Result<Vec<u8>> {
+    let mut pos = entry.offset;
+    let member = take(members, &mut pos, entry.len)?;
+
+    match entry.flags {
+        ENTRY_COMPRESSED => decompress(member),
+        0 => Ok(member.to_vec()),
+        _ => Err(CompressionError::DecompressionFailed),
+    }
+}
+
+/// Decode a little-endian `u64` length or offset
+pub(crate) fn read_usize(bytes: &[u8]) -> Result<usize> {
+    usize::try_from(u64::from_le_bytes(bytes.try_into().unwrap()))
+        .map_err(|_| CompressionError::DecompressionFailed)
+}
+
+/// Borrow `len` bytes at `pos` and advance past them
+pub(crate) fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let end = pos.checked_add(len).ok_or(CompressionError::DecompressionFailed)?;
+    let bytes = data.get(*pos..end).ok_or(CompressionError::DecompressionFailed)?;
+    *pos = end;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let circuit: Vec<u8> = (0..3000).map(|i| ((i * 13) % 41) as u8).collect();
+        let notes = b"gate H q0; gate CNOT q0 q1; measure q0 q1;".repeat(10);
+        let entries = vec![
+            ("circuit.bin".to_string(), &circuit[..]),
+            ("notes.txt".to_string(), &notes[..]),
+            ("empty".to_string(), &b""[..]),
+            ("notes.txt".to_string(), &b"short duplicate"[..]),
+        ];
+
+        let archive = create_archive(&entries, &Config::default());
+        let extracted = extract_archive(&archive).unwrap();
+
+        assert_eq!(extracted.len(), entries.len());
+        for ((name, data), (out_name, out_data)) in entries.iter().zip(&extracted) {
+            assert_eq!(name, out_name);
+            assert_eq!(data, out_data);
+        }
+
+        assert!(extract_archive(&archive[..archive.len() - 1]).is_err());
+
+        // A config that cannot compress leaves the entries raw
+        let broken = Config { dictionary: Some(b"QCDT".to_vec()), ..Config::default() };
+        let raw = create_archive(&entries, &broken);
+        let (table, _) = parse_table(&raw).unwrap();
+        assert!(table.iter().all(|e| e.flags == 0));
+        assert_eq!(extract_archive(&raw).unwrap(), extracted);
+    }
+
+    #[test]
+    fn test_overflowing_lengths_are_rejected() {
+        let entries = vec![("a".to_string(), &b"one"[..]), ("b".to_string(), &b"two"[..])];
+        let mut archive = create_archive(&entries, &Config::default());
+        // Both len fields to u64::MAX: their sum wraps
+        let first_len = 5 + 4 + 4 + 1 + 1 + 8;
+        let second_len = first_len + 8 + 4 + 1 + 1 + 8;
+        for pos in [first_len, second_len] {
+            archive[pos..pos + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        }
+        assert!(extract_archive(&archive).is_err());
+    }
+
+    #[test]
+    fn test_extract_middle_entry() {
+        let first = vec![1u8; 500];
+        let middle: Vec<u8> = (0..700).map(|i| (i % 13) as u8 * 11).collect();
+        let last = vec![9u8; 300];
+        let entries = vec![
+            ("first".to_string(), &first[..]),
+            ("middle".to_string(), &middle[..]),
+            ("last".to_string(), &last[..]),
+        ];
+        let mut archive = create_archive(&entries, &Config::default());
+
+        // Corrupt the neighbours: the middle entry must not depend on them
+        let (table, members) = parse_table(&archive).unwrap();
+        let base = archive.len() - members.len();
+        let damaged = [base + table[0].offset + 20, base + table[2].offset + 20];
+        for pos in damaged {
+            archive[pos] ^= 0xFF;
+        }
+
+        assert_eq!(extract_entry(&archive, "middle").unwrap(), Some(middle));
+        assert_eq!(extract_entry(&archive, "missing").unwrap(), None);
+        assert!(extract_entry(&archive, "first").is_err());
+    }
+}
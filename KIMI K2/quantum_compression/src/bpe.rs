@@ -0,0 +1,172 @@
+//! Byte-pair (BPE) symbol grouping
+//!
+//! Repeatedly replaces the most frequent adjacent byte pair with a byte value
+//! that does not occur in the data, shrinking text and structured inputs
+//! before entropy coding. Merges are undone in reverse order on decode.
+
+/// One learned merge: `symbol` stands for the pair `(left, right)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Merge {
+    pub symbol: u8,
+    pub left: u8,
+    pub right: u8,
+}
+
+/// A merge must save more bytes than its 3-byte table entry costs
+const MIN_PAIR_COUNT: usize = 4;
+
+/// Learn up to `max_merges` merges on `data` and apply them.
+///
+/// Stops early when no byte value is left unused or no pair repeats often
+/// enough to pay for its table entry.
+pub fn encode(data: &[u8], max_merges: usize) -> (Vec<Merge>, Vec<u8>) {
+    let mut used = [false; 256];
+    for &b in data {
+        used[b as usize] = true;
+    }
+    let mut free = (0..=255u8).filter(|&b| !used[b as usize]);
+
+    let mut merges = Vec::new();
+    let mut current = data.to_vec();
+    let mut counts = vec![0usize; 256 * 256];
+
+    while merges.len() < max_merges {
+        counts.iter_mut().for_each(|c| *c = 0);
+        for pair in current.windows(2) {
+            counts[(pair[0] as usize) << 8 | pair[1] as usize] += 1;
+        }
+        let (best, &count) = counts
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, c)| (*c, std::cmp::Reverse(i)))
+            .expect("non-empty counts");
+        if count < MIN_PAIR_COUNT {
+            break;
+        }
+        let Some(symbol) = free.next() else {
+            break;
+        };
+
+        let merge = Merge { symbol, left: (best >> 8) as u8, right: best as u8 };
+        current = apply(&current, merge);
+        merges.push(merge);
+    }
+
+    (merges, current)
+}
+
+/// Apply previously learned merges in order, as [`encode`] did while
+/// learning them. No merge symbol may occur in `data`.
+pub fn apply_merges(data: &[u8], merges: &[Merge]) -> Vec<u8> {
+    merges.iter().fold(data.to_vec(), |current, &merge| apply(&current, merge))
+}
+
+/// Replace non-overlapping occurrences of a pair, scanning left to right
+fn apply(data: &[u8], merge: Merge) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if i + 1 < data.len() && data[i] == merge.left && data[i + 1] == merge.right {
+            out.push(merge.symbol);
+            i += 2;
+        } else {
+            out.push(data[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Undo merges (last learned first) to recover the original bytes
+pub fn decode(data: &[u8], merges: &[Merge]) -> Vec<u8> {
+    let mut current = data.to_vec();
+    for merge in merges.iter().rev() {
+        let mut out = Vec::with_capacity(current.len() + current.len() / 4);
+        for &b in &current {
+            if b == merge.symbol {
+                out.push(merge.left);
+                out.push(merge.right);
+            } else {
+                out.push(b);
+            }
+        }
+        current = out;
+    }
+    current
+}
+
+/// Streaming [`decode`]: expands one symbol at a time from `data`.
+///
+/// Each pending byte remembers how many merges may still apply to it, so
+/// the expansion mirrors the reverse passes of [`decode`] exactly (and ends
+/// even for malformed tables) while holding only a small stack.
+pub fn decode_iter<I: Iterator<Item = u8>>(data: I, merges: &[Merge]) -> Expand<I> {
+    Expand { inner: data, merges: merges.to_vec(), stack: Vec::new() }
+}
+
+/// Iterator returned by [`decode_iter`]
+pub struct Expand<I> {
+    inner: I,
+    merges: Vec<Merge>,
+    /// Pending bytes with the number of leading merges still applicable
+    stack: Vec<(u8, usize)>,
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Expand<I> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            let (byte, applicable) = match self.stack.pop() {
+                Some(pending) => pending,
+                None => (self.inner.next()?, self.merges.len()),
+            };
+            match self.merges[..applicable].iter().rposition(|m| m.symbol == byte) {
+                Some(i) => {
+                    let merge = self.merges[i];
+                    self.stack.push((merge.right, i));
+                    self.stack.push((merge.left, i));
+                }
+                None => return Some(byte),
+            }
+        }
+    }
+}
+
+/// Serialize a merge table: count + (symbol, left, right) triples
+pub fn serialize_merges(merges: &[Merge], out: &mut Vec<u8>) {
+    out.push(merges.len() as u8);
+    for m in merges {
+        out.extend_from_slice(&[m.symbol, m.left, m.right]);
+    }
+}
+
+/// Parse a merge table, returning it and the number of bytes consumed
+pub fn deserialize_merges(data: &[u8]) -> Option<(Vec<Merge>, usize)> {
+    let count = *data.first()? as usize;
+    let table = data.get(1..1 + 3 * count)?;
+    let merges = table
+        .chunks_exact(3)
+        .map(|t| Merge { symbol: t[0], left: t[1], right: t[2] })
+        .collect();
+    Some((merges, 1 + 3 * count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpe_roundtrip() {
+        let data = b"the quantum gate, the quantum state, the quantum circuit".repeat(5);
+        let (merges, encoded) = encode(&data, 20);
+        assert!(!merges.is_empty());
+        assert!(encoded.len() < data.len());
+        assert_eq!(decode(&encoded, &merges), data);
+        assert_eq!(decode_iter(encoded.iter().copied(), &merges).collect::<Vec<_>>(), data);
+
+        let mut table = Vec::new();
+        serialize_merges(&merges, &mut table);
+        assert_eq!(deserialize_merges(&table), Some((merges, table.len())));
+    }
+}
@@ -0,0 +1,177 @@
+//! Decompression result cache (feature `decompress-cache`)
+//!
+//! Servers often decompress the same blob again and again. A
+//! [`DecompressCache`] remembers recent outputs by a hash of the compressed
+//! bytes, so a repeat costs a hash pass and a compare instead of a full
+//! decode.
+
+use crate::compress::decompress;
+use crate::error::Result;
+use std::hash::{DefaultHasher, Hasher};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Shared LRU cache of decompressed outputs, bounded by their total size.
+///
+/// Entries are found by the compressed input's length and 64-bit SipHash,
+/// and a hit is only returned once the input matches the stored copy byte
+/// for byte, so a hash collision costs a decode rather than a wrong output.
+/// Clones share one cache, so a handle can be passed to several threads.
+/// Outputs larger than the whole capacity are returned but not kept; the
+/// capacity counts outputs, not the compressed copies held beside them.
+#[derive(Clone)]
+pub struct DecompressCache {
+    inner: Arc<Mutex<CacheState>>,
+}
+
+struct CacheState {
+    capacity: usize,
+    /// Decompressed bytes currently held
+    size: usize,
+    /// Least recently used first
+    entries: Vec<CacheEntry>,
+    hits: u64,
+    misses: u64,
+}
+
+struct CacheEntry {
+    key: (usize, u64),
+    /// The compressed input, compared on every hit
+    input: Box<[u8]>,
+    output: Arc<Vec<u8>>,
+}
+
+impl CacheEntry {
+    fn matches(&self, key: (usize, u64), compressed: &[u8]) -> bool {
+        self.key == key && *self.input == *compressed
+    }
+}
+
+impl DecompressCache {
+    /// A cache holding at most `capacity` decompressed bytes
+    pub fn new(capacity: usize) -> Self {
+        let state = CacheState { capacity, size: 0, entries: Vec::new(), hits: 0, misses: 0 };
+        DecompressCache { inner: Arc::new(Mutex::new(state)) }
+    }
+
+    /// [`decompress`] `compressed`, or hand back the output of an earlier
+    /// call on the same bytes. Failures are not cached.
+    pub fn decompress(&self, compressed: &[u8]) -> Result<Arc<Vec<u8>>> {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(compressed);
+        let key = (compressed.len(), hasher.finish());
+
+        {
+            let mut state = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(i) = state.entries.iter().position(|e| e.matches(key, compressed)) {
+                state.hits += 1;
+                let entry = state.entries.remove(i);
+                let output = Arc::clone(&entry.output);
+                state.entries.push(entry);
+                return Ok(output);
+            }
+            state.misses += 1;
+        }
+
+        // Decode without the lock so other threads are not held up
+        let output = Arc::new(decompress(compressed)?);
+        let mut state = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        if output.len() <= state.capacity && !state.entries.iter().any(|e| e.matches(key, compressed)) {
+            while state.size + output.len() > state.capacity {
+                let evicted = state.entries.remove(0);
+                state.size -= evicted.output.len();
+            }
+            state.size += output.len();
+            let input = compressed.into();
+            state.entries.push(CacheEntry { key, input, output: Arc::clone(&output) });
+        }
+        Ok(output)
+    }
+
+    /// Outputs currently cached
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner).entries.len()
+    }
+
+    /// Whether no output is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Calls answered from the cache so far
+    pub fn hits(&self) -> u64 {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner).hits
+    }
+
+    /// Calls that had to decompress so far
+    pub fn misses(&self) -> u64 {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner).misses
+    }
+}
+
+impl std::fmt::Debug for DecompressCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        f.debug_struct("DecompressCache")
+            .field("capacity", &state.capacity)
+            .field("size", &state.size)
+            .field("len", &state.entries.len())
+            .field("hits", &state.hits)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, Config};
+
+    #[test]
+    fn test_repeat_decompress_hits_cache() {
+        let data = b"rx(0.5) q2; cz q1 q2; ".repeat(200);
+        let other = b"measure q0 -> c0; ".repeat(200);
+        let (compressed, _) = compress(&data, &Config::default()).unwrap();
+        let (other_compressed, _) = compress(&other, &Config::default()).unwrap();
+
+        let cache = DecompressCache::new(data.len() + other.len());
+        let first = cache.decompress(&compressed).unwrap();
+        let second = cache.clone().decompress(&compressed).unwrap();
+        assert_eq!(*first, data);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // Corrupt input is reported, not cached
+        assert!(cache.decompress(&compressed[..compressed.len() - 1]).is_err());
+        assert_eq!(cache.len(), 1);
+
+        // Both fit; a third output evicts the least recently used
+        assert_eq!(*cache.decompress(&other_compressed).unwrap(), other);
+        cache.decompress(&compressed).unwrap();
+        let (third, _) = compress(&other[..other.len() / 2], &Config::default()).unwrap();
+        cache.decompress(&third).unwrap();
+        assert_eq!(cache.len(), 2);
+        cache.decompress(&compressed).unwrap();
+        assert_eq!(cache.hits(), 3);
+        cache.decompress(&other_compressed).unwrap();
+        assert_eq!(cache.hits(), 3);
+    }
+
+    #[test]
+    fn test_hash_collision_is_not_a_hit() {
+        let data = b"h q0; cx q0 q1; ".repeat(200);
+        let (compressed, _) = compress(&data, &Config::default()).unwrap();
+        let cache = DecompressCache::new(4 * data.len());
+        cache.decompress(&compressed).unwrap();
+
+        // Plant a different input under the same key
+        {
+            let mut state = cache.inner.lock().unwrap();
+            let entry = &mut state.entries[0];
+            let mut forged = compressed.clone();
+            forged[compressed.len() / 2] ^= 1;
+            entry.input = forged.into();
+            entry.output = Arc::new(b"wrong".to_vec());
+        }
+        assert_eq!(*cache.decompress(&compressed).unwrap(), data);
+        assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    }
+}
@@ -0,0 +1,138 @@
+//! Quantum circuit dumps
+//!
+//! Circuit dumps interleave streams with very different statistics: gate
+//! indices (small alphabet, repetitive), rotation angles (smooth `f64`
+//! values) and measurement outcomes (near-binary). Compressing them as
+//! separate streams lets each use its best strategy: byte methods for gates
+//! and measurements, the amplitude MPS for angles.
+
+use crate::archive::{read_usize, take};
+use crate::compress::{compress, compress_f64, decompress, decompress_f64, MIN_INPUT_SIZE};
+use crate::error::{CompressionError, Result};
+use crate::Config;
+
+/// Magic bytes for circuit bundle identification
+const CIRCUIT_MAGIC: &[u8; 4] = b"QCCT";
+const CIRCUIT_VERSION: u8 = 1;
+
+/// Stream flag: stream is a compressed container rather than raw bytes
+const STREAM_COMPRESSED: u8 = 0x01;
+
+/// The three streams of a circuit dump
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CircuitStreams {
+    pub gates: Vec<u8>,
+    pub angles: Vec<f64>,
+    pub measurements: Vec<u8>,
+}
+
+/// Compress the gate, angle and measurement streams of a circuit.
+///
+/// Layout: magic + version, then for gates, angles and measurements in turn
+/// flags u8 + stream_len u64 + stream. Streams too short to compress are
+/// stored raw (angles as little-endian `f64`). Fails if an angle is outside
+/// the range [`compress_f64`] accepts.
+pub fn compress_circuit(
+    gates: &[u8],
+    angles: &[f64],
+    measurements: &[u8],
+    config: &Config,
+) -> Result<Vec<u8>> {
+    let angle_bytes: Vec<u8> = angles.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let angles = if angle_bytes.len() >= MIN_INPUT_SIZE {
+        (STREAM_COMPRESSED, compress_f64(angles, config)?.0)
+    } else {
+        (0, angle_bytes)
+    };
+    let streams = [encode_bytes(gates, config)?, angles, encode_bytes(measurements, config)?];
+
+    let mut output = Vec::new();
+    output.extend_from_slice(CIRCUIT_MAGIC);
+    output.push(CIRCUIT_VERSION);
+    for (flags, body) in &streams {
+        output.push(*flags);
+        output.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        output.extend_from_slice(body);
+    }
+
+    Ok(output)
+}
+
+/// Compress a byte stream, or keep it raw if it is too short
+fn encode_bytes(data: &[u8], config: &Config) -> Result<(u8, Vec<u8>)> {
+    if data.len() >= MIN_INPUT_SIZE {
+        Ok((STREAM_COMPRESSED, compress(data, config)?.0))
+    } else {
+        Ok((0, data.to_vec()))
+    }
+}
+
+/// Decompress a bundle produced by [`compress_circuit`]
+pub fn decompress_circuit(compressed: &[u8]) -> Result<CircuitStreams> {
+    if compressed.len() < 5 || &compressed[0..4] != CIRCUIT_MAGIC || compressed[4] != CIRCUIT_VERSION {
+        return Err(CompressionError::DecompressionFailed);
+    }
+
+    let mut pos = 5;
+    let mut next_stream = || -> Result<(u8, &[u8])> {
+        let flags = take(compressed, &mut pos, 1)?[0];
+        let len = read_usize(take(compressed, &mut pos, 8)?)?;
+        Ok((flags, take(compressed, &mut pos, len)?))
+    };
+
+    let gates = decode_bytes(next_stream()?)?;
+    let angles = match next_stream()? {
+        (STREAM_COMPRESSED, body) => decompress_f64(body)?,
+        (0, body) if body.len() % 8 == 0 => body
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect(),
+        _ => return Err(CompressionError::DecompressionFailed),
+    };
+    let measurements = decode_bytes(next_stream()?)?;
+
+    if pos != compressed.len() {
+        return Err(CompressionError::DecompressionFailed);
+    }
+
+    Ok(CircuitStreams { gates, angles, measurements })
+}
+
+/// Decode a byte stream stored raw or as a container
+fn decode_bytes((flags, body): (u8, &[u8])) -> Result<Vec<u8>> {
+    match flags {
+        STREAM_COMPRESSED => decompress(body),
+        0 => Ok(body.to_vec()),
+        _ => Err(CompressionError::DecompressionFailed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CorpusKind;
+
+    #[test]
+    fn test_circuit_roundtrip() {
+        // 2000 layers: a gate opcode, its rotation angle and a measurement bit
+        let gates: Vec<u8> = (0..2000).map(|i| [0u8, 1, 1, 2, 3][i % 5]).collect();
+        let angles: Vec<f64> = (0..2000)
+            .map(|i| (i as f64 * 0.01).sin() * std::f64::consts::PI)
+            .collect();
+        let measurements: Vec<u8> = CorpusKind::Random.generate(12, 2000).iter().map(|b| b >> 7).collect();
+
+        let config = Config::default();
+        let bundle = compress_circuit(&gates, &angles, &measurements, &config).unwrap();
+        let raw_len = gates.len() + 8 * angles.len() + measurements.len();
+        assert!(bundle.len() < raw_len);
+
+        let streams = decompress_circuit(&bundle).unwrap();
+        assert_eq!(streams, CircuitStreams { gates, angles, measurements });
+
+        // Short streams are stored raw
+        let tiny = compress_circuit(&[1, 2], &[0.5], &[], &config).unwrap();
+        let streams = decompress_circuit(&tiny).unwrap();
+        let expected = CircuitStreams { gates: vec![1, 2], angles: vec![0.5], measurements: vec![] };
+        assert_eq!(streams, expected);
+    }
+}
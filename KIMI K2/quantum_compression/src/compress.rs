@@ -2,87 +2,2310 @@
 //!
 //! Combines MPS tensor decomposition with adaptive Huffman for hybrid compression.
 
-use crate::error::{CompressionError, Result};
-use crate::huffman;
-use crate::mps::MPS;
-use crate::{CompressionStats, Config};
-use std::time::Instant;
+use crate::bpe;
+use crate::dictionary::{self, Dictionary};
+use crate::error::{ChecksumScope, CompressionError, Result};
+use crate::format::{self, Header, VERSION_CHUNKED, VERSION_SINGLE};
+use crate::gpu;
+use crate::huffman::{self, EntropyModel, FrequencyCounter};
+use crate::logging::emit;
+use crate::mps::{self, Precision, MPS};
+use crate::lossy;
+use crate::pipeline::{self, Pipeline};
+use crate::remap;
+use crate::rle;
+use crate::schema::{self, Schema};
+use crate::sparse;
+use crate::store;
+use crate::{CompressionStats, Config, DecompressionStats, Fidelity, Strategy, StrategyStats};
+use num_complex::Complex64;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::time::{Duration, Instant};
 
-/// Magic bytes for file format identification
-const MAGIC: &[u8; 4] = b"QCMP";
-const VERSION: u8 = 1;
+pub use crate::format::VERSION;
+
+/// Block frame: method + raw_len + payload_len + CRC32
+const BLOCK_HEADER_LEN: usize = 13;
+
+/// Low bits of the method byte select the [`BlockMethod`]
+const METHOD_MASK: u8 = 0x0F;
+/// Method-byte flag: payload starts with a BPE merge table
+const BLOCK_FLAG_BPE: u8 = 0x80;
+/// Method-byte flag: block holds big-endian 16-bit samples, coded byte-swapped
+const BLOCK_FLAG_SWAP16: u8 = 0x40;
+/// Method-byte flag: block codes the chunk's [`sparse`] bitmap + values form
+const BLOCK_FLAG_SPARSE: u8 = 0x20;
+/// Method-byte flag: payload starts with a [`remap`] table and codes the
+/// chunk over that reduced alphabet
+const BLOCK_FLAG_REMAP: u8 = 0x10;
+
+/// Huffman table length marking an MPS block body whose payload follows
+/// uncoded; a real table is never empty
+const UNCODED_TABLE_LEN: u32 = 0;
+
+/// Smallest input given a framed container; also the smallest chunk ever
+/// formed. Shorter inputs get a compact header, or are rejected when
+/// `Config::format_version` pins an older layout.
+pub const MIN_INPUT_SIZE: usize = 64;
+
+/// Largest `f64` magnitude accepted by [`compress_f64`]. Squares of larger
+/// values (formed in the SVD's Gram matrix) can overflow to infinity.
+pub const MAX_F64_AMPLITUDE: f64 = 1e150;
+
+/// Largest chunk [`compress_deadline`] codes at once, which bounds the work
+/// still in flight when the deadline passes
+const DEADLINE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Byte order of multi-byte input samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// How a block's payload was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum BlockMethod {
+    /// Raw bytes, copied verbatim
+    Stored = 0,
+    /// MPS decomposition + residual, Huffman coded
+    Mps = 1,
+    /// Huffman coding of the raw bytes
+    Huffman = 2,
+    /// MPS over `f64` samples + XOR residual, Huffman coded
+    MpsF64 = 3,
+    /// Adaptive Huffman coding of the raw bytes
+    AdaptiveHuffman = 4,
+    /// Order-1 (previous-byte context) Huffman coding of the raw bytes
+    Order1Huffman = 5,
+    /// Explicit [`Pipeline`](pipeline::Pipeline) of transforms, recorded in the payload
+    Pipeline = 6,
+    /// MPS decomposition + residual quantized to a per-byte error bound,
+    /// Huffman coded (lossy)
+    NearLossless = 7,
+    /// Runs of the dominant byte between the other bytes, for chunks where
+    /// one value makes up nearly everything
+    RunLength = 8,
+    /// Huffman coding with the merges and table of a trained
+    /// [`dictionary`], which the block names by id
+    Dictionary = 9,
+    /// Reference to a chunk kept in a [`ChunkStore`](crate::store::ChunkStore),
+    /// named by its [`chunk_hash`](crate::store::chunk_hash)
+    ChunkRef = 10,
+    /// Huffman coding with the code inline as symbol/length pairs and the
+    /// length left to the block header, for chunks too short to carry a
+    /// full table
+    CompactHuffman = 11,
+    /// CBOR [`Schema`](crate::schema::Schema) describing the container;
+    /// always the first block, with a raw length of 0
+    Schema = 12,
+}
+
+impl BlockMethod {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value & METHOD_MASK {
+            0 => Some(BlockMethod::Stored),
+            1 => Some(BlockMethod::Mps),
+            2 => Some(BlockMethod::Huffman),
+            3 => Some(BlockMethod::MpsF64),
+            4 => Some(BlockMethod::AdaptiveHuffman),
+            5 => Some(BlockMethod::Order1Huffman),
+            6 => Some(BlockMethod::Pipeline),
+            7 => Some(BlockMethod::NearLossless),
+            8 => Some(BlockMethod::RunLength),
+            9 => Some(BlockMethod::Dictionary),
+            10 => Some(BlockMethod::ChunkRef),
+            11 => Some(BlockMethod::CompactHuffman),
+            12 => Some(BlockMethod::Schema),
+            _ => None,
+        }
+    }
+}
+
+/// A chunk after encoding, ready to be framed
+struct EncodedBlock {
+    method: BlockMethod,
+    flags: u8,
+    payload: Vec<u8>,
+    rank: usize,
+    /// Decodes to an approximation of the chunk rather than the chunk
+    lossy: bool,
+    /// Size of the MPS stage output before Huffman coding (0 for blocks
+    /// that carry no MPS)
+    mps_len: usize,
+}
+
+impl EncodedBlock {
+    /// The chunk copied verbatim
+    fn stored(chunk: &[u8]) -> Self {
+        EncodedBlock { method: BlockMethod::Stored, flags: 0, payload: chunk.to_vec(), rank: 0, lossy: false, mps_len: 0 }
+    }
+}
+
+/// Split `len` bytes into chunks of `chunk_size`, folding a tail shorter than
+/// [`MIN_INPUT_SIZE`] into the previous chunk so no chunk is ever too small.
+pub fn plan_chunks(len: usize, chunk_size: usize) -> Vec<Range<usize>> {
+    plan_aligned_chunks(len, chunk_size, 1)
+}
+
+/// Like [`plan_chunks`], but every chunk boundary falls on a multiple of
+/// `alignment` so fixed-size records are never split. The chunk size is
+/// rounded down to a multiple of `alignment` (up, if that would fall below
+/// [`MIN_INPUT_SIZE`]).
+pub fn plan_aligned_chunks(len: usize, chunk_size: usize, alignment: usize) -> Vec<Range<usize>> {
+    let alignment = alignment.max(1);
+    let mut chunk_size = chunk_size.max(MIN_INPUT_SIZE) / alignment * alignment;
+    if chunk_size < MIN_INPUT_SIZE {
+        chunk_size = MIN_INPUT_SIZE.div_ceil(alignment) * alignment;
+    }
+
+    let mut ranges: Vec<Range<usize>> = (0..len)
+        .step_by(chunk_size)
+        .map(|start| start..(start + chunk_size).min(len))
+        .collect();
+
+    if ranges.len() > 1 && ranges.last().is_some_and(|r| r.len() < MIN_INPUT_SIZE) {
+        let tail = ranges.pop().unwrap();
+        ranges.last_mut().unwrap().end = tail.end;
+    }
+
+    ranges
+}
+
+/// Merge adjacent chunks until each spans at least `min_len` bytes; a
+/// short remainder joins the last merged chunk. Boundaries that survive
+/// are existing ones, so alignment is kept.
+pub fn coalesce_chunks(ranges: Vec<Range<usize>>, min_len: usize) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    let mut pending: Option<Range<usize>> = None;
+    for range in ranges {
+        let current = match pending.take() {
+            Some(open) => open.start..range.end,
+            None => range,
+        };
+        if current.len() >= min_len {
+            merged.push(current);
+        } else {
+            pending = Some(current);
+        }
+    }
+
+    if let Some(tail) = pending {
+        match merged.last_mut() {
+            Some(last) => last.end = tail.end,
+            None => merged.push(tail),
+        }
+    }
+    merged
+}
 
 /// Compress data using hybrid MPS + Huffman algorithm
 pub fn compress(data: &[u8], config: &Config) -> Result<(Vec<u8>, CompressionStats)> {
-    if data.len() < 64 {
-        return Err(CompressionError::InputTooSmall(data.len()));
+    compress_chunks(data, config, config.chunk_alignment, lossless_encoder)
+}
+
+thread_local! {
+    /// Output buffer reused by [`compress_with`] on each thread
+    static OUTPUT_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Compress data and hand the result to `f` as a borrowed slice.
+///
+/// The container is built in a per-thread buffer that is reused across
+/// calls, so callers that immediately write the bytes elsewhere avoid
+/// allocating (and later freeing) an output `Vec` each time. Returns the
+/// closure's result alongside the usual stats.
+pub fn compress_with<R>(
+    data: &[u8],
+    config: &Config,
+    f: impl FnOnce(&[u8]) -> R,
+) -> Result<(R, CompressionStats)> {
+    // Take the buffer out so a nested call from `f` just gets a fresh one
+    let mut output = OUTPUT_BUFFER.with(|buffer| std::mem::take(&mut *buffer.borrow_mut()));
+
+    let result = compress_chunks_into(data, config, config.chunk_alignment, lossless_encoder, &mut output)
+        .map(|stats| (f(&output), stats));
+
+    OUTPUT_BUFFER.with(|buffer| *buffer.borrow_mut() = output);
+    result
+}
+
+/// [`compress`], sending the container over `tx` piece by piece so
+/// downstream stages can start before the whole input is coded.
+///
+/// The header goes first, then each framed block as soon as it and every
+/// block before it are encoded; concatenated, the pieces are exactly what
+/// [`compress`] returns. `tx` is dropped on return, closing the channel
+/// whether or not compression succeeded, and the pieces sent before an
+/// error do not form a valid container. A single-block (version 1) or
+/// compact container is sent whole. A dropped receiver fails with
+/// [`CompressionError::Io`].
+pub fn compress_streaming(data: &[u8], config: &Config, tx: Sender<Vec<u8>>) -> Result<CompressionStats> {
+    let send = |piece: Vec<u8>| {
+        tx.send(piece)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "compressed block receiver dropped"))
+    };
+    let whole = config.format_version == VERSION_SINGLE || data.len() < MIN_INPUT_SIZE || config.embed_schema;
+    // An encrypted container is one ciphertext
+    #[cfg(feature = "encryption")]
+    let whole = whole || config.encryption_key.is_some();
+    if whole {
+        let (container, stats) = compress(data, config)?;
+        send(container)?;
+        return Ok(stats);
+    }
+
+    check_format_version(config)?;
+    let dictionary = config.dictionary.as_deref().map(Dictionary::parse).transpose()?;
+    let gpu_device = gpu::select_device(config);
+    let start = Instant::now();
+
+    let planned = plan_aligned_chunks(data.len(), config.chunk_size, config.chunk_alignment);
+    let ranges = coalesce_chunks(planned, config.min_block_size);
+    let header = Header {
+        version: config.format_version,
+        block_count: ranges.len() as u32,
+        data_checksum: (config.format_version == VERSION).then(|| crc32fast::hash(data)),
+    };
+    let mut piece = Vec::with_capacity(header.encoded_len());
+    header.write(&mut piece);
+    let mut compressed_size = piece.len();
+    send(piece)?;
+
+    let mut degraded_chunks = Vec::new();
+    let mut sizes = Vec::with_capacity(ranges.len());
+    let (mut rank, mut mps_serialized_size, mut payload_size, mut stored_fallback) = (0, 0, 0, true);
+
+    // Workers report blocks in completion order; they are forwarded in
+    // chunk order, holding back any that finish early
+    let cancelled = AtomicBool::new(false);
+    let (done_tx, done_rx) = mpsc::channel();
+    std::thread::scope(|scope| -> Result<()> {
+        let (ranges, cancelled, dictionary) = (&ranges, &cancelled, dictionary.as_ref());
+        scope.spawn(move || {
+            let encode_all = || {
+                ranges.par_iter().enumerate().for_each_init(
+                    || (done_tx.clone(), FrequencyCounter::new()),
+                    |(done_tx, counter), (index, range)| {
+                        if !cancelled.load(Ordering::Relaxed) {
+                            let block = lossless_encoder(&data[range.clone()], config, dictionary, counter);
+                            let _ = done_tx.send((index, block));
+                        }
+                    },
+                )
+            };
+            match thread_pool(config.threads) {
+                Some(pool) => pool.install(encode_all),
+                None => encode_all(),
+            }
+        });
+
+        let mut pending = HashMap::new();
+        let mut next = 0;
+        let result = done_rx.iter().try_for_each(|(index, block)| -> Result<()> {
+            pending.insert(index, block);
+            while let Some(block) = pending.remove(&next) {
+                let range = ranges[next].clone();
+                let block = match block {
+                    Ok(block) => block,
+                    Err(e) if config.fail_fast => return Err(e),
+                    Err(_) => {
+                        degraded_chunks.push(next);
+                        EncodedBlock::stored(&data[range.clone()])
+                    }
+                };
+
+                let mut frame = Vec::with_capacity(BLOCK_HEADER_LEN + block.payload.len());
+                push_frame(&mut frame, block.method as u8 | block.flags, range.len(), &block.payload);
+                if config.validate_roundtrip {
+                    let framed = Block {
+                        index: next,
+                        offset: 0,
+                        method: block.method,
+                        flags: block.flags,
+                        raw_len: range.len(),
+                        checksum: crc32fast::hash(&block.payload),
+                        payload: &block.payload,
+                    };
+                    if !decode_block(&framed, dictionary).is_ok_and(|chunk| chunk == data[range.clone()]) {
+                        return Err(CompressionError::RoundtripVerificationFailed);
+                    }
+                }
+
+                rank = rank.max(block.rank);
+                mps_serialized_size += block.mps_len;
+                payload_size += block.payload.len();
+                stored_fallback &= block.method == BlockMethod::Stored && block.flags == 0;
+                check_expansion(config, data.len(), compressed_size + frame.len())?;
+                sizes.push((block.method, range.len(), frame.len()));
+                compressed_size += frame.len();
+                send(frame)?;
+                next += 1;
+            }
+            Ok(())
+        });
+        if result.is_err() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        result
+    })?;
+
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+    if stored_fallback {
+        emit!(config, Info, "no method shrank the input; all {} bytes stored verbatim", data.len());
+    }
+
+    Ok(CompressionStats {
+        original_size: data.len(),
+        compressed_size,
+        compression_ratio: data.len() as f64 / compressed_size as f64,
+        processing_time_ms: elapsed,
+        tensor_rank_used: rank,
+        vram_peak_bytes: 0, // Would be set by GPU monitor
+        gpu_device,
+        fidelity: None,
+        stored_fallback,
+        per_strategy: strategy_stats(sizes),
+        degraded_chunks,
+        mps_serialized_size,
+        entropy_efficiency: entropy_efficiency(data, payload_size),
+    })
+}
+
+/// [`compress`] against a `deadline`, trading ratio for time.
+///
+/// Effort levels run cheapest first on a background thread: Huffman alone
+/// (no method search), then the search at a quarter of `config.max_rank`
+/// without BPE or remapping, then `config` itself. Chunks are at most
+/// [`DEADLINE_CHUNK_SIZE`], and once the deadline passes no chunk starts
+/// and the MPS sweeps in progress drop to rank 1, so the call returns
+/// within about one chunk's cheap methods of the deadline. The smallest
+/// container finished by the deadline is returned, and a level that fails
+/// is skipped. If none finishes, the input is stored verbatim instead
+/// (`stored_fallback` set). The fallback is framed, encrypted and checked
+/// against `Config::abort_on_expansion_over` like any other output.
+///
+/// Version 1 holds one block for the whole input, which no chunk limit
+/// bounds, so with it pinned the levels run detached: a level that misses
+/// the deadline finishes coarsely in the background and is dropped, and
+/// the fallback is a rank-0 MPS block whose residual is the input, uncoded.
+/// `processing_time_ms` is the wall time of the whole call.
+///
+/// Which level finishes in time depends on the machine and its load, so
+/// with `Config::deterministic` set the deadline is ignored and this is
+/// plain [`compress`].
+pub fn compress_deadline(data: &[u8], config: &Config, deadline: Instant) -> Result<(Vec<u8>, CompressionStats)> {
+    if config.deterministic {
+        return compress(data, config);
+    }
+    let start = Instant::now();
+    let single = config.format_version == VERSION_SINGLE;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+
+    let best = if start >= deadline {
+        None
+    } else if single {
+        let worker = (data.to_vec(), deadline_levels(config), Arc::clone(&cancelled));
+        std::thread::spawn(move || {
+            let (data, levels, cancelled) = worker;
+            for level in levels {
+                let result = mps::with_cancel(&cancelled, || compress(&data, &level));
+                if cancelled.load(Ordering::Relaxed) || tx.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+        let best = best_by_deadline(&rx, deadline, config);
+        cancelled.store(true, Ordering::Relaxed);
+        best
+    } else {
+        // Once cancelled, chunks not yet started fail at once, so the level
+        // gives up and the worker returns
+        let encoder = |chunk: &[u8], config: &Config, dictionary: Option<&Dictionary>, counter: &mut FrequencyCounter| {
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(CompressionError::DeadlinePassed);
+            }
+            mps::with_cancel(&cancelled, || lossless_encoder(chunk, config, dictionary, counter))
+        };
+        std::thread::scope(|scope| {
+            let (levels, encoder, cancelled) = (deadline_levels(config), &encoder, &cancelled);
+            scope.spawn(move || {
+                for level in levels {
+                    let level = Config { fail_fast: true, ..level };
+                    let result = compress_chunks(data, &level, level.chunk_alignment, encoder);
+                    if cancelled.load(Ordering::Relaxed) || tx.send(result).is_err() {
+                        break;
+                    }
+                }
+            });
+            let best = best_by_deadline(&rx, deadline, config);
+            cancelled.store(true, Ordering::Relaxed);
+            best
+        })
+    };
+
+    let (output, mut stats) = match best {
+        Some(best) => best,
+        None => {
+            emit!(config, Warn, "deadline passed before compression finished; {} bytes stored verbatim", data.len());
+            let (output, mut stats) = if single {
+                compress(data, &Config { max_rank: 0, ..config.clone() })?
+            } else {
+                let stored = |chunk: &[u8], _: &Config, _: Option<&Dictionary>, _: &mut FrequencyCounter| {
+                    Ok(EncodedBlock::stored(chunk))
+                };
+                compress_chunks(data, config, config.chunk_alignment, stored)?
+            };
+            stats.stored_fallback = true;
+            (output, stats)
+        }
+    };
+    stats.processing_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    Ok((output, stats))
+}
+
+/// The smallest container received on `rx` before `deadline`; failed
+/// levels are logged and skipped
+fn best_by_deadline(
+    rx: &mpsc::Receiver<Result<(Vec<u8>, CompressionStats)>>,
+    deadline: Instant,
+    config: &Config,
+) -> Option<(Vec<u8>, CompressionStats)> {
+    let mut best: Option<(Vec<u8>, CompressionStats)> = None;
+    while let Ok(result) = rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        match result {
+            Ok((output, stats)) if best.as_ref().is_none_or(|(smallest, _)| output.len() < smallest.len()) => {
+                best = Some((output, stats));
+            }
+            Ok(_) => {}
+            // Bound with an underscore as the message is compiled out without `logging`
+            Err(_error) => {
+                emit!(config, Warn, "deadline level failed and was skipped: {_error}");
+            }
+        }
+    }
+    best
+}
+
+/// Configs [`compress_deadline`] tries, cheapest first
+fn deadline_levels(config: &Config) -> Vec<Config> {
+    let chunk_size = config.chunk_size.min(DEADLINE_CHUNK_SIZE);
+    let mut levels = Vec::with_capacity(3);
+    // A single-block container takes no pipeline, and a caller's own
+    // pipeline is already a fixed method
+    if config.format_version != VERSION_SINGLE && config.pipeline.is_none() {
+        levels.push(Config {
+            pipeline: Some(pipeline::Pipeline::new().huffman()),
+            chunk_size,
+            ..config.clone()
+        });
+    }
+    levels.push(Config {
+        max_rank: (config.max_rank / 4).max(1),
+        bpe_merges: 0,
+        symbol_remap: false,
+        chunk_size,
+        ..config.clone()
+    });
+    levels.push(Config { chunk_size, ..config.clone() });
+    levels
+}
+
+/// Compress `f64` samples, decomposing the values themselves as amplitudes.
+///
+/// Every value must be finite and within ±[`MAX_F64_AMPLITUDE`]; larger
+/// magnitudes overflow the SVD's Gram matrix and are rejected with
+/// [`CompressionError::AmplitudeOutOfRange`] rather than silently corrupted.
+/// With `Config::fail_fast` off, chunks holding such values are stored
+/// verbatim instead and listed in [`CompressionStats::degraded_chunks`].
+/// Output decodes with [`decompress_f64`] (or to little-endian bytes with
+/// [`decompress`]).
+pub fn compress_f64(values: &[f64], config: &Config) -> Result<(Vec<u8>, CompressionStats)> {
+    require_chunked(config, "f64 sample blocks need a chunked container")?;
+    if config.fail_fast {
+        validate_f64(values)?;
+    }
+
+    // Never split a sample; `chunk_alignment` counts samples here
+    let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    compress_chunks(&data, config, 8 * config.chunk_alignment.max(1), encode_f64_chunk)
+}
+
+/// Compress 16-bit samples given as bytes in `config.sample_endian` order.
+///
+/// Samples are coded little-endian internally, so either byte order of the
+/// same values compresses alike. Each block records the caller's order:
+/// [`decompress`] restores the exact input bytes and [`decompress_u16`] the
+/// sample values.
+pub fn compress_u16(data: &[u8], config: &Config) -> Result<(Vec<u8>, CompressionStats)> {
+    if !data.len().is_multiple_of(2) {
+        return Err(CompressionError::SampleMisaligned { len: data.len(), width: 2 });
+    }
+
+    type Encoder = fn(&[u8], &Config, Option<&Dictionary>, &mut FrequencyCounter) -> Result<EncodedBlock>;
+    let encoder: Encoder = match config.sample_endian {
+        Endian::Little => lossless_encoder,
+        Endian::Big => {
+            require_chunked(config, "big-endian samples need a chunked container")?;
+            encode_be16_chunk
+        }
+    };
+    compress_chunks(data, config, 2 * config.chunk_alignment.max(1), encoder)
+}
+
+/// Lossy compression holding reconstruction error within `bound`, as
+/// measured by `config.error_metric` (a maximum for L2/LInf, a minimum
+/// PSNR in dB).
+///
+/// Each chunk gets the coarsest residual quantization that still meets the
+/// bound, or is coded losslessly when that is smaller or no loss is
+/// allowed. The achieved error over the whole input is reported in
+/// [`CompressionStats::fidelity`]. Output decodes with [`decompress`].
+pub fn compress_to_error(data: &[u8], config: &Config, bound: f64) -> Result<(Vec<u8>, CompressionStats)> {
+    require_chunked(config, "lossy blocks need a chunked container")?;
+    let encoder = |chunk: &[u8], config: &Config, dictionary: Option<&Dictionary>, counter: &mut FrequencyCounter| {
+        Ok(encode_near_lossless_chunk(chunk, config, dictionary, counter, bound))
+    };
+    let (output, mut stats) = compress_chunks(data, config, config.chunk_alignment, encoder)?;
+
+    let restored = decompress(&output)?;
+    let metric = config.error_metric;
+    stats.fidelity = Some(Fidelity { metric, value: metric.measure(data, &restored) });
+    Ok((output, stats))
+}
+
+/// Lossy compression that spends `budget` on rank rather than fixing it.
+///
+/// Each chunk is coded from its MPS approximation with the residual at its
+/// coarsest quantization ([`lossy::MAX_NEAR`]), so the error left is the
+/// truncation's. The first pass truncates at rank 1; each further pass
+/// doubles the rank, up to `config.max_rank`, while the budget lasts (a
+/// pass already begun is finished). The pass with the best
+/// `config.error_metric` fidelity, reported in
+/// [`CompressionStats::fidelity`], is returned; `processing_time_ms` is the
+/// wall time of all passes. Output decodes with [`decompress`].
+pub fn compress_refine(data: &[u8], config: &Config, budget: Duration) -> Result<(Vec<u8>, CompressionStats)> {
+    require_chunked(config, "lossy blocks need a chunked container")?;
+    let start = Instant::now();
+    let metric = config.error_metric;
+    let perfect = metric.measure(data, data);
+    let encoder = |chunk: &[u8], config: &Config, _: Option<&Dictionary>, _: &mut FrequencyCounter| {
+        Ok(encode_truncated_chunk(chunk, config))
+    };
+
+    let mut best: Option<(Vec<u8>, CompressionStats, f64)> = None;
+    let mut rank = 1;
+    loop {
+        let pass = Config { max_rank: rank, ..config.clone() };
+        let (output, stats) = compress_chunks(data, &pass, config.chunk_alignment, encoder)?;
+        let value = metric.measure(data, &decompress(&output)?);
+        if best.as_ref().is_none_or(|&(_, _, best)| metric.within(value, best)) {
+            best = Some((output, stats, value));
+        }
+        if value == perfect || rank >= config.max_rank || start.elapsed() >= budget {
+            break;
+        }
+        rank = (rank * 2).min(config.max_rank);
+    }
+
+    let (output, mut stats, value) = best.expect("the first pass always runs");
+    stats.fidelity = Some(Fidelity { metric, value });
+    stats.processing_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    Ok((output, stats))
+}
+
+/// Split into aligned chunks, encode them in parallel, and frame the blocks.
+/// `Config::dictionary` is parsed once and handed to `encoder` for every
+/// chunk.
+fn compress_chunks(
+    data: &[u8],
+    config: &Config,
+    alignment: usize,
+    encoder: impl Fn(&[u8], &Config, Option<&Dictionary>, &mut FrequencyCounter) -> Result<EncodedBlock> + Sync,
+) -> Result<(Vec<u8>, CompressionStats)> {
+    let mut output = Vec::new();
+    let stats = compress_chunks_into(data, config, alignment, encoder, &mut output)?;
+    Ok((output, stats))
+}
+
+/// [`compress_chunks`] into a caller-provided buffer, replacing its contents
+fn compress_chunks_into(
+    data: &[u8],
+    config: &Config,
+    alignment: usize,
+    encoder: impl Fn(&[u8], &Config, Option<&Dictionary>, &mut FrequencyCounter) -> Result<EncodedBlock> + Sync,
+    output: &mut Vec<u8>,
+) -> Result<CompressionStats> {
+    // A schema needs framing, so tiny inputs then take the chunked path
+    if data.len() < MIN_INPUT_SIZE && !config.embed_schema {
+        if config.format_version != VERSION {
+            return Err(CompressionError::InputTooSmall(data.len()));
+        }
+        let stats = compress_compact_into(data, config, output)?;
+        check_expansion(config, data.len(), output.len())?;
+        #[cfg(feature = "encryption")]
+        let stats = crate::encryption::encrypt_output(config, output, stats);
+        return Ok(stats);
+    }
+
+    check_format_version(config)?;
+    if config.embed_schema {
+        require_chunked(config, "a schema block needs a chunked container")?;
+    }
+    let dictionary = config.dictionary.as_deref().map(Dictionary::parse).transpose()?;
+    let gpu_device = gpu::select_device(config);
+    let start = Instant::now();
+
+    // Version 1 holds one unframed MPS block for the whole input
+    let single = config.format_version == VERSION_SINGLE;
+    let ranges = if single {
+        std::iter::once(0..data.len()).collect()
+    } else {
+        let planned = plan_aligned_chunks(data.len(), config.chunk_size, alignment);
+        coalesce_chunks(planned, config.min_block_size)
+    };
+
+    // Compress chunks in parallel; rayon preserves chunk order on collect.
+    // Each worker reuses one frequency counter across its chunks.
+    let encode_all = || -> Vec<Result<EncodedBlock>> {
+        ranges
+            .par_iter()
+            .map_init(FrequencyCounter::new, |counter, range| {
+                encoder(&data[range.clone()], config, dictionary.as_ref(), counter)
+            })
+            .collect()
+    };
+    let encoded = if single {
+        let (payload, rank, mps_len) = encode_mps_block(data, config);
+        vec![Ok(EncodedBlock { method: BlockMethod::Mps, flags: 0, payload, rank, lossy: false, mps_len })]
+    } else {
+        match thread_pool(config.threads) {
+            Some(pool) => pool.install(encode_all),
+            None => encode_all(),
+        }
+    };
+
+    // A chunk that failed to encode aborts, or with `fail_fast` off is
+    // stored verbatim and reported
+    let mut degraded_chunks = Vec::new();
+    let mut blocks = Vec::with_capacity(encoded.len());
+    for (index, (range, block)) in ranges.iter().zip(encoded).enumerate() {
+        match block {
+            Ok(block) => blocks.push(block),
+            Err(e) if config.fail_fast => return Err(e),
+            Err(_) => {
+                degraded_chunks.push(index);
+                blocks.push(EncodedBlock::stored(&data[range.clone()]));
+            }
+        }
+    }
+
+    // Build output: header + blocks (version 1 holds its one block unframed)
+    let data_checksum = match config.format_version {
+        VERSION if blocks.iter().any(|b| b.lossy) => {
+            Some(reconstructed_checksum(&ranges, &blocks, dictionary.as_ref())?)
+        }
+        VERSION => Some(crc32fast::hash(data)),
+        _ => None,
+    };
+    let schema = config.embed_schema.then(|| Schema::from_config(config).encode());
+    let block_count = (blocks.len() + schema.is_some() as usize) as u32;
+    let header = Header { version: config.format_version, block_count, data_checksum };
+    let body_len: usize = blocks.iter().map(|b| BLOCK_HEADER_LEN + b.payload.len()).sum();
+    output.clear();
+    output.reserve(header.encoded_len() + body_len);
+    header.write(output);
+    if single {
+        output.extend_from_slice(&blocks[0].payload);
+    }
+    if let Some(schema) = &schema {
+        push_frame(output, BlockMethod::Schema as u8, 0, schema);
+    }
+
+    for (range, block) in ranges.iter().zip(&blocks).filter(|_| !single) {
+        push_frame(output, block.method as u8 | block.flags, range.len(), &block.payload);
+    }
+
+    let restored = || decode_container(&parse_container(output)?, dictionary.as_ref());
+    if config.validate_roundtrip && !restored().is_ok_and(|restored| restored == data) {
+        return Err(CompressionError::RoundtripVerificationFailed);
+    }
+
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+    let framing = if single { 0 } else { BLOCK_HEADER_LEN };
+    let sizes = ranges.iter().zip(&blocks).map(|(range, b)| (b.method, range.len(), framing + b.payload.len()));
+
+    let stats = CompressionStats {
+        original_size: data.len(),
+        compressed_size: output.len(),
+        compression_ratio: data.len() as f64 / output.len() as f64,
+        processing_time_ms: elapsed,
+        tensor_rank_used: blocks.iter().map(|b| b.rank).max().unwrap_or(0),
+        vram_peak_bytes: 0, // Would be set by GPU monitor
+        gpu_device,
+        fidelity: None,
+        stored_fallback: blocks.iter().all(|b| b.method == BlockMethod::Stored && b.flags == 0),
+        per_strategy: strategy_stats(sizes),
+        degraded_chunks,
+        mps_serialized_size: blocks.iter().map(|b| b.mps_len).sum(),
+        entropy_efficiency: entropy_efficiency(data, blocks.iter().map(|b| b.payload.len()).sum()),
+    };
+    if stats.stored_fallback {
+        emit!(config, Info, "no method shrank the input; all {} bytes stored verbatim", data.len());
+    }
+    check_expansion(config, data.len(), output.len())?;
+    #[cfg(feature = "encryption")]
+    let stats = crate::encryption::encrypt_output(config, output, stats);
+
+    Ok(stats)
+}
+
+/// Write `data`, shorter than [`MIN_INPUT_SIZE`], as a compact container:
+/// the two-byte compact header and a stored or compact Huffman block body,
+/// whichever is smaller
+fn compress_compact_into(data: &[u8], config: &Config, output: &mut Vec<u8>) -> Result<CompressionStats> {
+    let start = Instant::now();
+    let huffman = (!data.is_empty())
+        .then(|| huffman::encode_compact(data))
+        .filter(|body| body.len() < data.len());
+    let (method, body) = match &huffman {
+        Some(body) => (BlockMethod::CompactHuffman, body.as_slice()),
+        None => (BlockMethod::Stored, data),
+    };
+
+    output.clear();
+    output.reserve(format::COMPACT_HEADER_LEN + body.len());
+    output.push(format::COMPACT_TAG | method as u8);
+    output.push(data.len() as u8);
+    output.extend_from_slice(body);
+
+    if config.validate_roundtrip && !decompress(output).is_ok_and(|restored| restored == data) {
+        return Err(CompressionError::RoundtripVerificationFailed);
+    }
+
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+    let mut stats = CompressionStats::new(data.len(), output.len(), elapsed);
+    stats.stored_fallback = method == BlockMethod::Stored;
+    stats.entropy_efficiency = entropy_efficiency(data, body.len());
+    if stats.stored_fallback {
+        emit!(config, Info, "no method shrank the input; all {} bytes stored verbatim", data.len());
+    }
+    stats.per_strategy = strategy_stats([(method, data.len(), output.len())]);
+    Ok(stats)
+}
+
+/// Append a framed block: method|flags + raw_len + payload_len + payload
+/// CRC32 + payload
+pub(crate) fn push_frame(output: &mut Vec<u8>, tag: u8, raw_len: usize, payload: &[u8]) {
+    output.push(tag);
+    output.extend_from_slice(&(raw_len as u32).to_le_bytes());
+    output.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    output.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    output.extend_from_slice(payload);
+}
+
+/// Wrap `data` in a container without compressing it.
+///
+/// The bytes go into stored blocks of the default chunk size, each with
+/// its CRC32, behind the usual data checksum, so the result is a valid
+/// container for [`decompress`] too. Any length is accepted, including
+/// inputs shorter than [`MIN_INPUT_SIZE`].
+pub fn seal(data: &[u8]) -> Vec<u8> {
+    let ranges = plan_chunks(data.len(), Config::default().chunk_size);
+    let header = Header {
+        version: VERSION,
+        block_count: ranges.len() as u32,
+        data_checksum: Some(crc32fast::hash(data)),
+    };
+    let mut output = Vec::with_capacity(header.encoded_len() + BLOCK_HEADER_LEN * ranges.len() + data.len());
+    header.write(&mut output);
+    for range in ranges {
+        push_frame(&mut output, BlockMethod::Stored as u8, range.len(), &data[range]);
+    }
+    output
+}
+
+/// Verify a container made by [`seal`] and return its bytes.
+///
+/// Fails with [`CompressionError::ChecksumMismatch`] on corruption, and
+/// with [`CompressionError::DecompressionFailed`] if any block is
+/// compressed rather than stored.
+pub fn unseal(sealed: &[u8]) -> Result<Vec<u8>> {
+    let container = parse_container(sealed)?;
+    if container.version != VERSION
+        || container.blocks.iter().any(|b| b.method != BlockMethod::Stored || b.flags != 0)
+    {
+        return Err(CompressionError::DecompressionFailed);
+    }
+    decode_container(&container, None)
+}
+
+/// Tally chunks and their sizes per block method, from (method, original
+/// size, framed size) of each block
+fn strategy_stats(blocks: impl IntoIterator<Item = (Strategy, usize, usize)>) -> HashMap<Strategy, StrategyStats> {
+    let mut per_strategy: HashMap<Strategy, StrategyStats> = HashMap::new();
+    for (method, original, compressed) in blocks {
+        let entry = per_strategy.entry(method).or_default();
+        entry.chunks += 1;
+        entry.original_size += original;
+        entry.compressed_size += compressed;
+        // Running sum of ratios, divided into a mean below
+        entry.average_ratio += original as f64 / compressed as f64;
+    }
+    for entry in per_strategy.values_mut() {
+        entry.average_ratio /= entry.chunks as f64;
+    }
+    per_strategy
+}
+
+/// Order-0 entropy of `data` in bits over the `payload_len` bytes coding
+/// it; 1.0 when nothing was written
+fn entropy_efficiency(data: &[u8], payload_len: usize) -> f64 {
+    if payload_len == 0 {
+        return 1.0;
+    }
+    order0_entropy_bits(data) / (payload_len * 8) as f64
+}
+
+/// Order-0 entropy of `data` in bits: the least any byte-wise coder needs
+fn order0_entropy_bits(data: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    data.iter().for_each(|&b| counts[b as usize] += 1);
+    let n = data.len() as f64;
+    counts.iter().filter(|&&c| c > 0).map(|&c| c as f64 * (n / c as f64).log2()).sum()
+}
+
+/// A dedicated pool per call when `threads` is set; `None` means rayon's
+/// global pool, which is also the fallback if the pool cannot be spawned
+fn thread_pool(threads: usize) -> Option<rayon::ThreadPool> {
+    if threads == 0 {
+        return None;
+    }
+    rayon::ThreadPoolBuilder::new().num_threads(threads).build().ok()
+}
+
+/// Reject format versions this crate cannot write, and settings whose output
+/// the single-block (version 1) layout cannot represent
+fn check_format_version(config: &Config) -> Result<()> {
+    let version = config.format_version;
+    let reason = match version {
+        VERSION_CHUNKED | VERSION => return Ok(()),
+        VERSION_SINGLE if config.bpe_merges > 0 => "BPE merges need a chunked container",
+        VERSION_SINGLE if config.pipeline.is_some() => "pipelines need a chunked container",
+        VERSION_SINGLE => return Ok(()),
+        _ => "unsupported version",
+    };
+    Err(CompressionError::FormatVersion { version, reason })
+}
+
+/// Fail with [`CompressionError::ExpansionLimit`] if `output_len` bytes for
+/// `input_len` exceed `Config::abort_on_expansion_over`, less the framing
+/// a stored copy of the input, chunked as `config` chunks it, would carry
+fn check_expansion(config: &Config, input_len: usize, output_len: usize) -> Result<()> {
+    let Some(limit) = config.abort_on_expansion_over else {
+        return Ok(());
+    };
+    let planned = plan_aligned_chunks(input_len, config.chunk_size, config.chunk_alignment);
+    let blocks = coalesce_chunks(planned, config.min_block_size).len();
+    let header = Header { version: VERSION, block_count: blocks as u32, data_checksum: Some(0) };
+    let framing = header.encoded_len() + BLOCK_HEADER_LEN * blocks;
+    if output_len as f64 > limit * input_len as f64 + framing as f64 {
+        return Err(CompressionError::ExpansionLimit { input: input_len, output: output_len, limit });
+    }
+    Ok(())
+}
+
+/// Fail when `config` pins the single-block layout, which holds only a
+/// plain byte MPS block and so cannot carry `feature`
+pub(crate) fn require_chunked(config: &Config, feature: &'static str) -> Result<()> {
+    if config.format_version == VERSION_SINGLE {
+        return Err(CompressionError::FormatVersion { version: VERSION_SINGLE, reason: feature });
+    }
+    Ok(())
+}
+
+/// CRC32 of what decoding `blocks` reproduces. Lossy containers record this
+/// instead of the input's CRC, so `decompress` checks against its own output.
+fn reconstructed_checksum(
+    ranges: &[Range<usize>],
+    blocks: &[EncodedBlock],
+    dictionary: Option<&Dictionary>,
+) -> Result<u32> {
+    let mut hasher = crc32fast::Hasher::new();
+    for (index, (range, block)) in ranges.iter().zip(blocks).enumerate() {
+        let block = Block {
+            index,
+            offset: 0,
+            method: block.method,
+            flags: block.flags,
+            raw_len: range.len(),
+            checksum: crc32fast::hash(&block.payload),
+            payload: &block.payload,
+        };
+        hasher.update(&decode_block(&block, dictionary)?);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Encode one chunk with the smallest of the MPS, Huffman-only (per
+/// [`EntropyModel`]) and adaptive Huffman methods, after an optional BPE pre-pass; stores it verbatim if
+/// nothing helps. Chunks at least `Config::sparse_threshold` zero also try
+/// the [`sparse`] form and keep whichever is smaller. A configured
+/// [`Pipeline`](pipeline::Pipeline) replaces the search and runs as given.
+fn encode_chunk(
+    chunk: &[u8],
+    config: &Config,
+    dictionary: Option<&Dictionary>,
+    counter: &mut FrequencyCounter,
+) -> EncodedBlock {
+    if let Some(pipeline) = &config.pipeline {
+        let (payload, rank) = pipeline.encode(chunk, config);
+        let lossy = pipeline.is_lossy();
+        return EncodedBlock { method: BlockMethod::Pipeline, flags: 0, payload, rank, lossy, mps_len: 0 };
+    }
+
+    let block = encode_unmapped_chunk(chunk, config, dictionary, counter);
+    if !config.symbol_remap {
+        return block;
+    }
+    let alphabet = remap::alphabet(chunk);
+    if alphabet.len() > remap::MAX_ALPHABET {
+        return block;
+    }
+
+    let mut remapped = encode_unmapped_chunk(&remap::encode(chunk, &alphabet), config, dictionary, counter);
+    let mut payload = remap::table(&alphabet);
+    payload.extend_from_slice(&remapped.payload);
+    if payload.len() >= block.payload.len() {
+        return block;
+    }
+    remapped.payload = payload;
+    remapped.flags |= BLOCK_FLAG_REMAP;
+    remapped
+}
+
+/// The dense and sparse forms of [`encode_chunk`], over `chunk` as given
+fn encode_unmapped_chunk(
+    chunk: &[u8],
+    config: &Config,
+    dictionary: Option<&Dictionary>,
+    counter: &mut FrequencyCounter,
+) -> EncodedBlock {
+    let dense = encode_dense_chunk(chunk, config, dictionary, counter);
+    if sparse::zero_fraction(chunk) < config.sparse_threshold {
+        return dense;
+    }
+    let mut sparse = encode_dense_chunk(&sparse::encode(chunk), config, dictionary, counter);
+    if sparse.payload.len() >= dense.payload.len() {
+        return dense;
+    }
+    sparse.flags |= BLOCK_FLAG_SPARSE;
+    sparse
+}
+
+/// The method search of [`encode_chunk`], over `chunk` as given
+fn encode_dense_chunk(
+    chunk: &[u8],
+    config: &Config,
+    dictionary: Option<&Dictionary>,
+    counter: &mut FrequencyCounter,
+) -> EncodedBlock {
+    let mut prefix = Vec::new();
+    let mut flags = 0;
+    let mut grouped = None;
+    if config.bpe_merges > 0 {
+        let (merges, encoded) = bpe::encode(chunk, config.bpe_merges);
+        if !merges.is_empty() {
+            bpe::serialize_merges(&merges, &mut prefix);
+            flags |= BLOCK_FLAG_BPE;
+            grouped = Some(encoded);
+        }
+    }
+    let data = grouped.as_deref().unwrap_or(chunk);
+
+    let (mps_payload, rank, mps_len) = encode_mps_block(data, config);
+    let (huffman_method, huffman_payload) = match config.entropy_model {
+        EntropyModel::Order0 => (BlockMethod::Huffman, encode_huffman_block(data, config)),
+        EntropyModel::Order1 => (BlockMethod::Order1Huffman, huffman::encode_order1_with(data, counter)),
+    };
+    let (method, body, rank) = if mps_payload.len() < huffman_payload.len() {
+        (BlockMethod::Mps, mps_payload, rank)
+    } else {
+        (huffman_method, huffman_payload, 0)
+    };
+    let (method, body, rank) = match encode_adaptive_block(data, config) {
+        Some(adaptive) if adaptive.len() < body.len() => (BlockMethod::AdaptiveHuffman, adaptive, 0),
+        _ => (method, body, rank),
+    };
+    // Huffman pays a bit per symbol even for a 99.9% byte; runs do not
+    let (dominant, share) = rle::dominant_byte(data);
+    let (method, body, rank) = match share >= rle::SKEW_THRESHOLD {
+        true => match rle::encode(data, dominant) {
+            runs if runs.len() < body.len() => (BlockMethod::RunLength, runs, 0),
+            _ => (method, body, rank),
+        },
+        false => (method, body, rank),
+    };
+    let (method, body, rank) = if body.len() < data.len() {
+        (method, body, rank)
+    } else {
+        (BlockMethod::Stored, data.to_vec(), 0)
+    };
+
+    let block = if prefix.len() + body.len() >= chunk.len() {
+        EncodedBlock::stored(chunk)
+    } else {
+        prefix.extend_from_slice(&body);
+        let mps_len = if method == BlockMethod::Mps { mps_len } else { 0 };
+        EncodedBlock { method, flags, payload: prefix, rank, lossy: false, mps_len }
+    };
+
+    // A trained dictionary spares the block its own tables
+    if let Some(dictionary) = dictionary {
+        let payload = dictionary.encode(chunk);
+        if payload.len() < block.payload.len() {
+            return EncodedBlock {
+                method: BlockMethod::Dictionary,
+                flags: 0,
+                payload,
+                rank: 0,
+                lossy: false,
+                mps_len: 0,
+            };
+        }
+    }
+    block
+}
+
+/// [`encode_chunk`] as a chunk encoder for [`compress_chunks`]; never fails
+fn lossless_encoder(
+    chunk: &[u8],
+    config: &Config,
+    dictionary: Option<&Dictionary>,
+    counter: &mut FrequencyCounter,
+) -> Result<EncodedBlock> {
+    Ok(encode_chunk(chunk, config, dictionary, counter))
+}
+
+/// Encode big-endian 16-bit samples in little-endian order, flagging the block
+fn encode_be16_chunk(
+    chunk: &[u8],
+    config: &Config,
+    dictionary: Option<&Dictionary>,
+    counter: &mut FrequencyCounter,
+) -> Result<EncodedBlock> {
+    let mut block = encode_chunk(&swap16(chunk), config, dictionary, counter);
+    block.flags |= BLOCK_FLAG_SWAP16;
+    Ok(block)
+}
+
+/// Swap the bytes of each 16-bit sample
+fn swap16(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(2).flat_map(|pair| [pair[1], pair[0]]).collect()
+}
+
+/// Encode a chunk of little-endian `f64` samples, preferring the amplitude
+/// MPS when it beats the generic byte methods. Fails on samples the SVD
+/// cannot take (indices relative to the chunk).
+fn encode_f64_chunk(
+    chunk: &[u8],
+    config: &Config,
+    dictionary: Option<&Dictionary>,
+    counter: &mut FrequencyCounter,
+) -> Result<EncodedBlock> {
+    let values: Vec<f64> = chunk
+        .chunks_exact(8)
+        .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    validate_f64(&values)?;
+
+    let generic = encode_chunk(chunk, config, dictionary, counter);
+    if config.pipeline.is_some() {
+        return Ok(generic);
+    }
+    let (payload, rank, mps_len) = encode_mps_f64_block(&values, config);
+
+    if payload.len() < generic.payload.len() {
+        Ok(EncodedBlock { method: BlockMethod::MpsF64, flags: 0, payload, rank, lossy: false, mps_len })
+    } else {
+        Ok(generic)
+    }
+}
+
+/// Encode one chunk within `bound`: the smaller of the lossless
+/// [`encode_chunk`] result and a near-lossless MPS block
+fn encode_near_lossless_chunk(
+    chunk: &[u8],
+    config: &Config,
+    dictionary: Option<&Dictionary>,
+    counter: &mut FrequencyCounter,
+    bound: f64,
+) -> EncodedBlock {
+    let lossless = encode_chunk(chunk, config, dictionary, counter);
+
+    match encode_near_lossless_block(chunk, config, bound) {
+        Some((payload, rank)) if payload.len() < lossless.payload.len() => {
+            EncodedBlock { method: BlockMethod::NearLossless, flags: 0, payload, rank, lossy: true, mps_len: 0 }
+        }
+        _ => lossless,
+    }
+}
+
+/// Near-lossless block body: Huffman block of (near u8 + mps_len + MPS +
+/// quantized residual), where `mps_len == 0` means the residual is taken
+/// against zero (plain quantization). Both are tried and the smaller kept;
+/// `None` when the bound allows no loss.
+fn encode_near_lossless_block(data: &[u8], config: &Config, bound: f64) -> Option<(Vec<u8>, usize)> {
+    let mut mps = if config.periodic {
+        MPS::from_bytes_periodic(data, config.max_rank)
+    } else {
+        MPS::from_bytes(data, config.max_rank)
+    };
+    mps.round_to(config.precision);
+    let rank = mps.bond_dims.iter().copied().max().unwrap_or(1);
+
+    let encode = |mps: Option<&MPS>| {
+        let approx = mps.map_or_else(|| vec![0; data.len()], MPS::to_bytes);
+        let near = lossy::choose_near(data, &approx, config.error_metric, bound);
+        (near, near_lossless_payload(data, &approx, mps, near, config))
+    };
+
+    let (near, quantized) = encode(None);
+    if near == 0 {
+        return None;
+    }
+    match encode(Some(&mps)) {
+        (near, with_mps) if near > 0 && with_mps.len() < quantized.len() => Some((with_mps, rank)),
+        _ => Some((quantized, 0)),
+    }
+}
+
+/// Near-lossless block coding `chunk` from its MPS truncated at
+/// `config.max_rank`, with the residual at [`lossy::MAX_NEAR`]
+fn encode_truncated_chunk(chunk: &[u8], config: &Config) -> EncodedBlock {
+    let mut mps = if config.periodic {
+        MPS::from_bytes_periodic(chunk, config.max_rank)
+    } else {
+        MPS::from_bytes(chunk, config.max_rank)
+    };
+    mps.round_to(config.precision);
+    let rank = mps.bond_dims.iter().copied().max().unwrap_or(1);
+    let payload = near_lossless_payload(chunk, &mps.to_bytes(), Some(&mps), lossy::MAX_NEAR, config);
+    EncodedBlock { method: BlockMethod::NearLossless, flags: 0, payload, rank, lossy: true, mps_len: 0 }
+}
+
+/// Near-lossless block body for `data` against `approx`, the
+/// reconstruction of `mps` (zero without one), quantized at `near`
+fn near_lossless_payload(data: &[u8], approx: &[u8], mps: Option<&MPS>, near: u8, config: &Config) -> Vec<u8> {
+    let mps_data = mps.map(|mps| mps.serialize_with(config.precision)).unwrap_or_default();
+    let mut payload = Vec::with_capacity(5 + mps_data.len() + data.len());
+    payload.push(near);
+    payload.extend_from_slice(&(mps_data.len() as u32).to_le_bytes());
+    payload.extend_from_slice(&mps_data);
+    payload.extend_from_slice(&lossy::quantize_residual(data, approx, near));
+    encode_huffman_block(&payload, config)
+}
+
+/// MPS-f64 block body: Huffman block of (mps_len + MPS + XOR residual bits).
+/// Returns the body, the largest bond dimension, and the size before
+/// Huffman coding.
+fn encode_mps_f64_block(values: &[f64], config: &Config) -> (Vec<u8>, usize, usize) {
+    let mut mps = if config.exploit_real {
+        MPS::from_real(values, config.max_rank, config.periodic)
+    } else {
+        let amplitudes: Vec<Complex64> = values.iter().map(|&v| Complex64::new(v, 0.0)).collect();
+        MPS::from_amplitudes(&amplitudes, config.max_rank, config.periodic)
+    };
+    mps.round_to(config.precision);
+    let mut payload = Vec::with_capacity(4 + 8 * values.len());
+    push_mps(&mps, config.precision, &mut payload);
+
+    // XOR of bit patterns: close approximations leave the high bytes zero
+    for (v, approx) in values.iter().zip(mps.to_f64()) {
+        payload.extend_from_slice(&(v.to_bits() ^ approx.to_bits()).to_le_bytes());
+    }
+
+    let rank = mps.bond_dims.iter().copied().max().unwrap_or(1);
+    (encode_mps_payload(&payload, config), rank, payload.len())
+}
+
+/// Reject samples that cannot be decomposed without overflow
+fn validate_f64(values: &[f64]) -> Result<()> {
+    match values
+        .iter()
+        .position(|v| !v.is_finite() || v.abs() > MAX_F64_AMPLITUDE)
+    {
+        Some(index) => Err(CompressionError::AmplitudeOutOfRange { index, value: values[index] }),
+        None => Ok(()),
+    }
+}
+
+/// Huffman block body: table_len + Huffman table + Huffman data
+pub(crate) fn encode_huffman_block(data: &[u8], config: &Config) -> Vec<u8> {
+    let platform = config.target_platform.for_len(data.len(), config.simd_threshold);
+    let (huffman_data, table_data) = match &config.huffman_table_cache {
+        Some(cache) => {
+            let (huffman_data, table) = huffman::encode_cached(data, platform, cache);
+            (huffman_data, table.serialize())
+        }
+        None => {
+            let (huffman_data, table) = huffman::encode_with_platform(data, platform);
+            (huffman_data, table.serialize())
+        }
+    };
+    let table_data = table_data.expect("length-limited codes always serialize");
+
+    let mut output = Vec::with_capacity(4 + table_data.len() + huffman_data.len());
+    output.extend_from_slice(&(table_data.len() as u32).to_le_bytes());
+    output.extend_from_slice(&table_data);
+    output.extend_from_slice(&huffman_data);
+    output
+}
+
+/// Adaptive Huffman block body: rebuild_interval + adaptive Huffman data.
+/// Returns `None` when the adaptive coder is disabled.
+fn encode_adaptive_block(data: &[u8], config: &Config) -> Option<Vec<u8>> {
+    let interval = config.huffman_rebuild_interval;
+    if interval == 0 {
+        return None;
+    }
+
+    let encoded = huffman::encode_adaptive(data, interval);
+    let mut output = Vec::with_capacity(4 + encoded.len());
+    output.extend_from_slice(&(interval as u32).to_le_bytes());
+    output.extend_from_slice(&encoded);
+    Some(output)
+}
+
+/// MPS block body: (mps_len + MPS + residual) through
+/// [`encode_mps_payload`]. Returns the body, the largest bond dimension,
+/// and the size before Huffman coding.
+fn encode_mps_block(data: &[u8], config: &Config) -> (Vec<u8>, usize, usize) {
+    let (payload, rank) = encode_mps_residual(data, config.max_rank, config);
+    // Rank 0 stores the input as the residual of an all-zero chain
+    if config.max_rank == 0 {
+        let mut body = Vec::with_capacity(4 + payload.len());
+        body.extend_from_slice(&UNCODED_TABLE_LEN.to_le_bytes());
+        body.extend_from_slice(&payload);
+        return (body, 0, payload.len());
+    }
+    if rank >= config.max_rank {
+        emit!(config, Debug, "MPS bond rank clamped at max_rank {} on a {}-byte chunk", config.max_rank, data.len());
+    }
+    (encode_mps_payload(&payload, config), rank, payload.len())
+}
+
+/// Entropy stage of an MPS block body: a Huffman block of `payload`, or
+/// [`UNCODED_TABLE_LEN`] and `payload` as is when Huffman would not shrink
+/// it. Serialized tensors and noisy residuals are often close to 8 bits a
+/// byte, and a table costs over 512 bytes, so the payload's order-0 entropy
+/// settles most cases without running the coder.
+fn encode_mps_payload(payload: &[u8], config: &Config) -> Vec<u8> {
+    // A table holds 256 code lengths and at least a byte per code
+    let floor = 4 + 512 + (order0_entropy_bits(payload) / 8.0).ceil() as usize;
+    if floor < 4 + payload.len() {
+        let coded = encode_huffman_block(payload, config);
+        if coded.len() < 4 + payload.len() {
+            return coded;
+        }
+    }
+    let mut output = Vec::with_capacity(4 + payload.len());
+    output.extend_from_slice(&UNCODED_TABLE_LEN.to_le_bytes());
+    output.extend_from_slice(payload);
+    output
+}
+
+/// MPS decomposition plus the residual against its truncated reconstruction
+/// (mps_len + MPS + residual), which keeps the codec lossless. Returns the
+/// payload and the largest bond dimension. A `max_rank` of 0 keeps an
+/// all-zero chain, so the residual is the data itself.
+pub(crate) fn encode_mps_residual(data: &[u8], max_rank: usize, config: &Config) -> (Vec<u8>, usize) {
+    let precision = config.precision;
+    let mut payload = Vec::with_capacity(4 + data.len());
+    if max_rank == 0 {
+        // A zero chain reconstructs zeros, so there is nothing to contract
+        push_mps(&MPS::zeros(data.len()), precision, &mut payload);
+        payload.extend_from_slice(data);
+        return (payload, 0);
+    }
+    let mut mps = if config.exploit_real {
+        MPS::from_bytes_real(data, max_rank, config.periodic)
+    } else if config.periodic {
+        MPS::from_bytes_periodic(data, max_rank)
+    } else {
+        MPS::from_bytes(data, max_rank)
+    };
+    // The residual is taken against the chain as the decoder will read it
+    mps.round_to(precision);
+    push_mps(&mps, precision, &mut payload);
+    payload.extend(
+        data.iter()
+            .zip(mps.to_bytes())
+            .map(|(&orig, approx)| orig.wrapping_sub(approx)),
+    );
+
+    (payload, mps.bond_dims.iter().copied().max().unwrap_or(1))
+}
+
+/// Append mps_len u32 + serialized MPS, serializing in place
+fn push_mps(mps: &MPS, precision: Precision, payload: &mut Vec<u8>) {
+    let start = payload.len();
+    payload.extend_from_slice(&[0; 4]);
+    mps.serialize_into_with(payload, precision);
+    let mps_len = (payload.len() - start - 4) as u32;
+    payload[start..start + 4].copy_from_slice(&mps_len.to_le_bytes());
+}
+
+/// Decompress data
+///
+/// Fails with [`CompressionError::ChecksumMismatch`] if a block payload
+/// does not match its framed CRC32 (the error names the block and its byte
+/// range in `compressed`), or if the reconstructed bytes do not match the
+/// original-data checksum stored at compression.
+///
+/// Blocks coded against a dictionary fail with
+/// [`CompressionError::DictionaryRequired`]; read those with
+/// [`decompress_with_dictionary`].
+///
+/// Bytes after the last block of a chunked container (transport padding,
+/// say) are ignored; [`decompress_member`] reports where the container ended.
+/// A container whose counts and lengths were written big-endian fails with
+/// [`CompressionError::ByteSwapped`] rather than as generic corruption.
+pub fn decompress(compressed: &[u8]) -> Result<Vec<u8>> {
+    decode_container(&parse_container(compressed)?, None)
+}
+
+/// [`decompress`] against a `deadline`, yielding what is done by then.
+///
+/// Blocks are decoded in order, a round of one per worker thread at a
+/// time, and no round starts after the deadline. Returns the decoded
+/// prefix, which ends on a block boundary, and whether it is the whole
+/// data; the first round always runs, so a preview of a non-empty archive
+/// is never empty. Each block is checked against its CRC32 as usual, but
+/// the whole-data checksum only once every block is decoded.
+pub fn decompress_deadline(compressed: &[u8], deadline: Instant) -> Result<(Vec<u8>, bool)> {
+    let container = parse_container(compressed)?;
+    let stages = schema::from_blocks(&container.blocks).and_then(|schema| schema.pipeline());
+    let round = rayon::current_num_threads().max(1);
+
+    let mut output = Vec::new();
+    for (i, batch) in container.blocks.chunks(round).enumerate() {
+        if i > 0 && Instant::now() >= deadline {
+            return Ok((output, false));
+        }
+        for chunk in decode_blocks(batch, None, stages.as_ref())? {
+            output.extend_from_slice(&chunk);
+        }
+    }
+    verify_data_checksum(container.data_checksum, &output)?;
+    Ok((output, true))
+}
+
+/// Decompress the container at the start of `compressed`, returning the
+/// data and the number of bytes the container occupied.
+///
+/// Anything past that count is left alone, so callers can check that it is
+/// padding or go on to the next member. Single-block (version 1) and
+/// compact containers are unframed and always consume the whole input.
+pub fn decompress_member(compressed: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let container = parse_container(compressed)?;
+    Ok((decode_container(&container, None)?, container.len))
+}
+
+/// Decompress segments, containers compressed separately and written back
+/// to back (a text header then a binary body, say), the `i`th segment into
+/// `writers[i]`.
+///
+/// Every segment is parsed before anything is written, so a segment count
+/// other than `writers.len()` fails with
+/// [`CompressionError::SegmentCountMismatch`] and leaves the writers
+/// untouched. Single-block (version 1) and compact containers run to the
+/// end of the input, so only the last segment may be one.
+pub fn decompress_segments_to(compressed: &[u8], writers: &mut [&mut dyn Write]) -> Result<()> {
+    let mut segments = Vec::new();
+    let mut rest = compressed;
+    while !rest.is_empty() {
+        let container = parse_container(rest)?;
+        rest = &rest[container.len..];
+        segments.push(container);
+    }
+    if segments.len() != writers.len() {
+        return Err(CompressionError::SegmentCountMismatch { expected: writers.len(), found: segments.len() });
+    }
+
+    for (container, writer) in segments.iter().zip(writers) {
+        writer.write_all(&decode_container(container, None)?)?;
+    }
+    Ok(())
+}
+
+/// [`decompress`] without copying when the container holds a single block
+/// stored verbatim (as incompressible inputs up to one chunk are): the
+/// result then borrows that block from `compressed`. Any other container
+/// is decoded into an owned buffer.
+pub fn decompress_borrowed(compressed: &[u8]) -> Result<Cow<'_, [u8]>> {
+    let container = parse_container(compressed)?;
+    match container.blocks.as_slice() {
+        [block] if block.method == BlockMethod::Stored && block.flags == 0 => {
+            verify_checksum(block)?;
+            if block.payload.len() != block.raw_len {
+                return Err(CompressionError::DecompressionFailed);
+            }
+            verify_data_checksum(container.data_checksum, block.payload)?;
+            Ok(Cow::Borrowed(block.payload))
+        }
+        _ => decode_container(&container, None).map(Cow::Owned),
+    }
+}
+
+/// [`decompress`], then check the output against `expected_hash`, the
+/// CRC32 of the original data recorded by the caller.
+///
+/// The hash is recomputed over the reconstructed bytes whatever checksums
+/// the container carries (version 2 containers carry none for the data), so
+/// an archive restore does not rest on the container vouching for itself.
+/// A mismatch fails with [`CompressionError::ChecksumMismatch`] in scope
+/// [`ChecksumScope::Expected`].
+pub fn decompress_verified(compressed: &[u8], expected_hash: u32) -> Result<Vec<u8>> {
+    let output = decompress(compressed)?;
+    let actual = crc32fast::hash(&output);
+    if actual != expected_hash {
+        return Err(CompressionError::ChecksumMismatch {
+            scope: ChecksumScope::Expected,
+            expected: expected_hash,
+            actual,
+        });
+    }
+    Ok(output)
+}
+
+/// Decompress data compressed with `Config::dictionary` set to `dictionary`
+pub fn decompress_with_dictionary(compressed: &[u8], dictionary: &[u8]) -> Result<Vec<u8>> {
+    decode_container(&parse_container(compressed)?, Some(&Dictionary::parse(dictionary)?))
+}
+
+/// Decompress data and report container statistics
+pub fn decompress_with_stats(compressed: &[u8]) -> Result<(Vec<u8>, DecompressionStats)> {
+    let container = parse_container(compressed)?;
+    let output = decode_container(&container, None)?;
+
+    Ok((output, container_stats(compressed, &container)))
+}
+
+/// Validate a container and gather its statistics without decoding it.
+///
+/// Block headers are parsed and every payload checksum is verified, but no
+/// block is reconstructed, so the original-data checksum is not checked.
+/// Single-block (version 1) containers are unframed, so their block is still
+/// decoded once to learn its length.
+pub fn inspect(compressed: &[u8]) -> Result<DecompressionStats> {
+    let container = parse_container(compressed)?;
+    for block in &container.blocks {
+        verify_checksum(block)?;
+    }
+
+    Ok(container_stats(compressed, &container))
+}
+
+/// Summarize a parsed container
+fn container_stats(compressed: &[u8], container: &Container) -> DecompressionStats {
+    let blocks = &container.blocks;
+    DecompressionStats {
+        version: container.version,
+        block_count: blocks.len(),
+        compressed_size: compressed.len(),
+        decompressed_size: blocks.iter().map(|b| b.raw_len).sum(),
+        block_checksums: blocks.iter().map(|b| b.checksum).collect(),
+    }
+}
+
+/// Decode every block in parallel, then check the result against the data
+/// checksum
+fn decode_container(container: &Container, dictionary: Option<&Dictionary>) -> Result<Vec<u8>> {
+    let stages = schema::from_blocks(&container.blocks).and_then(|schema| schema.pipeline());
+    let output = decode_blocks(&container.blocks, dictionary, stages.as_ref())?.concat();
+
+    verify_data_checksum(container.data_checksum, &output)?;
+    Ok(output)
+}
+
+/// Decode blocks concurrently; rayon preserves block order on collect
+fn decode_blocks(
+    blocks: &[Block],
+    dictionary: Option<&Dictionary>,
+    stages: Option<&Pipeline>,
+) -> Result<Vec<Vec<u8>>> {
+    blocks.par_iter().map(|block| decode_block_with(block, dictionary, stages)).collect()
+}
+
+/// Compare reconstructed bytes with the stored original-data CRC32, if any
+pub(crate) fn verify_data_checksum(expected: Option<u32>, data: &[u8]) -> Result<()> {
+    match expected {
+        Some(expected) => {
+            let actual = crc32fast::hash(data);
+            if actual != expected {
+                return Err(CompressionError::ChecksumMismatch { scope: ChecksumScope::Data, expected, actual });
+            }
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Decompress one container from a buffered reader.
+///
+/// Chunked containers are read frame by frame: each block header is read,
+/// then exactly its payload with `read_exact`, so the reader is left
+/// positioned at the first byte after the container (e.g. the next member).
+/// Single-block (version 1) and compact containers are unframed and read
+/// to EOF.
+pub fn decompress_reader<R: BufRead>(reader: &mut R) -> Result<Vec<u8>> {
+    decompress_from(reader)
+}
+
+/// [`decompress_reader`] over any reader, for callers holding a trait
+/// object.
+///
+/// Reads go straight to `reader` without buffering, so it is left exactly
+/// after the container; short reads are retried until each frame is
+/// complete. Wrap slow unbuffered sources in a `BufReader` first.
+pub fn decompress_from(reader: &mut dyn Read) -> Result<Vec<u8>> {
+    let mut prefix = vec![0u8; format::PREFIX_LEN];
+    reader.read_exact(&mut prefix[..1])?;
+    if format::is_compact(&prefix) {
+        // Compact containers are unframed, like version 1
+        prefix.truncate(1);
+        reader.read_to_end(&mut prefix)?;
+        return decompress(&prefix);
+    }
+    reader.read_exact(&mut prefix[1..])?;
+    prefix.resize(format::header_len(&prefix)?, 0);
+    reader.read_exact(&mut prefix[format::PREFIX_LEN..])?;
+    let (header, _) = Header::parse(&prefix)?;
+
+    match header.version {
+        VERSION_SINGLE => {
+            let mut body = Vec::new();
+            reader.read_to_end(&mut body)?;
+            decode_mps_block(&body)
+        }
+        _ => {
+            // Bytes consumed so far, to locate each payload for error reports
+            let mut pos = header.encoded_len();
+            let mut output = Vec::new();
+            let mut frame = [0u8; BLOCK_HEADER_LEN];
+            let mut payload = Vec::new();
+            for index in 0..header.block_count as usize {
+                reader.read_exact(&mut frame)?;
+                let method = BlockMethod::from_u8(frame[0])
+                    .ok_or(CompressionError::DecompressionFailed)?;
+                let flags = frame[0] & !METHOD_MASK;
+                let payload_len = read_u32(&frame, 5)? as usize;
+
+                payload.resize(payload_len, 0);
+                reader.read_exact(&mut payload)?;
+                pos += BLOCK_HEADER_LEN;
+
+                let block = Block {
+                    index,
+                    offset: pos,
+                    method,
+                    flags,
+                    raw_len: read_u32(&frame, 1)? as usize,
+                    checksum: read_u32(&frame, 9)?,
+                    payload: &payload,
+                };
+                output.extend_from_slice(&decode_block(&block, None)?);
+                pos += payload_len;
+            }
+
+            verify_data_checksum(header.data_checksum, &output)?;
+            Ok(output)
+        }
+    }
+}
+
+/// Decompress into `writer`, holding at most `config.max_chunk_memory`
+/// decoded bytes of any one chunk in memory.
+///
+/// Chunks under the cap are decoded whole, consecutive ones in parallel
+/// batches (on `config.threads` workers) that together fit the cap; larger
+/// ones are streamed symbol by symbol (MPS chunks one contraction window at
+/// a time) and written in pieces no bigger than the cap. Returns the number
+/// of bytes written. The
+/// original-data checksum can only be checked at the end, so on
+/// [`CompressionError::ChecksumMismatch`] the writer already holds the
+/// corrupt output.
+pub fn decompress_to_writer<W: Write>(compressed: &[u8], writer: &mut W, config: &Config) -> Result<u64> {
+    let container = parse_container(compressed)?;
+    let dictionary = config.dictionary.as_deref().map(Dictionary::parse).transpose()?;
+    let stages = schema::from_blocks(&container.blocks).and_then(|schema| schema.pipeline());
+    let cap = config.max_chunk_memory.max(1);
+    let mut hasher = crc32fast::Hasher::new();
+    let mut sink = |piece: &[u8]| {
+        hasher.update(piece);
+        writer.write_all(piece)
+    };
+
+    let pool = thread_pool(config.threads);
+    let blocks = &container.blocks;
+    let mut written = 0u64;
+    let mut next = 0;
+    while next < blocks.len() {
+        if blocks[next].raw_len > cap {
+            stream_block(&blocks[next], dictionary.as_ref(), cap, &mut sink)?;
+            written += blocks[next].raw_len as u64;
+            next += 1;
+            continue;
+        }
+
+        let (start, mut held) = (next, 0);
+        while next < blocks.len() && blocks[next].raw_len <= cap - held {
+            held += blocks[next].raw_len;
+            next += 1;
+        }
+        let batch = &blocks[start..next];
+        let decode = || decode_blocks(batch, dictionary.as_ref(), stages.as_ref());
+        let decoded = match &pool {
+            Some(pool) => pool.install(decode),
+            None => decode(),
+        }?;
+        for chunk in &decoded {
+            sink(chunk)?;
+        }
+        written += held as u64;
+    }
+
+    if let Some(expected) = container.data_checksum {
+        let actual = hasher.finalize();
+        if actual != expected {
+            return Err(CompressionError::ChecksumMismatch { scope: ChecksumScope::Data, expected, actual });
+        }
+    }
+    Ok(written)
+}
+
+/// Verify and decode a block too large to hold whole, handing `sink`
+/// pieces of at most `cap` bytes
+fn stream_block(
+    block: &Block,
+    dictionary: Option<&Dictionary>,
+    cap: usize,
+    sink: &mut impl FnMut(&[u8]) -> std::io::Result<()>,
+) -> Result<()> {
+    verify_checksum(block)?;
+
+    let mut piece = Vec::with_capacity(cap.min(block.raw_len));
+    let mut total = 0;
+    // One byte past `raw_len` is enough to detect an overlong block
+    for byte in block_stream(block, dictionary)?.take(block.raw_len + 1) {
+        piece.push(byte);
+        if piece.len() == cap {
+            sink(&piece)?;
+            total += piece.len();
+            piece.clear();
+        }
+    }
+    sink(&piece)?;
+    total += piece.len();
+
+    if total != block.raw_len {
+        return Err(CompressionError::DecompressionFailed);
+    }
+    Ok(())
+}
+
+/// Byte-at-a-time view of a block's decoded contents; corrupt input ends
+/// the stream early
+fn block_stream<'a>(
+    block: &Block<'a>,
+    dictionary: Option<&Dictionary>,
+) -> Result<Box<dyn Iterator<Item = u8> + 'a>> {
+    let (alphabet, merges, body) = split_prefixes(block)?;
+    let invalid = || CompressionError::DecompressionFailed;
+    let huffman_decoder = |body: &'a [u8]| -> Result<huffman::Decoder<'a>> {
+        let (table_data, huffman_data) = split_huffman_block(body)?;
+        huffman::Decoder::new(huffman_data, table_data).ok_or_else(invalid)
+    };
+
+    let bytes: Box<dyn Iterator<Item = u8> + 'a> = match block.method {
+        BlockMethod::Stored => Box::new(body.iter().copied()),
+        // Uncoded MPS payloads are rebuilt whole
+        BlockMethod::Mps if uncoded_mps_payload(body)? => Box::new(decode_mps_block(body)?.into_iter()),
+        BlockMethod::MpsF64 if uncoded_mps_payload(body)? => Box::new(decode_mps_f64_block(body)?.into_iter()),
+        BlockMethod::Mps => Box::new(MpsStream::new(huffman_decoder(body)?, false)?),
+        BlockMethod::Huffman => Box::new(huffman_decoder(body)?),
+        BlockMethod::MpsF64 => Box::new(MpsStream::new(huffman_decoder(body)?, true)?),
+        BlockMethod::AdaptiveHuffman => {
+            let interval = adaptive_interval(body)?;
+            Box::new(huffman::Decoder::adaptive(&body[4..], interval).ok_or_else(invalid)?)
+        }
+        BlockMethod::Order1Huffman => Box::new(huffman::Decoder::order1(body).ok_or_else(invalid)?),
+        // Whole-chunk transforms: the chunk is held once here
+        BlockMethod::Pipeline => Box::new(pipeline::decode(body)?.into_iter()),
+        BlockMethod::NearLossless => Box::new(decode_near_lossless_block(body)?.into_iter()),
+        BlockMethod::RunLength => Box::new(rle::decode_iter(body.iter().copied())),
+        BlockMethod::Dictionary => Box::new(decode_dictionary_block(body, dictionary)?.into_iter()),
+        BlockMethod::ChunkRef => return Err(chunk_store_required(body)),
+        BlockMethod::CompactHuffman => {
+            Box::new(huffman::Decoder::compact(body, block.raw_len).ok_or_else(invalid)?)
+        }
+        BlockMethod::Schema => Box::new(std::iter::empty()),
+    };
+    let bytes = if merges.is_empty() {
+        bytes
+    } else {
+        Box::new(bpe::decode_iter(bytes, &merges))
+    };
+    let bytes = if block.flags & BLOCK_FLAG_SPARSE != 0 {
+        Box::new(sparse::decode_iter(bytes, inner_len(block, alphabet)).ok_or_else(invalid)?)
+    } else {
+        bytes
+    };
+    let mut bytes = if alphabet.is_empty() {
+        bytes
+    } else {
+        Box::new(remap::decode_iter(bytes, alphabet, block.raw_len))
+    };
+
+    if block.flags & BLOCK_FLAG_SWAP16 != 0 {
+        if !block.raw_len.is_multiple_of(2) {
+            return Err(invalid());
+        }
+        let pairs = std::iter::from_fn(move || Some([bytes.next()?, bytes.next()?]));
+        return Ok(Box::new(pairs.flat_map(|[lo, hi]| [hi, lo])));
+    }
+    Ok(bytes)
+}
+
+/// Streams an MPS or MPS-f64 block body: contracts one window of the chain
+/// at a time and applies the residual as it is decoded
+struct MpsStream<'a> {
+    payload: huffman::Decoder<'a>,
+    mps: MPS,
+    samples: bool,
+    next_window: usize,
+    window: std::vec::IntoIter<u8>,
+}
+
+impl<'a> MpsStream<'a> {
+    /// Read the MPS off the front of `payload`; `samples` selects the
+    /// MPS-f64 residual (XOR of 8-byte bit patterns)
+    fn new(mut payload: huffman::Decoder<'a>, samples: bool) -> Result<Self> {
+        let header: Vec<u8> = payload.by_ref().take(4).collect();
+        let mps_len = read_u32(&header, 0)? as usize;
+        let mps_data: Vec<u8> = payload.by_ref().take(mps_len).collect();
+        let mps = MPS::deserialize(&mps_data).ok_or(CompressionError::DecompressionFailed)?;
+
+        let residual_len = if samples { mps.len.checked_mul(8) } else { Some(mps.len) };
+        if mps_data.len() != mps_len || residual_len != Some(payload.remaining()) {
+            return Err(CompressionError::DecompressionFailed);
+        }
+
+        Ok(MpsStream { payload, mps, samples, next_window: 0, window: Vec::new().into_iter() })
+    }
+
+    /// Reconstruct the next window, or `None` at the end or on a short residual
+    fn decode_window(&mut self) -> Option<Vec<u8>> {
+        let window = self.next_window;
+        self.next_window += 1;
+
+        if self.samples {
+            let approx = self.mps.window_f64(window);
+            let mut out = Vec::with_capacity(8 * approx.len());
+            for a in approx {
+                let mut residual = [0u8; 8];
+                for byte in &mut residual {
+                    *byte = self.payload.next()?;
+                }
+                out.extend_from_slice(&(a.to_bits() ^ u64::from_le_bytes(residual)).to_le_bytes());
+            }
+            Some(out)
+        } else {
+            let approx = self.mps.window_bytes(window);
+            approx.into_iter().map(|a| Some(a.wrapping_add(self.payload.next()?))).collect()
+        }
+    }
+}
+
+impl Iterator for MpsStream<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            if let Some(byte) = self.window.next() {
+                return Some(byte);
+            }
+            if self.next_window == self.mps.window_count() {
+                return None;
+            }
+            self.window = self.decode_window()?.into_iter();
+        }
+    }
+}
+
+/// A framed block inside a container, borrowed from the input
+#[derive(Debug, Clone)]
+pub(crate) struct Block<'a> {
+    /// Position in the container's block list
+    pub index: usize,
+    /// Start of the payload in the compressed input
+    pub offset: usize,
+    pub method: BlockMethod,
+    pub flags: u8,
+    pub raw_len: usize,
+    pub checksum: u32,
+    pub payload: &'a [u8],
+}
+
+/// A container split into its blocks, borrowed from the input
+#[derive(Debug, Clone)]
+pub(crate) struct Container<'a> {
+    pub version: u8,
+    /// CRC32 of the original data (absent before version 3)
+    pub data_checksum: Option<u32>,
+    pub blocks: Vec<Block<'a>>,
+    /// Bytes of the input the container occupies, header included
+    pub len: usize,
+}
+
+/// Validate the container header and split it into blocks.
+///
+/// Single-block (version 1) containers carry no framing, so their block is
+/// decoded once here to learn its length and checksummed on the fly.
+/// Compact containers carry no checksum either and are checksummed the
+/// same way.
+pub(crate) fn parse_container(compressed: &[u8]) -> Result<Container<'_>> {
+    if format::is_compact(compressed) {
+        return parse_compact(compressed);
+    }
+    let (header, body) = Header::parse(compressed)?;
+    let (mut blocks, len) = match header.version {
+        VERSION_SINGLE => (
+            vec![Block {
+                index: 0,
+                offset: 0,
+                method: BlockMethod::Mps,
+                flags: 0,
+                raw_len: decode_mps_block(body)?.len(),
+                checksum: crc32fast::hash(body),
+                payload: body,
+            }],
+            body.len(),
+        ),
+        _ => parse_blocks(body, header.block_count as usize)
+            .map_err(|e| if byte_swapped(&header, body) { CompressionError::ByteSwapped } else { e })?,
+    };
+    // Block offsets were taken relative to the body
+    for block in &mut blocks {
+        block.offset += header.encoded_len();
+    }
+
+    Ok(Container {
+        version: header.version,
+        data_checksum: header.data_checksum,
+        blocks,
+        len: header.encoded_len() + len,
+    })
+}
+
+/// Split a compact container into its one block, which runs to the end of
+/// the input
+fn parse_compact(compressed: &[u8]) -> Result<Container<'_>> {
+    let method = match BlockMethod::from_u8(compressed[0] & METHOD_MASK) {
+        Some(method @ (BlockMethod::Stored | BlockMethod::CompactHuffman)) => method,
+        _ => return Err(CompressionError::DecompressionFailed),
+    };
+    let raw_len = *compressed.get(1).ok_or(CompressionError::DecompressionFailed)? as usize;
+    let payload = &compressed[format::COMPACT_HEADER_LEN..];
+    let block = Block {
+        index: 0,
+        offset: format::COMPACT_HEADER_LEN,
+        method,
+        flags: 0,
+        raw_len,
+        checksum: crc32fast::hash(payload),
+        payload,
+    };
+
+    Ok(Container {
+        version: format::VERSION_COMPACT,
+        data_checksum: None,
+        blocks: vec![block],
+        len: compressed.len(),
+    })
+}
+
+/// Split the `count` framed blocks of a chunked container off the front of
+/// `body`. Also returns where the last block ends; any bytes after it are
+/// not part of the container.
+fn parse_blocks(body: &[u8], count: usize) -> Result<(Vec<Block<'_>>, usize)> {
+    let mut pos = 0;
+    let mut blocks = Vec::with_capacity(count.min(body.len() / BLOCK_HEADER_LEN));
+
+    for index in 0..count {
+        let tag = *body.get(pos).ok_or(CompressionError::DecompressionFailed)?;
+        let method = BlockMethod::from_u8(tag).ok_or(CompressionError::DecompressionFailed)?;
+        let raw_len = read_u32(body, pos + 1)? as usize;
+        let payload_len = read_u32(body, pos + 5)? as usize;
+        let checksum = read_u32(body, pos + 9)?;
+        pos += BLOCK_HEADER_LEN;
+        let payload = body
+            .get(pos..pos + payload_len)
+            .ok_or(CompressionError::DecompressionFailed)?;
+        pos += payload_len;
+
+        let offset = pos - payload_len;
+        blocks.push(Block { index, offset, method, flags: tag & !METHOD_MASK, raw_len, checksum, payload });
+    }
+
+    Ok((blocks, pos))
+}
+
+/// Whether a chunked container body that failed to parse would frame
+/// cleanly with its block count and payload lengths read big-endian, as a
+/// writer that byte-swapped its numeric fields leaves them
+fn byte_swapped(header: &Header, body: &[u8]) -> bool {
+    let count = header.block_count.swap_bytes();
+    let mut pos = 0;
+    for _ in 0..count {
+        let Ok(payload_len) = read_u32(body, pos + 5) else {
+            return false;
+        };
+        pos += BLOCK_HEADER_LEN + payload_len.swap_bytes() as usize;
+        if pos > body.len() {
+            return false;
+        }
+    }
+    count > 0
+}
+
+/// Check a block payload against its framed CRC32, reporting where the
+/// payload sits in the compressed input on mismatch
+pub(crate) fn verify_checksum(block: &Block) -> Result<()> {
+    let actual = crc32fast::hash(block.payload);
+    if actual != block.checksum {
+        let offsets = block.offset..block.offset + block.payload.len();
+        return Err(CompressionError::ChecksumMismatch {
+            scope: ChecksumScope::Block { index: block.index, offsets },
+            expected: block.checksum,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Verify and decode a single block
+pub(crate) fn decode_block(block: &Block, dictionary: Option<&Dictionary>) -> Result<Vec<u8>> {
+    decode_block_with(block, dictionary, None)
+}
+
+/// [`decode_block`], undoing a pipeline block with `stages` (from the
+/// container's schema) in place of the stage list the block records
+fn decode_block_with(block: &Block, dictionary: Option<&Dictionary>, stages: Option<&Pipeline>) -> Result<Vec<u8>> {
+    verify_checksum(block)?;
+    let (alphabet, merges, body) = split_prefixes(block)?;
+
+    let chunk = match block.method {
+        BlockMethod::Stored => body.to_vec(),
+        BlockMethod::Mps => decode_mps_block(body)?,
+        BlockMethod::Huffman => decode_huffman_block(body)?,
+        BlockMethod::MpsF64 => decode_mps_f64_block(body)?,
+        BlockMethod::AdaptiveHuffman => decode_adaptive_block(body)?,
+        BlockMethod::Order1Huffman => {
+            huffman::decode_order1(body).ok_or(CompressionError::DecompressionFailed)?
+        }
+        BlockMethod::Pipeline => match stages {
+            Some(stages) => pipeline::decode_with(body, stages)?,
+            None => pipeline::decode(body)?,
+        },
+        BlockMethod::NearLossless => decode_near_lossless_block(body)?,
+        BlockMethod::RunLength => {
+            rle::decode(body).ok_or(CompressionError::DecompressionFailed)?
+        }
+        BlockMethod::Dictionary => decode_dictionary_block(body, dictionary)?,
+        BlockMethod::ChunkRef => return Err(chunk_store_required(body)),
+        BlockMethod::CompactHuffman => {
+            huffman::decode_compact(body, block.raw_len).ok_or(CompressionError::DecompressionFailed)?
+        }
+        // Describes the container and holds none of its data
+        BlockMethod::Schema => Vec::new(),
+    };
+    let chunk = if merges.is_empty() { chunk } else { bpe::decode(&chunk, &merges) };
+    let chunk = if block.flags & BLOCK_FLAG_SPARSE != 0 {
+        sparse::decode(&chunk, inner_len(block, alphabet)).ok_or(CompressionError::DecompressionFailed)?
+    } else {
+        chunk
+    };
+    let chunk = if alphabet.is_empty() {
+        chunk
+    } else {
+        remap::decode(&chunk, alphabet, block.raw_len).ok_or(CompressionError::DecompressionFailed)?
+    };
+    if chunk.len() != block.raw_len {
+        return Err(CompressionError::DecompressionFailed);
     }
-    
-    let start = Instant::now();
-    
-    // Step 1: MPS tensor decomposition
-    let mps = MPS::from_bytes(data, config.max_rank);
-    let mps_data = mps.serialize();
-    
-    // Step 2: Huffman encoding of MPS data
-    let (huffman_data, table) = huffman::encode(&mps_data);
-    let table_data = table.serialize();
-    
-    // Build output: magic + version + table_len + table + compressed
-    let mut output = Vec::with_capacity(5 + 4 + table_data.len() + huffman_data.len());
-    output.extend_from_slice(MAGIC);
-    output.push(VERSION);
-    output.extend_from_slice(&(table_data.len() as u32).to_le_bytes());
-    output.extend_from_slice(&table_data);
-    output.extend_from_slice(&huffman_data);
-    
-    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
-    
-    let stats = CompressionStats {
-        original_size: data.len(),
-        compressed_size: output.len(),
-        compression_ratio: data.len() as f64 / output.len() as f64,
-        processing_time_ms: elapsed,
-        tensor_rank_used: config.max_rank,
-        vram_peak_bytes: 0, // Would be set by GPU monitor
+
+    if block.flags & BLOCK_FLAG_SWAP16 != 0 {
+        if !chunk.len().is_multiple_of(2) {
+            return Err(CompressionError::DecompressionFailed);
+        }
+        return Ok(swap16(&chunk));
+    }
+    Ok(chunk)
+}
+
+/// Length of a block's chunk before any alphabet remap is undone
+fn inner_len(block: &Block, alphabet: &[u8]) -> usize {
+    if alphabet.is_empty() {
+        block.raw_len
+    } else {
+        remap::encoded_len(block.raw_len, alphabet)
+    }
+}
+
+/// Decode a dictionary block body, which needs the dictionary it names
+fn decode_dictionary_block(body: &[u8], dictionary: Option<&Dictionary>) -> Result<Vec<u8>> {
+    match dictionary {
+        Some(dictionary) => dictionary.decode(body),
+        None => Err(CompressionError::DictionaryRequired { id: dictionary::block_id(body)? }),
+    }
+}
+
+/// The error for a chunk reference read without its store
+fn chunk_store_required(body: &[u8]) -> CompressionError {
+    match store::reference_hash(body) {
+        Ok(hash) => CompressionError::ChunkStoreRequired { hash },
+        Err(e) => e,
+    }
+}
+
+/// Split off the prefixes a block's flags announce: the alphabet of
+/// [`BLOCK_FLAG_REMAP`] (empty when not remapped), then the merge table of
+/// [`BLOCK_FLAG_BPE`]
+fn split_prefixes<'a>(block: &Block<'a>) -> Result<(&'a [u8], Vec<bpe::Merge>, &'a [u8])> {
+    let (alphabet, payload) = if block.flags & BLOCK_FLAG_REMAP != 0 {
+        remap::split_table(block.payload).ok_or(CompressionError::DecompressionFailed)?
+    } else {
+        (&[][..], block.payload)
     };
-    
-    Ok((output, stats))
+    if block.flags & BLOCK_FLAG_BPE == 0 {
+        return Ok((alphabet, Vec::new(), payload));
+    }
+    let (merges, used) = bpe::deserialize_merges(payload)
+        .ok_or(CompressionError::DecompressionFailed)?;
+    Ok((alphabet, merges, &payload[used..]))
 }
 
-/// Decompress data
-pub fn decompress(compressed: &[u8]) -> Result<Vec<u8>> {
-    // Validate magic
-    if compressed.len() < 9 || &compressed[0..4] != MAGIC {
+/// Decode a Huffman block body produced by [`encode_huffman_block`]
+pub(crate) fn decode_huffman_block(block: &[u8]) -> Result<Vec<u8>> {
+    let (table_data, huffman_data) = split_huffman_block(block)?;
+    huffman::decode(huffman_data, table_data).ok_or(CompressionError::DecompressionFailed)
+}
+
+/// Split a Huffman block body into its serialized table and coded data
+fn split_huffman_block(block: &[u8]) -> Result<(&[u8], &[u8])> {
+    let table_len = read_u32(block, 0)? as usize;
+    let table_data = block
+        .get(4..4 + table_len)
+        .ok_or(CompressionError::DecompressionFailed)?;
+    Ok((table_data, &block[4 + table_len..]))
+}
+
+/// Decode an adaptive Huffman block body produced by [`encode_adaptive_block`]
+fn decode_adaptive_block(block: &[u8]) -> Result<Vec<u8>> {
+    let interval = adaptive_interval(block)?;
+    huffman::decode_adaptive(&block[4..], interval).ok_or(CompressionError::DecompressionFailed)
+}
+
+/// Read the rebuild interval leading an adaptive Huffman block body
+fn adaptive_interval(block: &[u8]) -> Result<usize> {
+    match read_u32(block, 0)? {
+        0 => Err(CompressionError::DecompressionFailed),
+        interval => Ok(interval as usize),
+    }
+}
+
+/// Decode an MPS block body produced by [`encode_mps_block`]
+fn decode_mps_block(block: &[u8]) -> Result<Vec<u8>> {
+    decode_mps_residual(&decode_mps_payload(block)?)
+}
+
+/// Undo [`encode_mps_payload`]
+fn decode_mps_payload(block: &[u8]) -> Result<Vec<u8>> {
+    if uncoded_mps_payload(block)? {
+        return Ok(block[4..].to_vec());
+    }
+    decode_huffman_block(block)
+}
+
+/// Whether an MPS block body skipped the Huffman stage
+fn uncoded_mps_payload(block: &[u8]) -> Result<bool> {
+    Ok(read_u32(block, 0)? == UNCODED_TABLE_LEN)
+}
+
+/// Rebuild bytes from a payload produced by [`encode_mps_residual`]
+pub(crate) fn decode_mps_residual(payload: &[u8]) -> Result<Vec<u8>> {
+    let mps_len = read_u32(payload, 0)? as usize;
+    let mps_data = payload
+        .get(4..4 + mps_len)
+        .ok_or(CompressionError::DecompressionFailed)?;
+    let residual = &payload[4 + mps_len..];
+
+    // Reconstruct MPS and apply residual
+    let mps = MPS::deserialize(mps_data)
+        .ok_or(CompressionError::DecompressionFailed)?;
+    let approx = mps.to_bytes();
+    if approx.len() != residual.len() {
         return Err(CompressionError::DecompressionFailed);
     }
-    
-    let version = compressed[4];
-    if version != VERSION {
+
+    Ok(approx
+        .iter()
+        .zip(residual)
+        .map(|(&a, &r)| a.wrapping_add(r))
+        .collect())
+}
+
+/// Decode a near-lossless block body produced by [`encode_near_lossless_block`]
+fn decode_near_lossless_block(block: &[u8]) -> Result<Vec<u8>> {
+    let payload = decode_huffman_block(block)?;
+    let near = *payload.first().ok_or(CompressionError::DecompressionFailed)?;
+    if near == 0 || near > lossy::MAX_NEAR {
         return Err(CompressionError::DecompressionFailed);
     }
-    
-    // Extract table
-    let table_len = u32::from_le_bytes(
-        compressed[5..9].try_into().map_err(|_| CompressionError::DecompressionFailed)?
-    ) as usize;
-    
-    if compressed.len() < 9 + table_len {
+    let mps_len = read_u32(&payload, 1)? as usize;
+    let mps_data = payload
+        .get(5..5 + mps_len)
+        .ok_or(CompressionError::DecompressionFailed)?;
+    let residual = &payload[5 + mps_len..];
+
+    let approx = if mps_len == 0 {
+        vec![0; residual.len()]
+    } else {
+        MPS::deserialize(mps_data)
+            .ok_or(CompressionError::DecompressionFailed)?
+            .to_bytes()
+    };
+    if approx.len() != residual.len() {
         return Err(CompressionError::DecompressionFailed);
     }
-    
-    let table_data = &compressed[9..9 + table_len];
-    let huffman_data = &compressed[9 + table_len..];
-    
-    // Decode Huffman
-    let mps_data = huffman::decode(huffman_data, table_data)
+    Ok(lossy::dequantize_residual(&approx, residual, near))
+}
+
+/// Decode an MPS-f64 block body produced by [`encode_mps_f64_block`]
+fn decode_mps_f64_block(block: &[u8]) -> Result<Vec<u8>> {
+    let payload = decode_mps_payload(block)?;
+    let mps_len = read_u32(&payload, 0)? as usize;
+    let mps_data = payload
+        .get(4..4 + mps_len)
         .ok_or(CompressionError::DecompressionFailed)?;
-    
-    // Reconstruct MPS
-    let mps = MPS::deserialize(&mps_data)
+    let residual = &payload[4 + mps_len..];
+
+    let mps = MPS::deserialize(mps_data)
         .ok_or(CompressionError::DecompressionFailed)?;
-    
-    Ok(mps.to_bytes())
+    let approx = mps.to_f64();
+    if approx.len() * 8 != residual.len() {
+        return Err(CompressionError::DecompressionFailed);
+    }
+
+    Ok(approx
+        .iter()
+        .zip(residual.chunks_exact(8))
+        .flat_map(|(a, r)| (a.to_bits() ^ u64::from_le_bytes(r.try_into().unwrap())).to_le_bytes())
+        .collect())
+}
+
+/// Decompress a container produced by [`compress_f64`]
+pub fn decompress_f64(compressed: &[u8]) -> Result<Vec<f64>> {
+    let bytes = decompress(compressed)?;
+    if bytes.len() % 8 != 0 {
+        return Err(CompressionError::DecompressionFailed);
+    }
+    Ok(bytes
+        .chunks_exact(8)
+        .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+        .collect())
+}
+
+/// Decompress a container produced by [`compress_u16`], honouring the byte
+/// order recorded in each block
+pub fn decompress_u16(compressed: &[u8]) -> Result<Vec<u16>> {
+    let container = parse_container(compressed)?;
+    let bytes = decode_container(&container, None)?;
+
+    let mut values = Vec::with_capacity(bytes.len() / 2);
+    let mut offset = 0;
+    for block in &container.blocks {
+        let chunk = &bytes[offset..offset + block.raw_len];
+        offset += block.raw_len;
+        if !chunk.len().is_multiple_of(2) {
+            return Err(CompressionError::DecompressionFailed);
+        }
+
+        let from_bytes = if block.flags & BLOCK_FLAG_SWAP16 != 0 {
+            u16::from_be_bytes
+        } else {
+            u16::from_le_bytes
+        };
+        values.extend(chunk.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])));
+    }
+
+    Ok(values)
+}
+
+/// Read a little-endian `u32` at `pos`
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or(CompressionError::DecompressionFailed)
+}
+
+/// Passes [`benchmark_vs_zlib`] times after its warm-up
+const BENCHMARK_RUNS: usize = 3;
+
+/// [`compress`] with steady-state timing for benchmarks.
+///
+/// A pool for `config.threads` is spawned once up front rather than per
+/// call, and one untimed warm-up pass absorbs the remaining first-call
+/// costs (allocator growth, cold caches). Then `runs` passes (at least one)
+/// are timed and the fastest is reported in `processing_time_ms`.
+pub fn compress_timed(data: &[u8], config: &Config, runs: usize) -> Result<(Vec<u8>, CompressionStats)> {
+    compress_timed_runs(data, config, runs).map(|(output, stats, _)| (output, stats))
+}
+
+/// [`compress_timed`], also returning every timed pass's time in order
+fn compress_timed_runs(data: &[u8], config: &Config, runs: usize) -> Result<(Vec<u8>, CompressionStats, Vec<f64>)> {
+    let pooled = Config { threads: 0, ..config.clone() };
+    let measure = || {
+        compress(data, &pooled)?;
+
+        let (output, mut stats) = compress(data, &pooled)?;
+        let mut times = vec![stats.processing_time_ms];
+        for _ in 1..runs {
+            let (_, run) = compress(data, &pooled)?;
+            times.push(run.processing_time_ms);
+            stats.processing_time_ms = stats.processing_time_ms.min(run.processing_time_ms);
+        }
+        Ok((output, stats, times))
+    };
+    match thread_pool(config.threads) {
+        Some(pool) => pool.install(measure),
+        None => measure(),
+    }
 }
 
 /// Compare our compression to zlib
@@ -94,16 +2317,12 @@ pub fn benchmark_vs_zlib(data: &[u8]) -> (CompressionStats, f64, f64) {
     let config = Config::default();
     
     // Our compression
-    let start = Instant::now();
-    let (our_compressed, our_stats) = compress(data, &config).unwrap();
-    let our_time = start.elapsed().as_secs_f64() * 1000.0;
+    let (_, our_stats) = compress_timed(data, &config, BENCHMARK_RUNS).unwrap();
     
     // Zlib compression
-    let start = Instant::now();
     let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
     encoder.write_all(data).unwrap();
     let zlib_compressed = encoder.finish().unwrap();
-    let zlib_time = start.elapsed().as_secs_f64() * 1000.0;
     
     let zlib_ratio = data.len() as f64 / zlib_compressed.len() as f64;
     let improvement = our_stats.compression_ratio / zlib_ratio;
@@ -114,6 +2333,8 @@ pub fn benchmark_vs_zlib(data: &[u8]) -> (CompressionStats, f64, f64) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::simd::{self, SimdLevel};
+    use crate::testing::CorpusKind;
     
     #[test]
     fn test_compress_decompress() {
@@ -123,7 +2344,774 @@ mod tests {
         let (compressed, stats) = compress(data, &config).unwrap();
         println!("Compression ratio: {:.2}", stats.compression_ratio);
         
-        // Decompression would work with full implementation
-        assert!(compressed.len() > 0);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_compress_with_matches_compress() {
+        let data: Vec<u8> = (0..5000).map(|i| ((i * 3) % 200) as u8).collect();
+        let config = Config { chunk_size: 2048, ..Default::default() };
+        let (expected, _) = compress(&data, &config).unwrap();
+
+        // Second call reuses the thread's buffer
+        for _ in 0..2 {
+            let (received, stats) = compress_with(&data, &config, |bytes| bytes.to_vec()).unwrap();
+            assert_eq!(received, expected);
+            assert_eq!(stats.compressed_size, expected.len());
+        }
+
+        for len in [10, 100] {
+            let received = compress_with(&data[..len], &config, |bytes| bytes.len()).unwrap().0;
+            assert_eq!(received, compress(&data[..len], &config).unwrap().0.len());
+        }
+    }
+
+    #[test]
+    fn test_thread_count_does_not_change_output() {
+        let data: Vec<u8> = (0..20000).map(|i| ((i * 7) % 61) as u8).collect();
+        let config = Config { chunk_size: 4096, ..Config::default() };
+        let (expected, _) = compress(&data, &config).unwrap();
+        for threads in [1, 3] {
+            let (output, _) = compress(&data, &Config { threads, ..config.clone() }).unwrap();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn test_pinned_format_version_layout() {
+        let data: Vec<u8> = (0..6000).map(|i| ((i * 7) % 61) as u8).collect();
+        let v1 = Config { format_version: 1, chunk_size: 4096, ..Config::default() };
+        let (compressed, _) = compress(&data, &v1).unwrap();
+        assert_eq!(&compressed[..5], b"QCMP\x01");
+        assert_eq!(compressed, compress(&data, &v1).unwrap().0);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        // Unframed body: one Huffman-coded (mps_len + MPS + residual) block
+        // covering the whole input, chunk_size notwithstanding
+        let (table_data, _) = split_huffman_block(&compressed[5..]).unwrap();
+        assert_eq!(read_u32(&compressed, 5).unwrap() as usize, table_data.len());
+        let inner = decode_huffman_block(&compressed[5..]).unwrap();
+        let mps_len = read_u32(&inner, 0).unwrap() as usize;
+        assert_eq!(inner.len(), 4 + mps_len + data.len());
+
+        let v2 = Config { format_version: 2, ..v1.clone() };
+        let (compressed, _) = compress(&data, &v2).unwrap();
+        assert_eq!(compressed[4], 2);
+        assert_eq!(read_u32(&compressed, 5).unwrap(), 2);
+        assert_eq!(parse_container(&compressed).unwrap().blocks[0].raw_len, 4096);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        // Settings the pinned layout cannot carry are refused, not upgraded
+        let bpe = Config { bpe_merges: 16, ..v1.clone() };
+        assert!(matches!(compress(&data, &bpe), Err(CompressionError::FormatVersion { version: 1, .. })));
+        assert!(compress_f64(&[0.5; 64], &v1).is_err());
+        let future = Config { format_version: VERSION + 1, ..Config::default() };
+        assert!(matches!(compress(&data, &future), Err(CompressionError::FormatVersion { .. })));
+    }
+
+    #[test]
+    fn test_cached_huffman_tables_match_fresh() {
+        let cache = huffman::HuffmanTableCache::new(8);
+        let cached = Config { huffman_table_cache: Some(cache.clone()), ..Config::default() };
+        let messages: Vec<Vec<u8>> = (0..3)
+            .map(|m| (0..2000).map(|i| ((i * 7 + m) % 23) as u8).collect())
+            .collect();
+
+        for message in messages.iter().chain(&messages) {
+            let (fresh, _) = compress(message, &Config::default()).unwrap();
+            let (output, _) = compress(message, &cached).unwrap();
+            assert_eq!(output, fresh);
+        }
+        // Every second pass reuses the tables of the first
+        assert!(cache.hits() >= messages.len() as u64);
+        assert!(cache.len() <= 8);
+
+        // The least recently used histogram is evicted first
+        let freq = |n: u64| std::array::from_fn(|i| if i < 3 { n + i as u64 } else { 0 });
+        let small = huffman::HuffmanTableCache::new(2);
+        let first = small.get_or_build(&freq(1));
+        small.get_or_build(&freq(2));
+        small.get_or_build(&freq(1));
+        small.get_or_build(&freq(3));
+        assert!(std::sync::Arc::ptr_eq(&first, &small.get_or_build(&freq(1))));
+        assert_eq!(small.hits(), 2);
+        small.get_or_build(&freq(2));
+        assert_eq!(small.hits(), 2);
+    }
+
+    #[test]
+    fn test_streamed_pieces_form_container() {
+        let mut data = b"u3(0.1, 0.2, 0.3) q4; barrier q; ".repeat(300);
+        data.extend(CorpusKind::Random.generate(1, 3000));
+        let config = Config { chunk_size: 1024, threads: 4, ..Config::default() };
+        let (expected, expected_stats) = compress(&data, &config).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let stats = compress_streaming(&data, &config, tx).unwrap();
+        let pieces: Vec<Vec<u8>> = rx.iter().collect();
+        let blocks = parse_container(&expected).unwrap().blocks;
+        assert_eq!(pieces.len(), 1 + blocks.len());
+        for (piece, block) in pieces[1..].iter().zip(&blocks) {
+            assert_eq!(piece.len(), BLOCK_HEADER_LEN + block.payload.len());
+        }
+        assert_eq!(pieces.concat(), expected);
+        assert_eq!(stats.compressed_size, expected.len());
+        assert_eq!(stats.per_strategy, expected_stats.per_strategy);
+
+        // On error the channel closes with nothing sent
+        let (tx, rx) = mpsc::channel();
+        let pinned = Config { format_version: VERSION_CHUNKED, ..config.clone() };
+        assert!(compress_streaming(&data[..10], &pinned, tx).is_err());
+        assert!(rx.recv().is_err());
+
+        // A receiver that hangs up stops the compressor
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+        assert!(matches!(compress_streaming(&data, &config, tx), Err(CompressionError::Io(_))));
+    }
+
+    #[test]
+    fn test_simd_threshold_keeps_small_inputs_scalar() {
+        let tiny = b"sx q5; ".repeat(40);
+        let config = Config { simd_threshold: 4096, ..Config::default() };
+        let simd_config = Config { simd_threshold: 0, ..Config::default() };
+
+        let scalar = encode_huffman_block(&tiny, &config);
+        assert_eq!(simd::last_level(), Some(SimdLevel::Scalar));
+        let vectorized = encode_huffman_block(&tiny, &simd_config);
+        assert_eq!(simd::last_level(), Some(simd::detect()));
+        assert_eq!(scalar, vectorized);
+
+        let big = b"sx q5; ".repeat(1000);
+        encode_huffman_block(&big, &config);
+        assert_eq!(simd::last_level(), Some(simd::detect()));
+        assert_eq!(compress(&tiny, &config).unwrap().0, compress(&tiny, &simd_config).unwrap().0);
+    }
+
+    #[test]
+    fn test_min_block_size_coalesces_tiny_chunks() {
+        let data = b"cx q0 q1; rz(0.25) q1; ".repeat(300);
+        let tiny = Config { chunk_size: 64, ..Config::default() };
+        let config = Config { min_block_size: 1000, ..tiny.clone() };
+        let (fragmented, _) = compress(&data, &tiny).unwrap();
+        let (compressed, stats) = compress(&data, &config).unwrap();
+
+        let blocks = parse_container(&compressed).unwrap().blocks;
+        assert!(blocks.len() > 1);
+        assert!(blocks.len() < parse_container(&fragmented).unwrap().blocks.len());
+        assert!(blocks.iter().all(|b| b.raw_len >= 1000));
+        assert!(compressed.len() < fragmented.len());
+        assert_eq!(stats.original_size, data.len());
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        // Merging keeps existing boundaries: 64-byte chunks pair up evenly
+        let ranges = coalesce_chunks(plan_chunks(640, 64), 100);
+        assert_eq!(ranges, [0..128, 128..256, 256..384, 384..512, 512..640]);
+        assert_eq!(coalesce_chunks(plan_chunks(640, 64), 1000).len(), 1);
+    }
+
+    #[test]
+    fn test_plan_chunks_merges_tail() {
+        let ranges = plan_chunks(4 * 1024 + 30, 1024);
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges.last().unwrap(), &(3072..4126));
+        assert!(ranges.iter().all(|r| r.len() >= MIN_INPUT_SIZE));
+    }
+
+    #[test]
+    fn test_inspect_matches_decompress_stats() {
+        let data: Vec<u8> = (0..10_000).map(|i| ((i * 7) % 251) as u8).collect();
+        let config = Config { chunk_size: 4096, ..Default::default() };
+        let (mut compressed, _) = compress(&data, &config).unwrap();
+
+        let (output, full) = decompress_with_stats(&compressed).unwrap();
+        let inspected = inspect(&compressed).unwrap();
+        assert_eq!(output, data);
+        assert_eq!(inspected, full);
+        assert_eq!(inspected.block_count, 3);
+        assert_eq!(inspected.decompressed_size, data.len());
+
+        // Corrupt the last payload byte: inspect must catch it without decoding
+        *compressed.last_mut().unwrap() ^= 0xFF;
+        assert!(inspect(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_validate_roundtrip_catches_lossy_encoder() {
+        // Drops the low bit of every byte, framed as if it were lossless
+        fn lossy_encoder(
+            chunk: &[u8],
+            _: &Config,
+            _: Option<&Dictionary>,
+            _: &mut FrequencyCounter,
+        ) -> Result<EncodedBlock> {
+            let payload = chunk.iter().map(|b| b & !1).collect();
+            Ok(EncodedBlock { payload, ..EncodedBlock::stored(&[]) })
+        }
+
+        let data: Vec<u8> = (0..3000).map(|i| (i % 251) as u8).collect();
+        let paranoid = Config { validate_roundtrip: true, ..Default::default() };
+        assert!(matches!(
+            compress_chunks(&data, &paranoid, 1, lossy_encoder),
+            Err(CompressionError::RoundtripVerificationFailed)
+        ));
+
+        // Unchecked, the damage only shows up at decompression time
+        let (unchecked, _) = compress_chunks(&data, &Config::default(), 1, lossy_encoder).unwrap();
+        assert!(decompress(&unchecked).is_err());
+
+        let (compressed, _) = compress(&data, &paranoid).unwrap();
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_seal_roundtrip_and_corruption() {
+        let data = CorpusKind::Random.generate(2, 5000);
+        let sealed = seal(&data);
+        assert_eq!(sealed.len(), 13 + BLOCK_HEADER_LEN + data.len());
+        assert_eq!(unseal(&sealed).unwrap(), data);
+        assert_eq!(decompress(&sealed).unwrap(), data);
+        assert_eq!(unseal(&seal(b"tiny")).unwrap(), b"tiny");
+        assert!(unseal(&seal(&[])).unwrap().is_empty());
+
+        let mut corrupt = sealed.clone();
+        corrupt[13 + BLOCK_HEADER_LEN + 100] ^= 1;
+        assert!(matches!(
+            unseal(&corrupt),
+            Err(CompressionError::ChecksumMismatch { scope: ChecksumScope::Block { index: 0, .. }, .. })
+        ));
+
+        // Compressed containers are not sealed ones
+        let text = b"gate H q0; measure q0; ".repeat(50);
+        assert!(unseal(&compress(&text, &Config::default()).unwrap().0).is_err());
+    }
+
+    /// Cheap chunks on a dedicated pool: a cold call spends much of its
+    /// time spawning workers
+    fn timing_input() -> (Vec<u8>, Config) {
+        let data: Vec<u8> = (0..4096u32).map(|i| ((i as f64 * 0.01).sin() * 100.0) as u8 ^ (i % 7) as u8).collect();
+        let pipeline = Some(pipeline::Pipeline::new().delta().huffman());
+        (data, Config { chunk_size: 1024, threads: 8, pipeline, ..Config::default() })
+    }
+
+    #[test]
+    fn test_timed_reports_fastest_run() {
+        let (data, config) = timing_input();
+        let (output, stats, times) = compress_timed_runs(&data, &config, 5).unwrap();
+        assert_eq!(output, compress(&data, &config).unwrap().0);
+        assert_eq!(times.len(), 5);
+        assert!(stats.processing_time_ms > 0.0);
+        assert!(times.iter().all(|&time| stats.processing_time_ms <= time));
+        assert!(times.contains(&stats.processing_time_ms));
+
+        // At least one pass is timed
+        assert_eq!(compress_timed_runs(&data, &config, 0).unwrap().2.len(), 1);
+    }
+
+    #[test]
+    fn test_warm_timing_not_slower_than_cold() {
+        // Fastest of several runs each, so one descheduled run cannot flip it
+        let (data, config) = timing_input();
+        let cold = (0..10)
+            .map(|_| compress(&data, &config).unwrap().1.processing_time_ms)
+            .fold(f64::INFINITY, f64::min);
+        let (_, warm) = compress_timed(&data, &config, 10).unwrap();
+        assert!(warm.processing_time_ms <= cold, "warm {} ms vs cold {cold} ms", warm.processing_time_ms);
+    }
+
+    #[test]
+    fn test_data_checksum_catches_valid_looking_corruption() {
+        let data = CorpusKind::Random.generate(3, 2000);
+        let (mut compressed, _) = compress(&data, &Config::default()).unwrap();
+
+        // Single stored block: flip a payload byte and re-seal its block CRC
+        let frame = 13;
+        assert_eq!(compressed[frame], BlockMethod::Stored as u8);
+        let payload = frame + BLOCK_HEADER_LEN;
+        compressed[payload + 100] ^= 0x5A;
+        let crc = crc32fast::hash(&compressed[payload..]);
+        compressed[frame + 9..frame + 13].copy_from_slice(&crc.to_le_bytes());
+
+        assert!(inspect(&compressed).is_ok());
+        assert!(matches!(
+            decompress(&compressed),
+            Err(CompressionError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            decompress_reader(&mut &compressed[..]),
+            Err(CompressionError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_block_checksum_error_locates_corruption() {
+        let data: Vec<u8> = (0..12_000).map(|i| ((i * 7) % 61) as u8).collect();
+        let config = Config { chunk_size: 4096, ..Config::default() };
+        let (mut compressed, _) = compress(&data, &config).unwrap();
+
+        let target = parse_container(&compressed).unwrap().blocks[2].offset + 10;
+        compressed[target] ^= 0xFF;
+
+        for result in [
+            decompress(&compressed).map(|_| ()),
+            decompress_reader(&mut &compressed[..]).map(|_| ()),
+            inspect(&compressed).map(|_| ()),
+        ] {
+            match result {
+                Err(CompressionError::ChecksumMismatch {
+                    scope: ChecksumScope::Block { index, offsets },
+                    ..
+                }) => {
+                    assert_eq!(index, 2);
+                    assert!(offsets.contains(&target));
+                    assert_eq!(offsets.end, compressed.len());
+                }
+                other => panic!("expected a block checksum error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse_chunks_beat_dense_path() {
+        // 95% zeros, scattered nonzero bytes
+        let data: Vec<u8> = (0..16384u32)
+            .map(|i| {
+                let h = i.wrapping_mul(2654435761);
+                if h % 20 == 0 { (h >> 24) as u8 | 1 } else { 0 }
+            })
+            .collect();
+        let dense_config = Config { sparse_threshold: 2.0, ..Config::default() };
+        let (dense, _) = compress(&data, &dense_config).unwrap();
+        let (compressed, stats) = compress(&data, &Config::default()).unwrap();
+
+        let block = &parse_container(&compressed).unwrap().blocks[0];
+        assert_ne!(block.flags & BLOCK_FLAG_SPARSE, 0);
+        assert!(dense.len() as f64 / compressed.len() as f64 > 1.5);
+        assert!(stats.compression_ratio > 10.0);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        let mut streamed = Vec::new();
+        let small = Config { max_chunk_memory: 1000, ..Config::default() };
+        decompress_to_writer(&compressed, &mut streamed, &small).unwrap();
+        assert_eq!(streamed, data);
+    }
+
+    #[test]
+    fn test_symbol_remap_shrinks_small_alphabets() {
+        // A slow sine quantized to 10 widely spaced levels
+        let levels = [3u8, 29, 55, 81, 107, 133, 159, 185, 211, 237];
+        let data: Vec<u8> = (0..20000)
+            .map(|i| levels[(((i as f64 * 0.02).sin() * 0.5 + 0.5) * 9.99) as usize])
+            .collect();
+        assert_eq!(remap::alphabet(&data).len(), 10);
+
+        let (plain, _) = compress(&data, &Config::default()).unwrap();
+        let remap_config = Config { symbol_remap: true, ..Config::default() };
+        let (compressed, stats) = compress(&data, &remap_config).unwrap();
+        assert!(parse_container(&compressed).unwrap().blocks.iter().all(|b| b.flags & BLOCK_FLAG_REMAP != 0));
+        assert!(stats.compression_ratio > 1.3 * data.len() as f64 / plain.len() as f64);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        let mut streamed = Vec::new();
+        let small = Config { max_chunk_memory: 1000, ..Config::default() };
+        decompress_to_writer(&compressed, &mut streamed, &small).unwrap();
+        assert_eq!(streamed, data);
+    }
+
+    #[test]
+    fn test_extreme_skew_codes_runs_near_entropy() {
+        // 99.9% one byte: 100 scattered outliers in 100 000 bytes
+        let mut data = vec![0x55u8; 100_000];
+        for k in 0..100u32 {
+            let pos = (k.wrapping_mul(2654435761) % 100_000) as usize;
+            data[pos] = (k % 200) as u8;
+        }
+        let dominant = data.iter().filter(|&&b| b == 0x55).count() as f64 / data.len() as f64;
+        assert!(dominant >= 0.999);
+
+        let (compressed, _) = compress(&data, &Config::default()).unwrap();
+        let block = &parse_container(&compressed).unwrap().blocks[0];
+        assert_eq!(block.method, BlockMethod::RunLength);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        let mut streamed = Vec::new();
+        decompress_to_writer(&compressed, &mut streamed, &Config { max_chunk_memory: 4096, ..Config::default() })
+            .unwrap();
+        assert_eq!(streamed, data);
+
+        // Order-0 entropy in bytes, against Huffman's floor of a bit per symbol
+        let mut counts = [0usize; 256];
+        data.iter().for_each(|&b| counts[b as usize] += 1);
+        let entropy_bits: f64 = counts
+            .iter()
+            .filter(|&&c| c > 0)
+            .map(|&c| c as f64 * (data.len() as f64 / c as f64).log2())
+            .sum();
+        let huffman = encode_huffman_block(&data, &Config::default());
+        assert!(huffman.len() >= data.len() / 8);
+        assert!((block.payload.len() as f64) < 2.5 * entropy_bits / 8.0);
+    }
+
+    #[test]
+    fn test_entropy_efficiency_measures_headroom() {
+        // Noise leaves nothing above its order-0 bound to take
+        let noise = CorpusKind::Random.generate(4, 8192);
+        let (_, stats) = compress(&noise, &Config::default()).unwrap();
+        assert!(stats.stored_fallback);
+        assert!((0.95..=1.0).contains(&stats.entropy_efficiency), "{}", stats.entropy_efficiency);
+
+        // A ramp has the same flat histogram, so the same order-0 bound,
+        // but the MPS captures its structure and beats the bound
+        let ramp: Vec<u8> = (0..8192u32).map(|i| i as u8).collect();
+        let (_, stats) = compress(&ramp, &Config::default()).unwrap();
+        assert!(stats.per_strategy.contains_key(&BlockMethod::Mps));
+        assert!(stats.entropy_efficiency > 2.0, "{}", stats.entropy_efficiency);
+    }
+
+    #[test]
+    fn test_per_strategy_counts_cover_chunks() {
+        // Compressible text, incompressible noise and a near-constant run
+        let mut data = b"gate H q0; gate CNOT q0 q1; measure q1; ".repeat(200);
+        data.extend(CorpusKind::Random.generate(5, 8000));
+        data.extend(std::iter::repeat_n(7u8, 8000));
+        let config = Config { chunk_size: 4096, ..Config::default() };
+        let (compressed, stats) = compress(&data, &config).unwrap();
+
+        let blocks = parse_container(&compressed).unwrap().blocks;
+        let chunks: usize = stats.per_strategy.values().map(|s| s.chunks).sum();
+        assert_eq!(chunks, blocks.len());
+        assert!(stats.per_strategy.len() >= 2);
+        assert!(stats.per_strategy.contains_key(&BlockMethod::Stored));
+
+        let original: usize = stats.per_strategy.values().map(|s| s.original_size).sum();
+        let framed: usize = stats.per_strategy.values().map(|s| s.compressed_size).sum();
+        assert_eq!(original, data.len());
+        assert_eq!(framed + 13, compressed.len());
+        let stored = &stats.per_strategy[&BlockMethod::Stored];
+        assert!(stored.average_ratio < 1.0);
+    }
+
+    #[test]
+    fn test_mps_serialized_size_reported() {
+        // Version 1 always codes one MPS block: magic + version + body
+        let data: Vec<u8> = (0..4096u32).map(|i| (i / 16) as u8).collect();
+        let config = Config { format_version: VERSION_SINGLE, ..Config::default() };
+        let (compressed, stats) = compress(&data, &config).unwrap();
+
+        // Chain + one residual byte per input byte, which Huffman shrinks
+        assert!(stats.mps_serialized_size > data.len());
+        assert!(stats.compressed_size < stats.mps_serialized_size);
+        assert_eq!(stats.compressed_size, compressed.len());
+
+        // Nothing MPS-coded, nothing reported
+        let text = b"measure q0; ".repeat(400);
+        let (_, stats) = compress(&text, &Config::default()).unwrap();
+        assert!(!stats.per_strategy.contains_key(&BlockMethod::Mps));
+        assert_eq!(stats.mps_serialized_size, 0);
+    }
+
+    #[test]
+    fn test_trained_dictionary_helps_small_messages() {
+        let message = |i: u32| {
+            format!(
+                "circuit run {i}: gate H q{}; gate CNOT q{} q{}; measure q{} -> c{}; shots {}\n",
+                i % 7,
+                i % 5,
+                (i + 1) % 5,
+                i % 3,
+                i % 3,
+                100 + i * 13
+            )
+            .into_bytes()
+        };
+        let samples: Vec<Vec<u8>> = (0..100).map(message).collect();
+        let refs: Vec<&[u8]> = samples.iter().map(Vec::as_slice).collect();
+        let dict = crate::train_dictionary(&refs, 1024);
+        assert!(dict.len() <= 1024);
+
+        let held_out = message(1000);
+        let plain = compress(&held_out, &Config::default()).unwrap().0;
+        let config = Config { dictionary: Some(dict.clone()), ..Config::default() };
+        let (with_dict, stats) = compress(&held_out, &config).unwrap();
+        assert!(with_dict.len() < plain.len());
+        assert!(stats.compression_ratio > 1.0);
+        assert_eq!(parse_container(&with_dict).unwrap().blocks[0].method, BlockMethod::Dictionary);
+
+        assert_eq!(decompress_with_dictionary(&with_dict, &dict).unwrap(), held_out);
+        let mut streamed = Vec::new();
+        decompress_to_writer(&with_dict, &mut streamed, &config).unwrap();
+        assert_eq!(streamed, held_out);
+
+        let id = crc32fast::hash(&dict);
+        assert!(matches!(decompress(&with_dict), Err(CompressionError::DictionaryRequired { id: i }) if i == id));
+        let other = crate::train_dictionary(&refs[..10], 512);
+        assert!(matches!(
+            decompress_with_dictionary(&with_dict, &other),
+            Err(CompressionError::DictionaryRequired { .. })
+        ));
+        let broken = Config { dictionary: Some(b"QCDT".to_vec()), ..Config::default() };
+        assert!(matches!(compress(&held_out, &broken), Err(CompressionError::InvalidDictionary)));
+    }
+
+    #[test]
+    fn test_streamed_blocks_match_whole_decode() {
+        // Two MPS contraction windows
+        let ramp: Vec<u8> = (0..8192).map(|i| (i / 32) as u8).collect();
+        let noise = CorpusKind::Random.generate(6, 3000);
+        let text = b"measure q0; gate H q1; gate CNOT q1 q2; ".repeat(150);
+        let samples: Vec<f64> = (0..4096).map(|i| (i as f64 * 0.01).sin()).collect();
+
+        let order1 = Config { entropy_model: EntropyModel::Order1, ..Config::default() };
+        let bpe = Config { bpe_merges: 16, huffman_rebuild_interval: 0, ..Config::default() };
+        let big_endian = Config { sample_endian: Endian::Big, ..Config::default() };
+        let containers = [
+            compress(&ramp, &Config::default()).unwrap().0,
+            compress(&noise, &Config::default()).unwrap().0,
+            compress(&text, &Config::default()).unwrap().0,
+            compress(&text, &order1).unwrap().0,
+            compress(&text, &bpe).unwrap().0,
+            compress_u16(&ramp, &big_endian).unwrap().0,
+            compress_f64(&samples, &Config { max_rank: 4, ..Config::default() }).unwrap().0,
+        ];
+
+        // Every method is exercised through the streaming path
+        let mut methods: Vec<u8> = containers
+            .iter()
+            .flat_map(|c| parse_container(c).unwrap().blocks)
+            .map(|b| b.method as u8)
+            .collect();
+        methods.sort_unstable();
+        methods.dedup();
+        assert_eq!(methods, [0, 1, 2, 3, 4, 5]);
+
+        let tight = Config { max_chunk_memory: 100, ..Config::default() };
+        for compressed in &containers {
+            let mut streamed = Vec::new();
+            let written = decompress_to_writer(compressed, &mut streamed, &tight).unwrap();
+            assert_eq!(written as usize, streamed.len());
+            assert_eq!(streamed, decompress(compressed).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_tiny_input_uses_compact_header() {
+        let data = b"h q0; cx q0 q1; rz(0.25) q1; measure q1;";
+        assert_eq!(data.len(), 40);
+        let (compressed, stats) = compress(data, &Config::default()).unwrap();
+        assert!(format::is_compact(&compressed));
+        assert!(compressed.len() <= format::COMPACT_HEADER_LEN + data.len());
+        assert_eq!(stats.compressed_size, compressed.len());
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        assert_eq!(decompress_from(&mut compressed.as_slice()).unwrap(), data);
+        assert_eq!(inspect(&compressed).unwrap().version, format::VERSION_COMPACT);
+
+        // Skewed tiny inputs can still be Huffman coded; empty ones are stored
+        let skewed = b"aaaaaaaaaabaaaaaaaaaaaaaaaaaaaacaaaaaaaa";
+        let (compressed, _) = compress(skewed, &Config::default()).unwrap();
+        assert_eq!(compressed[0], format::COMPACT_TAG | BlockMethod::CompactHuffman as u8);
+        assert!(compressed.len() < 20);
+        assert_eq!(decompress(&compressed).unwrap(), skewed);
+        assert_eq!(compress(&[], &Config::default()).unwrap().0, [format::COMPACT_TAG, 0]);
+        assert_eq!(decompress(&[format::COMPACT_TAG, 0]).unwrap(), b"");
+
+        // Only the current layout has a compact form
+        let pinned = Config { format_version: VERSION_CHUNKED, ..Config::default() };
+        let err = compress(data, &pinned).unwrap_err();
+        assert!(matches!(err, CompressionError::InputTooSmall(40)));
+        assert!(err.to_string().ends_with("without a compact container need 64)"));
+    }
+
+    #[test]
+    fn test_stored_container_decompresses_borrowed() {
+        let noise = CorpusKind::Random.generate(7, 3000);
+        let (compressed, stats) = compress(&noise, &Config::default()).unwrap();
+        assert!(stats.stored_fallback);
+        match decompress_borrowed(&compressed).unwrap() {
+            Cow::Borrowed(bytes) => {
+                assert_eq!(bytes, noise);
+                assert!(compressed.as_ptr_range().contains(&bytes.as_ptr()));
+            }
+            Cow::Owned(_) => panic!("stored block was copied"),
+        }
+
+        // Coded or multi-block containers come back owned
+        let text = b"cx q0 q1; ".repeat(300);
+        let (coded, _) = compress(&text, &Config::default()).unwrap();
+        assert!(matches!(decompress_borrowed(&coded).unwrap(), Cow::Owned(bytes) if bytes == text));
+        let chunked = Config { chunk_size: 1024, ..Config::default() };
+        let (split, _) = compress(&noise, &chunked).unwrap();
+        assert!(matches!(decompress_borrowed(&split).unwrap(), Cow::Owned(bytes) if bytes == noise));
+
+        // The checksums still guard the borrowed path
+        let mut corrupt = compressed.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(decompress_borrowed(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_segments_go_to_their_writers() {
+        let header = b"OPENQASM 3.0; include \"stdgates.inc\"; qubit[8] q; bit[8] c; // 4096 shots\n".to_vec();
+        let body: Vec<u8> = (0..6000u32).map(|i| ((i * 7) % 61 + (i >> 9) % 5) as u8).collect();
+        let mut segmented = compress(&header, &Config::default()).unwrap().0;
+        segmented.extend(compress(&body, &Config::default()).unwrap().0);
+
+        let (mut text, mut binary) = (io::Cursor::new(Vec::new()), io::Cursor::new(Vec::new()));
+        decompress_segments_to(&segmented, &mut [&mut text, &mut binary]).unwrap();
+        assert_eq!(text.into_inner(), header);
+        assert_eq!(binary.into_inner(), body);
+
+        // A count mismatch is caught before anything is written
+        let mut only = Vec::new();
+        match decompress_segments_to(&segmented, &mut [&mut only]) {
+            Err(CompressionError::SegmentCountMismatch { expected: 1, found: 2 }) => assert!(only.is_empty()),
+            other => panic!("expected a segment count mismatch, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deadline_falls_back_to_stored_in_time() {
+        let data: Vec<u8> = (0..4_000_000u32).map(|i| ((i * 7) % 61 + (i >> 12) % 5) as u8).collect();
+        let budget = Duration::from_millis(5);
+        let v1 = Config { format_version: VERSION_SINGLE, ..Config::default() };
+        for config in [Config::default(), v1.clone()] {
+            let start = Instant::now();
+            let (compressed, stats) = compress_deadline(&data, &config, start + budget).unwrap();
+            assert!(start.elapsed() < budget + Duration::from_millis(100), "overran: {:?}", start.elapsed());
+            assert!(stats.stored_fallback);
+            assert_eq!(compressed[4], config.format_version);
+            assert_eq!(stats.compressed_size, compressed.len());
+            assert_eq!(decompress(&compressed).unwrap(), data);
+        }
+
+        // With time to spare the full config's work is kept
+        let text = b"rz(0.5) q0; cx q0 q1; ".repeat(200);
+        let far = Instant::now() + Duration::from_secs(600);
+        let (compressed, stats) = compress_deadline(&text, &Config::default(), far).unwrap();
+        assert!(!stats.stored_fallback);
+        assert!(compressed.len() <= compress(&text, &Config::default()).unwrap().0.len());
+        assert_eq!(decompress(&compressed).unwrap(), text);
+
+        // The fallback keeps the caller's layout and chunking; version 1
+        // stores the input as the residual of a rank-0 chain
+        let (compressed, _) = compress_deadline(&text, &v1, Instant::now()).unwrap();
+        assert_eq!(read_u32(&compressed, format::PREFIX_LEN).unwrap(), UNCODED_TABLE_LEN);
+        assert!(compressed.ends_with(&text));
+        assert_eq!(decompress(&compressed).unwrap(), text);
+        let small = Config { chunk_size: 1024, ..Config::default() };
+        let (compressed, stats) = compress_deadline(&text, &small, Instant::now()).unwrap();
+        assert!(stats.stored_fallback);
+        assert_eq!(parse_container(&compressed).unwrap().blocks.len(), plan_chunks(text.len(), 1024).len());
+    }
+
+    #[test]
+    fn test_decompress_deadline_yields_a_block_prefix() {
+        let data: Vec<u8> = (0..256 * 1024u32).map(|i| ((i * 7) % 61 + (i >> 12) % 5) as u8).collect();
+        let (compressed, _) = compress(&data, &Config { chunk_size: 1024, ..Config::default() }).unwrap();
+
+        // A deadline already past stops after the first round of blocks
+        let (partial, complete) = decompress_deadline(&compressed, Instant::now()).unwrap();
+        assert!(!complete);
+        assert!(!partial.is_empty() && partial.len() < data.len());
+        assert_eq!(partial.len() % 1024, 0);
+        assert_eq!(partial, &data[..partial.len()]);
+
+        let far = Instant::now() + Duration::from_secs(600);
+        assert_eq!(decompress_deadline(&compressed, far).unwrap(), (data, true));
+    }
+
+    #[test]
+    fn test_incompressible_mps_payload_skips_huffman() {
+        // A single-block container must take the MPS, however noisy the
+        // input; at rank 2 the chain is small and the residual is noise
+        let noise = CorpusKind::Random.generate(8, 4096);
+        let v1 = Config { format_version: VERSION_SINGLE, max_rank: 2, ..Config::default() };
+        let (compressed, _) = compress(&noise, &v1).unwrap();
+        assert_eq!(read_u32(&compressed, format::PREFIX_LEN).unwrap(), UNCODED_TABLE_LEN);
+        assert_eq!(decompress(&compressed).unwrap(), noise);
+        let mut streamed = Vec::new();
+        decompress_to_writer(&compressed, &mut streamed, &Config { max_chunk_memory: 1024, ..Config::default() })
+            .unwrap();
+        assert_eq!(streamed, noise);
+
+        let (payload, _) = encode_mps_residual(&noise, v1.max_rank, &v1);
+        let forced = format::PREFIX_LEN + encode_huffman_block(&payload, &v1).len();
+        assert!(compressed.len() < forced, "{} vs {forced}", compressed.len());
+    }
+
+    #[test]
+    fn test_expansion_limit_aborts() {
+        // Run-length coding doubles noise, and a pipeline has no stored fallback
+        let noise = CorpusKind::Random.generate(9, 4096);
+        let expanding = Config { pipeline: Some(pipeline::Pipeline::new().rle()), ..Config::default() };
+        let (compressed, _) = compress(&noise, &expanding).unwrap();
+        assert!(compressed.len() > noise.len() * 3 / 2);
+
+        let guarded = Config { abort_on_expansion_over: Some(1.1), ..expanding };
+        match compress(&noise, &guarded) {
+            Err(CompressionError::ExpansionLimit { input: 4096, output, .. }) => assert_eq!(output, compressed.len()),
+            other => panic!("expected the expansion limit, got {other:?}"),
+        }
+        let (tx, _rx) = mpsc::channel();
+        assert!(matches!(compress_streaming(&noise, &guarded, tx), Err(CompressionError::ExpansionLimit { .. })));
+
+        // Stored fallback and the fixed overhead of tiny inputs stay within it
+        let limited = Config { abort_on_expansion_over: Some(1.0), ..Config::default() };
+        assert!(compress(&noise, &limited).unwrap().1.stored_fallback);
+        assert!(compress(&noise[..100], &limited).is_ok());
+        assert!(compress(&noise[..10], &limited).is_ok());
+
+        // The allowance follows the caller's chunking: 16 stored frames
+        // here, where default chunks would allow for one
+        let small = Config { chunk_size: 256, ..limited };
+        let (compressed, stats) = compress(&noise, &small).unwrap();
+        assert!(stats.stored_fallback);
+        assert_eq!(compressed.len(), noise.len() + 13 + 16 * BLOCK_HEADER_LEN);
+        let expanding = Config { pipeline: Some(pipeline::Pipeline::new().rle()), ..small };
+        assert!(matches!(compress(&noise, &expanding), Err(CompressionError::ExpansionLimit { .. })));
+    }
+
+    #[test]
+    fn test_refine_budget_buys_fidelity() {
+        let data: Vec<u8> = (0..8192u32)
+            .map(|i| (128.0 + 60.0 * (i as f64 / 37.0).sin() + 40.0 * (i as f64 / 5.3).cos()) as u8)
+            .collect();
+        let config = Config { error_metric: lossy::ErrorMetric::Psnr, ..Config::default() };
+
+        // No budget: the rank-1 pass alone
+        let (rough, rough_stats) = compress_refine(&data, &config, Duration::ZERO).unwrap();
+        assert_eq!(rough_stats.tensor_rank_used, 1);
+        let (fine, fine_stats) = compress_refine(&data, &config, Duration::from_secs(600)).unwrap();
+        assert!(fine_stats.tensor_rank_used > 1);
+
+        let (rough_psnr, fine_psnr) = (rough_stats.fidelity.unwrap().value, fine_stats.fidelity.unwrap().value);
+        assert!(fine_psnr > rough_psnr, "{fine_psnr} dB vs {rough_psnr} dB");
+        assert_eq!(lossy::ErrorMetric::Psnr.measure(&data, &decompress(&rough).unwrap()), rough_psnr);
+        assert_eq!(lossy::ErrorMetric::Psnr.measure(&data, &decompress(&fine).unwrap()), fine_psnr);
+    }
+
+    #[test]
+    fn test_byte_swapped_container_reported() {
+        let data = b"u3(0.1, 0.2, 0.3) q0; ".repeat(400);
+        let config = Config { chunk_size: 4096, ..Config::default() };
+        let (compressed, _) = compress(&data, &config).unwrap();
+
+        // Rewrite every u32 field big-endian, as a byte-swapping writer would
+        let container = parse_container(&compressed).unwrap();
+        assert!(container.blocks.len() > 1);
+        let mut swapped = compressed.clone();
+        let swap = |bytes: &mut [u8], pos: usize| bytes[pos..pos + 4].reverse();
+        swap(&mut swapped, format::PREFIX_LEN);
+        swap(&mut swapped, format::PREFIX_LEN + 4);
+        for block in &container.blocks {
+            let frame = block.offset - BLOCK_HEADER_LEN;
+            for field in [1, 5, 9] {
+                swap(&mut swapped, frame + field);
+            }
+        }
+        assert!(matches!(decompress(&swapped), Err(CompressionError::ByteSwapped)));
+        assert!(matches!(inspect(&swapped), Err(CompressionError::ByteSwapped)));
+
+        // Plain truncation is still just corruption
+        let truncated = &compressed[..compressed.len() - 10];
+        assert!(matches!(decompress(truncated), Err(CompressionError::DecompressionFailed)));
     }
 }
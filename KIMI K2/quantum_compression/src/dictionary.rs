@@ -0,0 +1,106 @@
+//! Trained dictionaries for small, similar messages
+//!
+//! Short messages pay heavily for what each block carries about itself: a
+//! Huffman table of 256+ bytes, a BPE merge table. Like `zstd --train`,
+//! [`train_dictionary`] learns those once from representative samples, and
+//! blocks coded against the dictionary (set as `Config::dictionary`) carry
+//! only its id and the bitstream. The same dictionary must be handed to
+//! `decompress_with_dictionary` to read them back.
+
+use crate::bpe::{self, Merge};
+use crate::error::{CompressionError, Result};
+use crate::huffman::{self, HuffmanTable};
+
+/// Magic bytes for dictionary identification
+const DICT_MAGIC: &[u8; 4] = b"QCDT";
+
+/// Magic + code lengths + empty merge table: the size of a dictionary
+/// without merges, and the least [`train_dictionary`] produces
+pub const MIN_DICTIONARY_SIZE: usize = DICT_MAGIC.len() + 256 + 1;
+
+/// Learn a dictionary of at most `dict_size` bytes (but at least
+/// [`MIN_DICTIONARY_SIZE`]) from `samples`.
+///
+/// Common substrings become BPE merges, as many as the size allows (up to
+/// 255), and the byte distribution left after merging becomes a shared
+/// Huffman table. Every symbol gets a code, so messages with bytes the
+/// samples never showed still encode.
+pub fn train_dictionary(samples: &[&[u8]], dict_size: usize) -> Vec<u8> {
+    let max_merges = (dict_size.saturating_sub(MIN_DICTIONARY_SIZE) / 3).min(255);
+    let corpus = samples.concat();
+    let (merges, merged) = bpe::encode(&corpus, max_merges);
+
+    // Add-one smoothing keeps unseen symbols encodable
+    let mut freq = [1u64; 256];
+    for &b in &merged {
+        freq[b as usize] += 1;
+    }
+    let table = HuffmanTable::from_frequencies(&freq);
+
+    let mut output = Vec::with_capacity(MIN_DICTIONARY_SIZE + 3 * merges.len());
+    output.extend_from_slice(DICT_MAGIC);
+    output.extend_from_slice(table.lengths());
+    bpe::serialize_merges(&merges, &mut output);
+    output
+}
+
+/// A parsed dictionary
+pub(crate) struct Dictionary {
+    /// CRC32 of the dictionary bytes, recorded in every block that uses it
+    pub id: u32,
+    merges: Vec<Merge>,
+    table: HuffmanTable,
+}
+
+impl Dictionary {
+    /// Parse the output of [`train_dictionary`]
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let invalid = || CompressionError::InvalidDictionary;
+        if bytes.get(..4) != Some(DICT_MAGIC) {
+            return Err(invalid());
+        }
+        let lengths: [u8; 256] = bytes.get(4..260).ok_or_else(invalid)?.try_into().unwrap();
+        let table = HuffmanTable::from_lengths(lengths).ok_or_else(invalid)?;
+        let (merges, used) = bpe::deserialize_merges(&bytes[260..]).ok_or_else(invalid)?;
+        if 260 + used != bytes.len() || lengths.contains(&0) {
+            return Err(invalid());
+        }
+        Ok(Dictionary { id: crc32fast::hash(bytes), merges, table })
+    }
+
+    /// Block body: dictionary id u32 + merges applied u8 + Huffman data.
+    ///
+    /// Merges apply in order up to the first whose symbol occurs in
+    /// `chunk`, since that byte could no longer be told apart.
+    pub fn encode(&self, chunk: &[u8]) -> Vec<u8> {
+        let mut present = [false; 256];
+        chunk.iter().for_each(|&b| present[b as usize] = true);
+        let applied = self.merges.iter().take_while(|m| !present[m.symbol as usize]).count();
+
+        let mut output = Vec::new();
+        output.extend_from_slice(&self.id.to_le_bytes());
+        output.push(applied as u8);
+        let merged = bpe::apply_merges(chunk, &self.merges[..applied]);
+        output.extend_from_slice(&huffman::encode_with_table(&merged, &self.table));
+        output
+    }
+
+    /// Decode a block body produced by [`Dictionary::encode`]
+    pub fn decode(&self, body: &[u8]) -> Result<Vec<u8>> {
+        let id = block_id(body)?;
+        if id != self.id {
+            return Err(CompressionError::DictionaryRequired { id });
+        }
+        let applied = *body.get(4).ok_or(CompressionError::DecompressionFailed)? as usize;
+        let merges = self.merges.get(..applied).ok_or(CompressionError::DecompressionFailed)?;
+        let merged = huffman::decode_with_table(&body[5..], &self.table)
+            .ok_or(CompressionError::DecompressionFailed)?;
+        Ok(bpe::decode(&merged, merges))
+    }
+}
+
+/// Id of the dictionary a block body was coded against
+pub(crate) fn block_id(body: &[u8]) -> Result<u32> {
+    let bytes = body.get(..4).ok_or(CompressionError::DecompressionFailed)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
@@ -0,0 +1,125 @@
+//! AES-256-GCM container encryption (feature `encryption`)
+//!
+//! With `Config::encryption_key` set, the finished container is encrypted
+//! whole and wrapped in a version 4 envelope: magic + version, a random
+//! 96-bit nonce, the ciphertext length u32 and its CRC32, then the
+//! ciphertext with the 16-byte GCM tag last. The magic, version and nonce
+//! are authenticated as associated data. Read it back with
+//! [`decompress_with_key`].
+
+use crate::compress::decompress;
+use crate::error::{ChecksumScope, CompressionError, Result};
+use crate::format::{MAGIC, PREFIX_LEN, VERSION_ENCRYPTED};
+use crate::{CompressionStats, Config};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+/// Bytes of the GCM nonce
+const NONCE_LEN: usize = 12;
+/// Magic + version + nonce + ciphertext length + ciphertext CRC32
+const ENVELOPE_HEADER_LEN: usize = PREFIX_LEN + NONCE_LEN + 8;
+
+/// Replace the container in `output` with its encrypted envelope when
+/// `config` holds a key, recording the new size in `stats`
+pub(crate) fn encrypt_output(config: &Config, output: &mut Vec<u8>, mut stats: CompressionStats) -> CompressionStats {
+    let Some(key) = &config.encryption_key else {
+        return stats;
+    };
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let mut envelope = Vec::with_capacity(ENVELOPE_HEADER_LEN + output.len() + 16);
+    envelope.extend_from_slice(MAGIC);
+    envelope.push(VERSION_ENCRYPTED);
+    envelope.extend_from_slice(&nonce);
+
+    let payload = Payload { msg: output, aad: &envelope };
+    let ciphertext = Aes256Gcm::new(key.into())
+        .encrypt(&nonce, payload)
+        .expect("container fits in one GCM message");
+    envelope.extend_from_slice(&(ciphertext.len() as u32).to_le_bytes());
+    envelope.extend_from_slice(&crc32fast::hash(&ciphertext).to_le_bytes());
+    envelope.extend_from_slice(&ciphertext);
+
+    *output = envelope;
+    stats.compressed_size = output.len();
+    stats.compression_ratio = stats.original_size as f64 / output.len() as f64;
+    stats
+}
+
+/// Decrypt and decompress a container written with
+/// `Config::encryption_key` set to `key`.
+///
+/// The ciphertext CRC32 is checked first, so plain corruption fails with
+/// [`CompressionError::ChecksumMismatch`]; a wrong key, tampering the
+/// checksum missed, or a container that was never encrypted fails with
+/// [`CompressionError::DecryptionFailed`].
+pub fn decompress_with_key(compressed: &[u8], key: &[u8; 32]) -> Result<Vec<u8>> {
+    if compressed.get(..4) != Some(MAGIC) || compressed.get(4) != Some(&VERSION_ENCRYPTED) {
+        return Err(CompressionError::DecryptionFailed);
+    }
+    let header = compressed.get(..ENVELOPE_HEADER_LEN).ok_or(CompressionError::DecompressionFailed)?;
+    let field = |pos: usize| u32::from_le_bytes(header[pos..pos + 4].try_into().unwrap());
+    let (len, expected) = (field(PREFIX_LEN + NONCE_LEN) as usize, field(PREFIX_LEN + NONCE_LEN + 4));
+    let offsets = ENVELOPE_HEADER_LEN..ENVELOPE_HEADER_LEN + len;
+    let ciphertext = compressed.get(offsets.clone()).ok_or(CompressionError::DecompressionFailed)?;
+
+    let actual = crc32fast::hash(ciphertext);
+    if actual != expected {
+        return Err(CompressionError::ChecksumMismatch { scope: ChecksumScope::Ciphertext { offsets }, expected, actual });
+    }
+
+    let nonce = Nonce::from_slice(&header[PREFIX_LEN..PREFIX_LEN + NONCE_LEN]);
+    let payload = Payload { msg: ciphertext, aad: &header[..PREFIX_LEN + NONCE_LEN] };
+    let container = Aes256Gcm::new(key.into())
+        .decrypt(nonce, payload)
+        .map_err(|_| CompressionError::DecryptionFailed)?;
+    decompress(&container)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, compress_deadline, Config};
+    use std::time::Instant;
+
+    #[test]
+    fn test_encrypted_roundtrip_needs_the_key() {
+        let data = b"ccx q0 q1 q2; measure q2 -> c0; ".repeat(300);
+        let key = [7u8; 32];
+        let config = Config { encryption_key: Some(key), ..Config::default() };
+        let (compressed, stats) = compress(&data, &config).unwrap();
+        assert_eq!(stats.compressed_size, compressed.len());
+        assert!(!compressed.windows(16).any(|w| data.windows(16).any(|d| d == w)));
+
+        assert_eq!(decompress_with_key(&compressed, &key).unwrap(), data);
+        assert!(matches!(decompress(&compressed), Err(CompressionError::KeyRequired)));
+        let mut wrong = key;
+        wrong[0] ^= 1;
+        assert!(matches!(decompress_with_key(&compressed, &wrong), Err(CompressionError::DecryptionFailed)));
+
+        // The checksum covers the ciphertext
+        let mut corrupt = compressed.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            decompress_with_key(&corrupt, &key),
+            Err(CompressionError::ChecksumMismatch { scope: ChecksumScope::Ciphertext { .. }, .. })
+        ));
+
+        // Tiny inputs are encrypted too, and plain containers are refused
+        let (tiny, _) = compress(b"x q0;", &config).unwrap();
+        assert_eq!(decompress_with_key(&tiny, &key).unwrap(), b"x q0;");
+        let (plain, _) = compress(&data, &Config::default()).unwrap();
+        assert!(matches!(decompress_with_key(&plain, &key), Err(CompressionError::DecryptionFailed)));
+    }
+
+    #[test]
+    fn test_missed_deadline_still_encrypts() {
+        let data = b"h q0; cx q0 q1; measure q1 -> c1; ".repeat(200);
+        let key = [3u8; 32];
+        let config = Config { encryption_key: Some(key), ..Config::default() };
+        let (compressed, stats) = compress_deadline(&data, &config, Instant::now()).unwrap();
+        assert!(stats.stored_fallback);
+        assert_eq!(stats.compressed_size, compressed.len());
+        assert!(matches!(decompress(&compressed), Err(CompressionError::KeyRequired)));
+        assert_eq!(decompress_with_key(&compressed, &key).unwrap(), data);
+    }
+}
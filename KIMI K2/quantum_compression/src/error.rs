@@ -1,10 +1,15 @@
 //! Error types for quantum compression
 
+use std::fmt;
+use std::ops::Range;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum CompressionError {
-    #[error("Input data too small: {0} bytes (minimum: 64)")]
+    #[error(
+        "Input data too small: {0} bytes (chunk stores and format versions without a compact container need {min})",
+        min = crate::compress::MIN_INPUT_SIZE
+    )]
     InputTooSmall(usize),
     
     #[error("Tensor decomposition failed: {0}")]
@@ -13,9 +18,54 @@ pub enum CompressionError {
     #[error("Huffman encoding failed: {0}")]
     HuffmanEncoding(String),
     
+    #[error("Input length {len} is not a multiple of the {width}-byte sample width")]
+    SampleMisaligned { len: usize, width: usize },
+    
+    #[error("Amplitude out of representable range at index {index}: {value}")]
+    AmplitudeOutOfRange { index: usize, value: f64 },
+    
     #[error("Decompression failed: data corrupted")]
     DecompressionFailed,
     
+    #[error("Container counts and lengths are big-endian; it was written byte-swapped")]
+    ByteSwapped,
+    
+    #[error("Roundtrip verification failed: decompressed output differs from input")]
+    RoundtripVerificationFailed,
+    
+    #[error("Checksum mismatch in {scope}: expected {expected:#010x}, got {actual:#010x}")]
+    ChecksumMismatch { scope: ChecksumScope, expected: u32, actual: u32 },
+    
+    #[error("Dictionary {id:#010x} is required to decode this data")]
+    DictionaryRequired { id: u32 },
+    
+    #[error("Invalid dictionary")]
+    InvalidDictionary,
+    
+    #[error("Container is encrypted; decompress it with its key")]
+    KeyRequired,
+    
+    #[error("Decryption failed: wrong key, tampered ciphertext or unencrypted container")]
+    DecryptionFailed,
+    
+    #[error("Chunk {hash:#018x} is stored separately; decode with its chunk store")]
+    ChunkStoreRequired { hash: u64 },
+    
+    #[error("Chunk {hash:#018x} is missing from the chunk store")]
+    ChunkMissing { hash: u64 },
+    
+    #[error("Output of {output} bytes for {input} input bytes exceeds the {limit}x expansion limit")]
+    ExpansionLimit { input: usize, output: usize, limit: f64 },
+    
+    #[error("Deadline passed before compression finished")]
+    DeadlinePassed,
+    
+    #[error("Expected {expected} segments, found {found}")]
+    SegmentCountMismatch { expected: usize, found: usize },
+    
+    #[error("Format version {version} cannot be produced: {reason}")]
+    FormatVersion { version: u8, reason: &'static str },
+    
     #[error("VRAM allocation failed: requested {requested} bytes, available {available}")]
     VramAllocation { requested: usize, available: usize },
     
@@ -24,6 +74,37 @@ pub enum CompressionError {
     
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// What a failed checksum covered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumScope {
+    /// The whole reconstructed output
+    Data,
+    /// One block's payload; `offsets` is its byte range in the compressed input
+    Block { index: usize, offsets: Range<usize> },
+    /// The whole reconstructed output, against a CRC32 the caller supplied
+    Expected,
+    /// An encrypted container's ciphertext; `offsets` is its byte range
+    Ciphertext { offsets: Range<usize> },
+}
+
+impl fmt::Display for ChecksumScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumScope::Data => write!(f, "decompressed data"),
+            ChecksumScope::Expected => write!(f, "decompressed data (caller's expected hash)"),
+            ChecksumScope::Block { index, offsets } => {
+                write!(f, "block {index} (bytes {}..{})", offsets.start, offsets.end)
+            }
+            ChecksumScope::Ciphertext { offsets } => {
+                write!(f, "ciphertext (bytes {}..{})", offsets.start, offsets.end)
+            }
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, CompressionError>;
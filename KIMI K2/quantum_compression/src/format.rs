@@ -0,0 +1,169 @@
+//! Container header layout
+//!
+//! Every container starts with the magic `QCMP` and a version byte. What
+//! follows depends on the version:
+//!
+//! - 1: one unframed MPS block body, to the end of the input
+//! - 2: block count u32, then the framed blocks
+//! - 3: block count u32 + CRC32 of the original data, then the framed blocks
+//! - 4: an encrypted container (feature `encryption`; see
+//!   `encryption.rs`), readable only with its key
+//!
+//! Each frame is method|flags u8 + raw_len u32 + payload_len u32 + payload
+//! CRC32 + payload, all little-endian.
+//! With `Config::embed_schema` set, the first frame is a schema block of
+//! raw length 0 (see `schema.rs`).
+//!
+//! Inputs too short for a framed container instead get a compact two-byte
+//! header: a tag byte ([`COMPACT_TAG`] | method) and the raw length u8, then
+//! one stored or compact Huffman block body to the end of the input. The tag's high
+//! bits never match the magic, so readers tell the forms apart by the first
+//! byte.
+
+use crate::error::{CompressionError, Result};
+
+/// Magic bytes for file format identification
+pub const MAGIC: &[u8; 4] = b"QCMP";
+/// Single-block layout: magic + version + MPS block body
+pub const VERSION_SINGLE: u8 = 1;
+/// Chunked layout: magic + version + block count + framed blocks
+pub const VERSION_CHUNKED: u8 = 2;
+/// Chunked layout with a CRC32 of the original data after the block count
+/// (the layout `compress` writes unless `Config::format_version` pins another)
+pub const VERSION: u8 = 3;
+/// AES-GCM envelope around a whole container
+pub const VERSION_ENCRYPTED: u8 = 4;
+
+/// Magic + version: enough to tell how long the full header is
+pub const PREFIX_LEN: usize = 5;
+
+/// High bits of the tag byte opening a compact container; the low nibble
+/// holds the block method
+pub const COMPACT_TAG: u8 = 0xC0;
+/// Compact tag + raw length u8
+pub const COMPACT_HEADER_LEN: usize = 2;
+/// Version reported for compact containers, which record none
+pub const VERSION_COMPACT: u8 = 0;
+
+/// Whether `data` opens with a compact header rather than the magic
+pub fn is_compact(data: &[u8]) -> bool {
+    data.first().is_some_and(|&tag| tag & 0xF0 == COMPACT_TAG)
+}
+
+/// A parsed container header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    /// Framed blocks that follow (always 1 for version 1, which records no
+    /// count)
+    pub block_count: u32,
+    /// CRC32 of the original data; present exactly in version 3
+    pub data_checksum: Option<u32>,
+}
+
+impl Header {
+    /// Split a header off the front of `data`, returning it and the bytes
+    /// after it. Fails on a bad magic, an unknown version or a truncated
+    /// header.
+    pub fn parse(data: &[u8]) -> Result<(Header, &[u8])> {
+        let len = header_len(data)?;
+        let fields = data.get(PREFIX_LEN..len).ok_or(CompressionError::DecompressionFailed)?;
+        let version = data[4];
+        let read = |pos: usize| u32::from_le_bytes(fields[pos..pos + 4].try_into().unwrap());
+
+        let header = match version {
+            VERSION_SINGLE => Header { version, block_count: 1, data_checksum: None },
+            VERSION_CHUNKED => Header { version, block_count: read(0), data_checksum: None },
+            _ => Header { version, block_count: read(0), data_checksum: Some(read(4)) },
+        };
+        Ok((header, &data[len..]))
+    }
+
+    /// Append the header to `out`; [`Header::parse`] reads it back
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(MAGIC);
+        out.push(self.version);
+        if self.version != VERSION_SINGLE {
+            out.extend_from_slice(&self.block_count.to_le_bytes());
+        }
+        if let Some(checksum) = self.data_checksum {
+            out.extend_from_slice(&checksum.to_le_bytes());
+        }
+    }
+
+    /// Bytes the header occupies
+    pub fn encoded_len(&self) -> usize {
+        match self.version {
+            VERSION_SINGLE => PREFIX_LEN,
+            _ => PREFIX_LEN + 4 + if self.data_checksum.is_some() { 4 } else { 0 },
+        }
+    }
+}
+
+/// Full header length announced by the first [`PREFIX_LEN`] bytes of
+/// `data`, for readers that must know how much to read before parsing
+pub fn header_len(data: &[u8]) -> Result<usize> {
+    if data.get(..4) != Some(MAGIC) {
+        return Err(CompressionError::DecompressionFailed);
+    }
+    match data.get(4) {
+        Some(&VERSION_SINGLE) => Ok(PREFIX_LEN),
+        Some(&VERSION_CHUNKED) => Ok(PREFIX_LEN + 4),
+        Some(&VERSION) => Ok(PREFIX_LEN + 8),
+        Some(&VERSION_ENCRYPTED) => Err(CompressionError::KeyRequired),
+        _ => Err(CompressionError::DecompressionFailed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_roundtrip() {
+        let headers = [
+            Header { version: VERSION_SINGLE, block_count: 1, data_checksum: None },
+            Header { version: VERSION_CHUNKED, block_count: 7, data_checksum: None },
+            Header { version: VERSION, block_count: 3, data_checksum: Some(0xDEAD_BEEF) },
+        ];
+        for header in headers {
+            let mut out = Vec::new();
+            header.write(&mut out);
+            assert_eq!(out.len(), header.encoded_len());
+            assert_eq!(header_len(&out).unwrap(), header.encoded_len());
+            out.extend_from_slice(b"body");
+
+            let (parsed, rest) = Header::parse(&out).unwrap();
+            assert_eq!(parsed, header);
+            assert_eq!(rest, b"body");
+        }
+    }
+
+    #[test]
+    fn test_malformed_headers_rejected() {
+        let mut valid = Vec::new();
+        Header { version: VERSION, block_count: 2, data_checksum: Some(1) }.write(&mut valid);
+
+        // Truncated anywhere, including inside the count and checksum
+        for len in 0..valid.len() {
+            assert!(Header::parse(&valid[..len]).is_err(), "prefix of {len} bytes");
+        }
+
+        let mut bad_magic = valid.clone();
+        bad_magic[0] = b'X';
+        assert!(Header::parse(&bad_magic).is_err());
+
+        for version in [0, VERSION + 1, 0xFF] {
+            let mut unknown = valid.clone();
+            unknown[4] = version;
+            assert!(Header::parse(&unknown).is_err());
+        }
+    }
+
+    #[test]
+    fn test_compact_tag_never_matches_magic() {
+        assert!(!is_compact(MAGIC));
+        assert!(!is_compact(&[]));
+        assert!(is_compact(&[COMPACT_TAG | 11, 40]));
+    }
+}
@@ -0,0 +1,62 @@
+//! GPU device selection
+//!
+//! No GPU backend is compiled in yet, so [`list_gpus`] finds no devices and
+//! every chunk is encoded on the CPU. `Config::gpu_device` is still checked
+//! against the devices present: naming one that does not exist falls back
+//! to the CPU (with a logged warning under feature `logging`) instead of
+//! failing the compression.
+
+use crate::logging::emit;
+use crate::Config;
+
+/// Number of GPU devices available to the compressor
+pub fn list_gpus() -> usize {
+    0
+}
+
+/// Device the GPU path should run on for `config`, or `None` for the CPU.
+///
+/// With `Config::use_gpu` set, the device is `Config::gpu_device` (device
+/// 0 when unset). An index past [`list_gpus`] is logged when it was asked
+/// for explicitly; either way the CPU takes over.
+pub(crate) fn select_device(config: &Config) -> Option<usize> {
+    if !config.use_gpu {
+        return None;
+    }
+    let device = config.gpu_device.unwrap_or(0);
+    let available = list_gpus();
+    if device < available {
+        return Some(device);
+    }
+    if config.gpu_device.is_some() {
+        emit!(config, Warn, "GPU device {device} requested but {available} available; compressing on the CPU");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::capture;
+    use crate::{compress, decompress};
+
+    #[test]
+    fn test_invalid_gpu_device_falls_back_to_cpu() {
+        capture::install();
+        let data = b"h q0; cx q0 q1; measure q0 q1; ".repeat(100);
+        let config = Config { gpu_device: Some(list_gpus() + 3), ..Config::default() };
+        let (compressed, stats) = compress(&data, &config).unwrap();
+        assert_eq!(stats.gpu_device, None);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        // Warned about only with logging on; the default device never is
+        // when no GPU is present
+        assert_eq!(select_device(&Config::default()), None);
+        let warnings = capture::messages("GPU device");
+        assert_eq!(warnings.len(), usize::from(cfg!(feature = "logging")));
+        if let Some((level, warning)) = warnings.first() {
+            assert_eq!(*level, log::Level::Warn);
+            assert!(warning.contains(&format!("GPU device {}", list_gpus() + 3)));
+        }
+    }
+}
@@ -2,9 +2,12 @@
 //!
 //! GPU-optimized Huffman encoding with adaptive frequency updates.
 
+use crate::error::{CompressionError, Result};
+use crate::simd::{self, TargetPlatform};
 use bitvec::prelude::*;
 use std::collections::BinaryHeap;
 use std::cmp::Ordering;
+use std::sync::{Arc, Mutex, PoisonError};
 
 /// Node in the Huffman tree
 #[derive(Clone, Eq, PartialEq)]
@@ -27,15 +30,47 @@ impl PartialOrd for HuffmanNode {
     }
 }
 
+/// Context model used for Huffman-only blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntropyModel {
+    /// One table for the whole block
+    #[default]
+    Order0,
+    /// One table per previous byte
+    Order1,
+}
+
 /// Huffman code table
+#[derive(Debug, PartialEq, Eq)]
 pub struct HuffmanTable {
     codes: [BitVec<u8, Msb0>; 256],
     lengths: [u8; 256],
 }
 
+/// Longest code [`HuffmanTable::from_frequencies`] will produce
+pub const MAX_CODE_LEN: u8 = 32;
+
 impl HuffmanTable {
-    /// Build Huffman table from frequency counts
+    /// Build Huffman table from frequency counts.
+    ///
+    /// Codes are length-limited to [`MAX_CODE_LEN`] bits: if a skewed
+    /// (e.g. Fibonacci-like) distribution yields a deeper tree, counts are
+    /// halved and the tree rebuilt until it fits.
     pub fn from_frequencies(freq: &[u64; 256]) -> Self {
+        let mut freq = *freq;
+        loop {
+            let table = Self::build_tree(&freq);
+            if table.lengths.iter().all(|&len| len <= MAX_CODE_LEN) {
+                return table;
+            }
+            for count in freq.iter_mut().filter(|count| **count > 0) {
+                *count = (*count / 2).max(1);
+            }
+        }
+    }
+
+    /// Unrestricted Huffman construction
+    fn build_tree(freq: &[u64; 256]) -> Self {
         let mut heap = BinaryHeap::new();
         
         // Create leaf nodes for symbols with non-zero frequency
@@ -52,7 +87,11 @@ impl HuffmanTable {
         
         // Handle edge case: all zeros or single symbol
         if heap.len() < 2 {
-            return Self::default_table();
+            let mut table = Self::empty_table();
+            if let Some(leaf) = heap.pop() {
+                Self::generate_codes(&leaf, BitVec::new(), &mut table.codes, &mut table.lengths);
+            }
+            return table;
         }
         
         // Build Huffman tree
@@ -61,7 +100,7 @@ impl HuffmanTable {
             let right = heap.pop().unwrap();
             
             heap.push(HuffmanNode {
-                freq: left.freq + right.freq,
+                freq: left.freq.saturating_add(right.freq),
                 symbol: None,
                 left: Some(Box::new(left)),
                 right: Some(Box::new(right)),
@@ -105,45 +144,179 @@ impl HuffmanTable {
         }
     }
     
-    fn default_table() -> Self {
-        let mut codes: [BitVec<u8, Msb0>; 256] = std::array::from_fn(|_| {
-            let mut bv = BitVec::new();
-            bv.push(false);
-            bv
-        });
-        let lengths = [1u8; 256];
-        HuffmanTable { codes, lengths }
+    /// Build canonical codes from code lengths (0 = symbol absent).
+    ///
+    /// Only the lengths need storing; returns `None` if they cannot form a
+    /// prefix code.
+    pub fn from_lengths(lengths: [u8; 256]) -> Option<Self> {
+        let mut symbols: Vec<usize> = (0..256).filter(|&s| lengths[s] > 0).collect();
+        symbols.sort_by_key(|&s| (lengths[s], s));
+
+        let mut table = Self::empty_table();
+        let mut code = 0u64;
+        let mut prev_len = 0u8;
+        for &symbol in &symbols {
+            let len = lengths[symbol];
+            if len > 63 {
+                return None;
+            }
+            code <<= len - prev_len;
+            if code >> len != 0 {
+                return None; // Kraft inequality violated
+            }
+            for bit in (0..len).rev() {
+                table.codes[symbol].push(code >> bit & 1 == 1);
+            }
+            code += 1;
+            prev_len = len;
+        }
+
+        table.lengths = lengths;
+        Some(table)
+    }
+
+    /// Code length of every symbol (0 = absent)
+    pub fn lengths(&self) -> &[u8; 256] {
+        &self.lengths
+    }
+
+    /// FNV-1a hash of the code lengths.
+    ///
+    /// Lengths determine a canonical code, so tables that would serialize to
+    /// the same canonical form share a fingerprint; use it to deduplicate
+    /// tables or detect a mismatched external table cheaply.
+    pub fn canonical_fingerprint(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+        self.lengths
+            .iter()
+            .fold(FNV_OFFSET, |hash, &len| (hash ^ len as u64).wrapping_mul(FNV_PRIME))
+    }
+
+    fn empty_table() -> Self {
+        HuffmanTable {
+            codes: std::array::from_fn(|_| BitVec::new()),
+            lengths: [0u8; 256],
+        }
     }
     
     /// Serialize the Huffman table
-    pub fn serialize(&self) -> Vec<u8> {
+    ///
+    /// Fails with [`CompressionError::HuffmanEncoding`] if a code needs more
+    /// than 255 bytes, which its one-byte size prefix cannot describe.
+    pub fn serialize(&self) -> Result<Vec<u8>> {
         let mut output = Vec::new();
         
         // Store lengths (256 bytes)
         output.extend_from_slice(&self.lengths);
         
         // Store codes (variable length, but bounded)
-        for code in &self.codes {
+        for (symbol, code) in self.codes.iter().enumerate() {
             let bytes = code.as_raw_slice();
-            output.push(bytes.len() as u8);
+            let n_bytes = u8::try_from(bytes.len()).map_err(|_| {
+                CompressionError::HuffmanEncoding(format!(
+                    "code for symbol {} is {} bits, too long to serialize",
+                    symbol,
+                    code.len()
+                ))
+            })?;
+            output.push(n_bytes);
             output.extend_from_slice(bytes);
         }
         
-        output
+        Ok(output)
+    }
+
+    /// Deserialize a Huffman table produced by [`HuffmanTable::serialize`]
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        let lengths: [u8; 256] = data.get(..256)?.try_into().ok()?;
+        let mut codes: [BitVec<u8, Msb0>; 256] = std::array::from_fn(|_| BitVec::new());
+
+        let mut pos = 256;
+        for (symbol, code) in codes.iter_mut().enumerate() {
+            let n_bytes = *data.get(pos)? as usize;
+            pos += 1;
+            let bytes = data.get(pos..pos + n_bytes)?;
+            pos += n_bytes;
+
+            let len = lengths[symbol] as usize;
+            if len > n_bytes * 8 {
+                return None;
+            }
+            code.extend_from_bitslice(&BitSlice::<u8, Msb0>::from_slice(bytes)[..len]);
+        }
+
+        Some(HuffmanTable { codes, lengths })
+    }
+
+    /// Build a binary decode trie: each node holds `[child0, child1]`,
+    /// leaves are tagged with `LEAF | symbol`.
+    fn decode_trie(&self) -> Option<Vec<[u32; 2]>> {
+        const EMPTY: u32 = 0;
+        let mut nodes = vec![[EMPTY; 2]];
+
+        for (symbol, code) in self.codes.iter().enumerate() {
+            if code.is_empty() {
+                continue;
+            }
+            let mut node = 0usize;
+            for (i, bit) in code.iter().by_vals().enumerate() {
+                let slot = nodes[node][bit as usize];
+                if i + 1 == code.len() {
+                    if slot != EMPTY {
+                        return None; // Not prefix-free
+                    }
+                    nodes[node][bit as usize] = LEAF | symbol as u32;
+                } else if slot == EMPTY {
+                    nodes.push([EMPTY; 2]);
+                    let child = (nodes.len() - 1) as u32;
+                    nodes[node][bit as usize] = child;
+                    node = child as usize;
+                } else if slot & LEAF != 0 {
+                    return None; // Not prefix-free
+                } else {
+                    node = slot as usize;
+                }
+            }
+        }
+
+        Some(nodes)
     }
 }
 
+/// Tag bit marking a decode-trie slot as a leaf symbol
+const LEAF: u32 = 1 << 31;
+
 /// Encode data using Huffman coding
 pub fn encode(data: &[u8]) -> (Vec<u8>, HuffmanTable) {
+    encode_with_platform(data, TargetPlatform::Auto)
+}
+
+/// Encode data, counting frequencies with the selected SIMD path
+pub fn encode_with_platform(data: &[u8], platform: TargetPlatform) -> (Vec<u8>, HuffmanTable) {
     // Count frequencies
-    let mut freq = [0u64; 256];
-    for &byte in data {
-        freq[byte as usize] += 1;
-    }
+    let freq = simd::histogram(data, platform);
     
     // Build table
     let table = HuffmanTable::from_frequencies(&freq);
     
+    (encode_with_table(data, &table), table)
+}
+
+/// Like [`encode_with_platform`], but the table comes from `cache` when the
+/// same histogram was seen recently
+pub fn encode_cached(
+    data: &[u8],
+    platform: TargetPlatform,
+    cache: &HuffmanTableCache,
+) -> (Vec<u8>, Arc<HuffmanTable>) {
+    let table = cache.get_or_build(&simd::histogram(data, platform));
+    (encode_with_table(data, &table), table)
+}
+
+/// Bitstream of `data` under `table`, prefixed with its length. Every
+/// byte of `data` must have a code in `table`.
+pub fn encode_with_table(data: &[u8], table: &HuffmanTable) -> Vec<u8> {
     // Encode data
     let mut bits: BitVec<u8, Msb0> = BitVec::new();
     for &byte in data {
@@ -159,30 +332,505 @@ pub fn encode(data: &[u8]) -> (Vec<u8>, HuffmanTable) {
     result.extend_from_slice(&len_bytes);
     result.append(&mut output);
     
-    (result, table)
+    result
+}
+
+/// Shared LRU cache of Huffman tables keyed by histogram.
+///
+/// Building a table costs a heap-driven tree construction per call; when
+/// the same byte distribution recurs (streams of similar messages), the
+/// cache hands back the table built the first time. Clones share one
+/// cache, so a handle can be placed in several `Config`s or threads.
+/// Entries are found by a histogram fingerprint and confirmed against the
+/// full histogram, so a hit always yields the table a fresh build would.
+#[derive(Clone)]
+pub struct HuffmanTableCache {
+    inner: Arc<Mutex<CacheState>>,
+}
+
+struct CacheState {
+    capacity: usize,
+    /// Least recently used first
+    entries: Vec<CacheEntry>,
+    hits: u64,
+}
+
+struct CacheEntry {
+    fingerprint: u64,
+    freq: Box<[u64; 256]>,
+    table: Arc<HuffmanTable>,
+}
+
+impl HuffmanTableCache {
+    /// A cache holding at most `capacity` tables (at least one)
+    pub fn new(capacity: usize) -> Self {
+        let state = CacheState { capacity: capacity.max(1), entries: Vec::new(), hits: 0 };
+        HuffmanTableCache { inner: Arc::new(Mutex::new(state)) }
+    }
+
+    /// The table for `freq`, built and inserted (evicting the least
+    /// recently used entry when full) on a miss
+    pub fn get_or_build(&self, freq: &[u64; 256]) -> Arc<HuffmanTable> {
+        let fingerprint = histogram_fingerprint(freq);
+        let mut state = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(i) = state
+            .entries
+            .iter()
+            .position(|e| e.fingerprint == fingerprint && *e.freq == *freq)
+        {
+            state.hits += 1;
+            let entry = state.entries.remove(i);
+            let table = Arc::clone(&entry.table);
+            state.entries.push(entry);
+            return table;
+        }
+
+        let table = Arc::new(HuffmanTable::from_frequencies(freq));
+        if state.entries.len() == state.capacity {
+            state.entries.remove(0);
+        }
+        state.entries.push(CacheEntry { fingerprint, freq: Box::new(*freq), table: Arc::clone(&table) });
+        table
+    }
+
+    /// Tables currently cached
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner).entries.len()
+    }
+
+    /// Whether no table is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lookups answered from the cache so far
+    pub fn hits(&self) -> u64 {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner).hits
+    }
+}
+
+impl std::fmt::Debug for HuffmanTableCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        f.debug_struct("HuffmanTableCache")
+            .field("capacity", &state.capacity)
+            .field("len", &state.entries.len())
+            .field("hits", &state.hits)
+            .finish()
+    }
+}
+
+/// FNV-1a hash of a histogram's counts
+fn histogram_fingerprint(freq: &[u64; 256]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    freq.iter()
+        .flat_map(|count| count.to_le_bytes())
+        .fold(FNV_OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
 }
 
 /// Decode Huffman-encoded data
 pub fn decode(encoded: &[u8], table_data: &[u8]) -> Option<Vec<u8>> {
-    if encoded.len() < 8 {
-        return None;
+    Decoder::new(encoded, table_data)?.collect_exact()
+}
+
+/// Decode data produced by [`encode_with_table`] with an already-built table
+pub fn decode_with_table(encoded: &[u8], table: &HuffmanTable) -> Option<Vec<u8>> {
+    Decoder::with_table(encoded, table)?.collect_exact()
+}
+
+/// Reusable order-1 frequency tables (256 contexts × 256 symbols).
+///
+/// The chunk encoder keeps one per worker and reuses it across chunks; only
+/// the contexts a chunk touched are cleared afterwards, so the 512 KiB table
+/// is neither reallocated nor wiped wholesale per chunk.
+pub struct FrequencyCounter {
+    order1: Vec<[u64; 256]>,
+}
+
+impl FrequencyCounter {
+    pub fn new() -> Self {
+        FrequencyCounter { order1: vec![[0u64; 256]; 256] }
     }
-    
-    let original_len = u64::from_le_bytes(encoded[0..8].try_into().ok()?) as usize;
-    let bits = BitSlice::<u8, Msb0>::from_slice(&encoded[8..]);
-    
-    // Rebuild decode table (tree traversal would go here)
-    // For now, simplified approach
-    
-    let mut result = Vec::with_capacity(original_len);
-    
-    // Placeholder: actual decoding requires tree reconstruction
-    // This is simplified for the prototype
-    for byte in encoded[8..].iter().take(original_len) {
-        result.push(*byte);
+}
+
+impl Default for FrequencyCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Order-0 byte counts gathered piece by piece, for callers that see their
+/// data in parts (or keep counting as it streams in) before committing to a
+/// table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrequencyAccumulator {
+    freq: [u64; 256],
+}
+
+impl FrequencyAccumulator {
+    pub fn new() -> Self {
+        FrequencyAccumulator { freq: [0; 256] }
+    }
+
+    /// Count the bytes of `data` on top of everything added so far
+    pub fn add(&mut self, data: &[u8]) {
+        let counts = simd::histogram(data, TargetPlatform::Auto);
+        for (total, count) in self.freq.iter_mut().zip(counts) {
+            *total += count;
+        }
+    }
+
+    /// Counts so far, indexed by byte value
+    pub fn frequencies(&self) -> &[u64; 256] {
+        &self.freq
+    }
+
+    /// The table [`HuffmanTable::from_frequencies`] builds from the counts
+    /// so far; the accumulator keeps counting afterwards
+    pub fn build(&self) -> HuffmanTable {
+        HuffmanTable::from_frequencies(&self.freq)
+    }
+}
+
+impl Default for FrequencyAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encode data with an order-1 model: one canonical table per previous byte
+/// (the first byte uses context 0).
+///
+/// Layout: len u64 + 32-byte bitmap of used contexts + per used context
+/// (symbol count - 1, then symbol/length pairs) + bitstream.
+pub fn encode_order1(data: &[u8]) -> Vec<u8> {
+    encode_order1_with(data, &mut FrequencyCounter::new())
+}
+
+/// [`encode_order1`] counting into a caller-provided, reusable table
+pub fn encode_order1_with(data: &[u8], counter: &mut FrequencyCounter) -> Vec<u8> {
+    let freq = &mut counter.order1;
+    let mut prev = 0u8;
+    for &byte in data {
+        freq[prev as usize][byte as usize] += 1;
+        prev = byte;
+    }
+
+    let mut result = Vec::new();
+    result.extend_from_slice(&(data.len() as u64).to_le_bytes());
+
+    let mut bitmap = [0u8; 32];
+    for (ctx, counts) in freq.iter().enumerate() {
+        if counts.iter().any(|&c| c > 0) {
+            bitmap[ctx / 8] |= 1 << (ctx % 8);
+        }
+    }
+    result.extend_from_slice(&bitmap);
+
+    // Tables only for used contexts; `slot` maps a context to its table
+    let used_contexts = bitmap.iter().map(|b| b.count_ones() as usize).sum();
+    let mut tables = Vec::with_capacity(used_contexts);
+    let mut slot = [0u8; 256];
+    for (ctx, counts) in freq.iter().enumerate() {
+        if bitmap[ctx / 8] & (1 << (ctx % 8)) == 0 {
+            continue;
+        }
+        let lengths = HuffmanTable::from_frequencies(counts).lengths;
+        let used: Vec<u8> = (0..=255u8).filter(|&s| lengths[s as usize] > 0).collect();
+        result.push((used.len() - 1) as u8);
+        for &symbol in &used {
+            result.extend_from_slice(&[symbol, lengths[symbol as usize]]);
+        }
+        slot[ctx] = tables.len() as u8;
+        tables.push(HuffmanTable::from_lengths(lengths).expect("Huffman lengths form a prefix code"));
+    }
+
+    let mut bits: BitVec<u8, Msb0> = BitVec::new();
+    let mut prev = 0u8;
+    for &byte in data {
+        let table = &tables[slot[prev as usize] as usize];
+        bits.extend_from_bitslice(&table.codes[byte as usize]);
+        prev = byte;
+    }
+    result.extend_from_slice(bits.as_raw_slice());
+
+    // Leave the counter zeroed for the next chunk
+    for (ctx, counts) in freq.iter_mut().enumerate() {
+        if bitmap[ctx / 8] & (1 << (ctx % 8)) != 0 {
+            *counts = [0; 256];
+        }
+    }
+    result
+}
+
+/// Decode data produced by [`encode_order1`]
+pub fn decode_order1(encoded: &[u8]) -> Option<Vec<u8>> {
+    Decoder::order1(encoded)?.collect_exact()
+}
+
+/// Encode short data with its canonical code inline and no length prefix,
+/// for blocks whose header already records the length.
+///
+/// Layout: symbol count - 1 + symbol/length pairs + bitstream. `data` must
+/// not be empty.
+pub fn encode_compact(data: &[u8]) -> Vec<u8> {
+    let mut freq = [0u64; 256];
+    for &byte in data {
+        freq[byte as usize] += 1;
+    }
+    let lengths = HuffmanTable::from_frequencies(&freq).lengths;
+    let table = HuffmanTable::from_lengths(lengths).expect("Huffman lengths form a prefix code");
+
+    let used: Vec<u8> = (0..=255u8).filter(|&s| lengths[s as usize] > 0).collect();
+    let mut result = Vec::with_capacity(1 + 2 * used.len() + data.len());
+    result.push((used.len() - 1) as u8);
+    for &symbol in &used {
+        result.extend_from_slice(&[symbol, lengths[symbol as usize]]);
+    }
+
+    let mut bits: BitVec<u8, Msb0> = BitVec::new();
+    for &byte in data {
+        bits.extend_from_bitslice(&table.codes[byte as usize]);
+    }
+    result.extend_from_slice(bits.as_raw_slice());
+    result
+}
+
+/// Decode the `len` bytes encoded by [`encode_compact`]
+pub fn decode_compact(encoded: &[u8], len: usize) -> Option<Vec<u8>> {
+    Decoder::compact(encoded, len)?.collect_exact()
+}
+
+/// Adaptive counts are halved once their total passes this, so statistics
+/// from long-gone regions of the input fade out
+const ADAPTIVE_AGING_LIMIT: u64 = 1 << 10;
+
+/// Frequency model mirrored by the adaptive encoder and decoder
+struct AdaptiveModel {
+    freq: [u64; 256],
+    total: u64,
+    rebuild_interval: usize,
+    since_rebuild: usize,
+    table: HuffmanTable,
+}
+
+impl AdaptiveModel {
+    fn new(rebuild_interval: usize) -> Self {
+        // Every symbol starts with count 1 so it always has a code
+        let freq = [1u64; 256];
+        AdaptiveModel {
+            freq,
+            total: 256,
+            rebuild_interval: rebuild_interval.max(1),
+            since_rebuild: 0,
+            table: HuffmanTable::from_frequencies(&freq),
+        }
+    }
+
+    /// Count `symbol`, rebuilding the table every `rebuild_interval`
+    /// symbols. Returns `true` when the table changed.
+    fn update(&mut self, symbol: u8) -> bool {
+        self.freq[symbol as usize] += 1;
+        self.total += 1;
+        self.since_rebuild += 1;
+        if self.since_rebuild < self.rebuild_interval {
+            return false;
+        }
+
+        if self.total > ADAPTIVE_AGING_LIMIT {
+            self.freq.iter_mut().for_each(|f| *f = (*f / 2).max(1));
+            self.total = self.freq.iter().sum();
+        }
+        self.table = HuffmanTable::from_frequencies(&self.freq);
+        self.since_rebuild = 0;
+        true
+    }
+}
+
+/// Encode data with an adaptive code, rebuilding the tree from running
+/// counts every `rebuild_interval` symbols. No table is stored: the decoder
+/// replays the same model, so it must be given the same interval.
+pub fn encode_adaptive(data: &[u8], rebuild_interval: usize) -> Vec<u8> {
+    let mut model = AdaptiveModel::new(rebuild_interval);
+    let mut bits: BitVec<u8, Msb0> = BitVec::new();
+    for &byte in data {
+        bits.extend_from_bitslice(&model.table.codes[byte as usize]);
+        model.update(byte);
+    }
+
+    let mut result = Vec::with_capacity(8 + bits.len() / 8 + 1);
+    result.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    result.extend_from_slice(bits.as_raw_slice());
+    result
+}
+
+/// Decode data produced by [`encode_adaptive`] with the same interval
+pub fn decode_adaptive(encoded: &[u8], rebuild_interval: usize) -> Option<Vec<u8>> {
+    Decoder::adaptive(encoded, rebuild_interval)?.collect_exact()
+}
+
+/// Code tables a [`Decoder`] walks, per coding mode
+enum DecoderModel {
+    Static(Vec<[u32; 2]>),
+    Order1 { tries: Vec<Option<Vec<[u32; 2]>>>, prev: u8 },
+    Adaptive { model: Box<AdaptiveModel>, trie: Vec<[u32; 2]> },
+}
+
+/// Symbol-at-a-time decoder for every Huffman mode.
+///
+/// Yields exactly the encoded length's worth of symbols, or stops early on
+/// a truncated stream or an unassigned code, so callers compare the yielded
+/// count with [`Decoder::remaining`] taken up front. Lets callers stream
+/// output without holding a whole decoded chunk.
+pub struct Decoder<'a> {
+    bits: &'a BitSlice<u8, Msb0>,
+    pos: usize,
+    remaining: usize,
+    model: DecoderModel,
+}
+
+impl<'a> Decoder<'a> {
+    /// Decoder for data produced by [`encode`] with its serialized table
+    pub fn new(encoded: &'a [u8], table_data: &[u8]) -> Option<Self> {
+        Self::with_table(encoded, &HuffmanTable::deserialize(table_data)?)
+    }
+
+    /// Decoder for data produced by [`encode_with_table`] with `table`
+    pub fn with_table(encoded: &'a [u8], table: &HuffmanTable) -> Option<Self> {
+        Self::with_model(encoded, 8, DecoderModel::Static(table.decode_trie()?))
+    }
+
+    /// Decoder for data produced by [`encode_order1`]
+    pub fn order1(encoded: &'a [u8]) -> Option<Self> {
+        let bitmap = encoded.get(8..40)?;
+
+        let mut pos = 40;
+        let mut tries: Vec<Option<Vec<[u32; 2]>>> = (0..256).map(|_| None).collect();
+        for (ctx, trie) in tries.iter_mut().enumerate() {
+            if bitmap[ctx / 8] & (1 << (ctx % 8)) == 0 {
+                continue;
+            }
+            let count = *encoded.get(pos)? as usize + 1;
+            let pairs = encoded.get(pos + 1..pos + 1 + 2 * count)?;
+            pos += 1 + 2 * count;
+
+            let mut lengths = [0u8; 256];
+            for pair in pairs.chunks_exact(2) {
+                lengths[pair[0] as usize] = pair[1];
+            }
+            *trie = Some(HuffmanTable::from_lengths(lengths)?.decode_trie()?);
+        }
+
+        Self::with_model(encoded, pos, DecoderModel::Order1 { tries, prev: 0 })
+    }
+
+    /// Decoder for the `len` symbols of data produced by [`encode_compact`]
+    pub fn compact(encoded: &'a [u8], len: usize) -> Option<Self> {
+        let count = *encoded.first()? as usize + 1;
+        let pairs = encoded.get(1..1 + 2 * count)?;
+        let mut lengths = [0u8; 256];
+        for pair in pairs.chunks_exact(2) {
+            lengths[pair[0] as usize] = pair[1];
+        }
+        let trie = HuffmanTable::from_lengths(lengths)?.decode_trie()?;
+
+        Self::with_length(&encoded[1 + 2 * count..], len, DecoderModel::Static(trie))
+    }
+
+    /// Decoder for data produced by [`encode_adaptive`] with the same interval
+    pub fn adaptive(encoded: &'a [u8], rebuild_interval: usize) -> Option<Self> {
+        let model = Box::new(AdaptiveModel::new(rebuild_interval));
+        let trie = model.table.decode_trie()?;
+        Self::with_model(encoded, 8, DecoderModel::Adaptive { model, trie })
+    }
+
+    /// Read the length prefix; the bitstream starts at `body`
+    fn with_model(encoded: &'a [u8], body: usize, model: DecoderModel) -> Option<Self> {
+        let remaining = u64::from_le_bytes(encoded.get(0..8)?.try_into().ok()?) as usize;
+        Self::with_length(encoded.get(body..)?, remaining, model)
+    }
+
+    /// Decoder for `remaining` symbols of the bitstream `bits`
+    fn with_length(bits: &'a [u8], remaining: usize, model: DecoderModel) -> Option<Self> {
+        let bits = BitSlice::<u8, Msb0>::from_slice(bits);
+
+        // Every symbol costs at least one bit
+        if remaining > bits.len() {
+            return None;
+        }
+
+        Some(Decoder { bits, pos: 0, remaining, model })
+    }
+
+    /// Symbols still to be decoded
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Decode everything, failing unless the full length comes out
+    fn collect_exact(self) -> Option<Vec<u8>> {
+        let len = self.remaining;
+        let mut result = Vec::with_capacity(len);
+        result.extend(self);
+        (result.len() == len).then_some(result)
+    }
+}
+
+impl Decoder<'_> {
+    /// Walk the trie for one symbol and update the model
+    fn decode_symbol(&mut self) -> Option<u8> {
+        let trie = match &self.model {
+            DecoderModel::Static(trie) | DecoderModel::Adaptive { trie, .. } => trie,
+            DecoderModel::Order1 { tries, prev } => tries[*prev as usize].as_ref()?,
+        };
+        let mut node = 0usize;
+        let symbol = loop {
+            let bit = *self.bits.get(self.pos)?;
+            self.pos += 1;
+            let slot = trie[node][bit as usize];
+            if slot & LEAF != 0 {
+                break (slot & !LEAF) as u8;
+            } else if slot == 0 {
+                return None; // Unassigned code
+            }
+            node = slot as usize;
+        };
+
+        match &mut self.model {
+            DecoderModel::Static(_) => {}
+            DecoderModel::Order1 { prev, .. } => *prev = symbol,
+            DecoderModel::Adaptive { model, trie } => {
+                if model.update(symbol) {
+                    *trie = model.table.decode_trie()?;
+                }
+            }
+        }
+        Some(symbol)
+    }
+}
+
+impl Iterator for Decoder<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        match self.decode_symbol() {
+            Some(symbol) => {
+                self.remaining -= 1;
+                Some(symbol)
+            }
+            None => {
+                // Corrupt stream: stay exhausted
+                self.remaining = 0;
+                None
+            }
+        }
     }
-    
-    Some(result)
 }
 
 #[cfg(test)]
@@ -192,7 +840,95 @@ mod tests {
     #[test]
     fn test_huffman_encode() {
         let data = b"abracadabra";
-        let (encoded, table) = encode(data);
+        let (encoded, _table) = encode(data);
         assert!(encoded.len() < data.len() + 8 || data.len() < 8);
     }
+
+    #[test]
+    fn test_huffman_roundtrip() {
+        for data in [&b"abracadabra"[..], b"aaaaaaaa", b""] {
+            let (encoded, table) = encode(data);
+            let decoded = decode(&encoded, &table.serialize().unwrap()).unwrap();
+            assert_eq!(decoded, data);
+        }
+    }
+
+    #[test]
+    fn test_degenerate_frequencies_are_length_limited() {
+        // Fibonacci counts give an unrestricted tree one level per symbol
+        let mut freq = [0u64; 256];
+        let (mut a, mut b) = (1u64, 1u64);
+        for count in freq.iter_mut().take(90) {
+            *count = a;
+            (a, b) = (b, a + b);
+        }
+        assert!(HuffmanTable::build_tree(&freq).lengths.iter().any(|&len| len > MAX_CODE_LEN));
+
+        let table = HuffmanTable::from_frequencies(&freq);
+        assert!(table.lengths.iter().all(|&len| len <= MAX_CODE_LEN));
+        assert_eq!(table.lengths.iter().filter(|&&len| len > 0).count(), 90);
+        assert!(table.decode_trie().is_some());
+        let restored = HuffmanTable::deserialize(&table.serialize().unwrap()).unwrap();
+        assert_eq!(restored, table);
+
+        // A code too long for its size prefix is rejected, not truncated
+        let mut oversized = HuffmanTable::empty_table();
+        oversized.codes[7] = BitVec::repeat(true, 256 * 8);
+        assert!(matches!(oversized.serialize(), Err(CompressionError::HuffmanEncoding(_))));
+    }
+
+    #[test]
+    fn test_table_equality_and_fingerprint() {
+        let freq = simd::histogram_scalar(b"abracadabra, the quantum cadabra");
+        let a = HuffmanTable::from_frequencies(&freq);
+        let b = HuffmanTable::from_frequencies(&freq);
+        assert_eq!(a, b);
+        assert_eq!(a.canonical_fingerprint(), b.canonical_fingerprint());
+
+        let other = HuffmanTable::from_frequencies(&simd::histogram_scalar(b"zzzzzzzzzyyyx"));
+        assert_ne!(a, other);
+        assert_ne!(a.canonical_fingerprint(), other.canonical_fingerprint());
+
+        let canonical = HuffmanTable::from_lengths(a.lengths).unwrap();
+        assert_eq!(canonical.canonical_fingerprint(), a.canonical_fingerprint());
+    }
+
+    #[test]
+    fn test_accumulated_halves_match_whole() {
+        let data: Vec<u8> = (0..10_001u32).map(|i| (i * i % 97) as u8).collect();
+        let (front, back) = data.split_at(4321);
+        let mut accumulator = FrequencyAccumulator::new();
+        accumulator.add(front);
+        accumulator.add(back);
+
+        let whole = simd::histogram_scalar(&data);
+        assert_eq!(accumulator.frequencies(), &whole);
+        assert_eq!(accumulator.build(), HuffmanTable::from_frequencies(&whole));
+        assert_eq!(accumulator.build(), encode(&data).1);
+    }
+
+    #[test]
+    fn test_order1_roundtrip() {
+        for data in [&b"abracadabra, abracadabra"[..], b"aaaaaaaa", b"z", b""] {
+            assert_eq!(decode_order1(&encode_order1(data)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_reused_counter_matches_fresh() {
+        let mut counter = FrequencyCounter::new();
+        for data in [&b"first chunk of text"[..], b"a different second chunk", b"", b"zzzz"] {
+            assert_eq!(encode_order1_with(data, &mut counter), encode_order1(data));
+        }
+    }
+
+    #[test]
+    fn test_adaptive_roundtrip() {
+        let data: Vec<u8> = (0..5000u32).map(|i| (i / 500) as u8 * 3 + (i % 3) as u8).collect();
+        for interval in [1, 7, 64, 100_000] {
+            let encoded = encode_adaptive(&data, interval);
+            assert_eq!(decode_adaptive(&encoded, interval).unwrap(), data);
+        }
+        assert_eq!(decode_adaptive(&encode_adaptive(b"", 16), 16).unwrap(), b"");
+    }
 }
@@ -3,25 +3,164 @@
 //! Hybrid compression using Matrix Product States (MPS) tensor networks
 //! combined with adaptive Huffman coding, optimized for RTX 4070.
 
+pub mod archive;
+pub mod bpe;
+#[cfg(feature = "decompress-cache")]
+pub mod cache;
+pub mod circuit;
 pub mod mps;
 pub mod huffman;
 pub mod compress;
+pub mod dictionary;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod error;
+pub mod format;
+pub mod gpu;
+pub mod lossy;
+pub mod manifest;
+pub mod pipeline;
+pub mod random_access;
+pub mod remap;
+pub mod report;
+pub mod rle;
+pub mod schema;
+pub mod simd;
+pub mod sparse;
+pub mod stats;
+pub mod store;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod linalg;
+mod logging;
+mod npz;
 
-pub use compress::{compress, decompress};
-pub use error::CompressionError;
+pub use archive::{create_archive, extract_archive, extract_entry};
+#[cfg(feature = "decompress-cache")]
+pub use cache::DecompressCache;
+pub use circuit::{compress_circuit, decompress_circuit, CircuitStreams};
+pub use compress::{
+    compress, compress_deadline, compress_f64, compress_refine, compress_streaming, compress_timed, compress_to_error,
+    compress_u16, compress_with, decompress, decompress_borrowed, decompress_deadline, decompress_f64, decompress_from,
+    decompress_member, decompress_reader, decompress_segments_to, decompress_to_writer, decompress_u16,
+    decompress_verified, decompress_with_dictionary, decompress_with_stats, inspect, seal, unseal, Endian,
+};
+pub use dictionary::train_dictionary;
+#[cfg(feature = "encryption")]
+pub use encryption::decompress_with_key;
+pub use error::{ChecksumScope, CompressionError};
+pub use gpu::list_gpus;
+pub use huffman::{EntropyModel, FrequencyAccumulator, HuffmanTableCache};
+pub use lossy::ErrorMetric;
+pub use manifest::{compress_file_with_manifest, read_manifest, Manifest};
+pub use mps::Precision;
+pub use pipeline::{Pipeline, Stage};
+pub use random_access::RandomAccessArchive;
+pub use report::{compress_report, CandidateResult, CandidateStrategy, StrategyComparison};
+pub use schema::{read_schema, Schema};
+pub use simd::TargetPlatform;
+pub use store::{chunk_hash, compress_with_store, decompress_with_store, ChunkStore};
+
+use std::collections::HashMap;
 
 /// Configuration for the compression algorithm
 #[derive(Debug, Clone)]
 pub struct Config {
-    /// Maximum tensor rank for MPS decomposition
+    /// Maximum tensor rank for MPS decomposition (0 keeps no tensors: MPS
+    /// blocks then hold the input uncoded, as their residual)
     pub max_rank: usize,
     /// Chunk size for parallel processing (bytes)
     pub chunk_size: usize,
     /// Use GPU acceleration if available
     pub use_gpu: bool,
+    /// GPU to compress on (`None` = device 0); an index past `list_gpus()`
+    /// falls back to the CPU with a warning
+    pub gpu_device: Option<usize>,
     /// VRAM budget in bytes (default: 10GB of 12GB)
     pub vram_budget: usize,
+    /// Close the MPS chain into a ring (periodic boundary conditions)
+    pub periodic: bool,
+    /// Element type MPS tensors are stored with; `F32` halves their size,
+    /// and lossless blocks absorb the rounding in the residual
+    pub precision: Precision,
+    /// Decompose real data in real arithmetic ([`mps::MPS::from_real`]) and
+    /// store its MPS tensors without imaginary parts, halving their size
+    pub exploit_real: bool,
+    /// SIMD width hint; `Scalar` forces portable code for reproducibility
+    pub target_platform: TargetPlatform,
+    /// Inputs shorter than this many bytes take the scalar paths whatever
+    /// the CPU supports, sparing them the SIMD setup cost (0 = never)
+    pub simd_threshold: usize,
+    /// Byte-pair merges learned per chunk before entropy coding (0 = off)
+    pub bpe_merges: usize,
+    /// Also try chunks with few distinct byte values over a dense alphabet
+    /// `0..k`, storing the remap table in the block (roughly doubles the
+    /// encoding cost of such chunks)
+    pub symbol_remap: bool,
+    /// Zero-byte fraction at which a chunk also tries the sparse
+    /// bitmap + values form (above 1.0 = off)
+    pub sparse_threshold: f64,
+    /// Symbols between adaptive Huffman tree rebuilds (0 = adaptive coder
+    /// off); smaller intervals track shifting statistics more closely
+    pub huffman_rebuild_interval: usize,
+    /// Context model for Huffman-only blocks
+    pub entropy_model: EntropyModel,
+    /// Dictionary from `train_dictionary`; chunks may be coded against it,
+    /// and must then be read with `decompress_with_dictionary`
+    pub dictionary: Option<Vec<u8>>,
+    /// Shared cache of order-0 Huffman tables, reused when a chunk's byte
+    /// histogram recurs (`None` = build every table)
+    pub huffman_table_cache: Option<HuffmanTableCache>,
+    /// Decompress the output inside `compress` and fail unless it matches
+    /// the input exactly (roughly doubles the cost)
+    pub validate_roundtrip: bool,
+    /// Chunk boundaries snap to multiples of this record size (0 or 1 = off)
+    pub chunk_alignment: usize,
+    /// Adjacent chunks are merged until each block spans at least this many
+    /// bytes (0 = off), trading parallelism for less per-block overhead
+    pub min_block_size: usize,
+    /// Byte order of multi-byte samples passed to `compress_u16`
+    pub sample_endian: Endian,
+    /// Largest chunk `decompress_to_writer` reconstructs in memory (bytes);
+    /// bigger chunks are streamed to the writer in pieces of this size
+    pub max_chunk_memory: usize,
+    /// Explicit transform stages run on every chunk in place of the
+    /// automatic method search (`None` = search)
+    pub pipeline: Option<Pipeline>,
+    /// Worker threads for chunk encoding (0 = rayon's global pool)
+    pub threads: usize,
+    /// Abort on the first chunk that fails to encode; when off, such chunks
+    /// are stored verbatim and listed in `CompressionStats::degraded_chunks`
+    pub fail_fast: bool,
+    /// Open the container with a CBOR schema block recording how it was
+    /// produced, so it decodes without outside metadata (chunked layouts
+    /// only; tiny inputs then skip the compact form)
+    pub embed_schema: bool,
+    /// Fail rather than return a container larger than this multiple of
+    /// the input (`None` = no limit). The header and block framing a stored
+    /// copy would carry are not counted, so small inputs are not refused
+    /// for fixed overhead.
+    pub abort_on_expansion_over: Option<f64>,
+    /// How `compress_to_error` measures the error it must stay within
+    pub error_metric: ErrorMetric,
+    /// Container layout to write (1 = single MPS block, 2 = chunked,
+    /// 3 = chunked with a data CRC); pin it for archives that must not
+    /// change shape as defaults evolve
+    pub format_version: u8,
+    /// Same input and config always give byte-identical output. Containers
+    /// record no timestamps, names or host details in any case; this also
+    /// keeps `compress_deadline` from letting the clock pick the result.
+    /// Encrypted output still differs per call, as each draws a fresh nonce.
+    pub deterministic: bool,
+    /// AES-256-GCM key to encrypt the finished container with (`None` =
+    /// plaintext); read it back with `decompress_with_key`
+    #[cfg(feature = "encryption")]
+    pub encryption_key: Option<[u8; 32]>,
+    /// Most verbose level the compressor logs at with feature `logging`
+    /// (the installed logger still filters); no effect without it. Warnings
+    /// only by default; `Info` adds per-call summaries such as a stored
+    /// fallback
+    pub log_level: log::LevelFilter,
 }
 
 impl Default for Config {
@@ -30,7 +169,36 @@ impl Default for Config {
             max_rank: 64,
             chunk_size: 1024 * 1024, // 1MB chunks
             use_gpu: true,
+            gpu_device: None,
             vram_budget: 10 * 1024 * 1024 * 1024, // 10GB
+            periodic: false,
+            precision: Precision::F64,
+            exploit_real: false,
+            target_platform: TargetPlatform::Auto,
+            simd_threshold: 4096,
+            bpe_merges: 0,
+            symbol_remap: false,
+            sparse_threshold: 0.75,
+            huffman_rebuild_interval: 256,
+            entropy_model: EntropyModel::Order0,
+            dictionary: None,
+            huffman_table_cache: None,
+            validate_roundtrip: false,
+            chunk_alignment: 1,
+            min_block_size: 0,
+            sample_endian: Endian::Little,
+            max_chunk_memory: 64 * 1024 * 1024, // 64MB
+            pipeline: None,
+            threads: 0,
+            fail_fast: true,
+            embed_schema: false,
+            abort_on_expansion_over: None,
+            error_metric: ErrorMetric::LInf,
+            format_version: compress::VERSION,
+            deterministic: false,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            log_level: log::LevelFilter::Warn,
         }
     }
 }
@@ -44,6 +212,46 @@ pub struct CompressionStats {
     pub processing_time_ms: f64,
     pub tensor_rank_used: usize,
     pub vram_peak_bytes: usize,
+    /// GPU the chunks were encoded on (`None` = CPU)
+    pub gpu_device: Option<usize>,
+    /// Reconstruction error of lossy output (`None` when lossless)
+    pub fidelity: Option<Fidelity>,
+    /// Every chunk was stored verbatim; nothing compressed
+    pub stored_fallback: bool,
+    /// Chunk counts and sizes broken down by the strategy that coded them
+    pub per_strategy: HashMap<Strategy, StrategyStats>,
+    /// Chunks that failed to encode and were stored verbatim instead (only
+    /// with `Config::fail_fast` off)
+    pub degraded_chunks: Vec<usize>,
+    /// Bytes the MPS stage produced (serialized chain + residual) before
+    /// Huffman coding, summed over the blocks coded with an MPS method
+    pub mps_serialized_size: usize,
+    /// Order-0 entropy bound of the input over the block payload bits
+    /// actually written (headers and framing excluded). Near 1.0 the codec
+    /// has no headroom left at order 0; above it, it exploits correlations
+    /// order 0 cannot see. 0.0 where not measured (chunk references).
+    pub entropy_efficiency: f64,
+}
+
+/// How a chunk was coded, as tallied in `CompressionStats::per_strategy`
+pub type Strategy = compress::BlockMethod;
+
+/// Totals for the chunks coded with one [`Strategy`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+pub struct StrategyStats {
+    pub chunks: usize,
+    pub original_size: usize,
+    /// Payload bytes including block framing
+    pub compressed_size: usize,
+    /// Mean of the per-chunk compression ratios
+    pub average_ratio: f64,
+}
+
+/// Achieved reconstruction error of a lossy compression
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct Fidelity {
+    pub metric: ErrorMetric,
+    pub value: f64,
 }
 
 impl CompressionStats {
@@ -55,6 +263,25 @@ impl CompressionStats {
             processing_time_ms: time_ms,
             tensor_rank_used: 0,
             vram_peak_bytes: 0,
+            gpu_device: None,
+            fidelity: None,
+            stored_fallback: false,
+            per_strategy: HashMap::new(),
+            degraded_chunks: Vec::new(),
+            mps_serialized_size: 0,
+            entropy_efficiency: 0.0,
         }
     }
 }
+
+/// Container statistics gathered while decompressing or inspecting
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DecompressionStats {
+    /// Container format version
+    pub version: u8,
+    pub block_count: usize,
+    pub compressed_size: usize,
+    pub decompressed_size: usize,
+    /// CRC32 of each block payload, in container order
+    pub block_checksums: Vec<u32>,
+}
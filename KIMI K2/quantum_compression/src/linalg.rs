@@ -0,0 +1,352 @@
+//! Dense linear algebra helpers for tensor decomposition
+//!
+//! Small, dependency-free routines (Hermitian Jacobi eigensolver, Householder
+//! QR and a Gram-matrix truncated SVD) sized for MPS bond matrices, generic
+//! over complex and real elements.
+
+use ndarray::{Array2, LinalgScalar};
+use num_complex::Complex64;
+use std::iter::Sum;
+
+const JACOBI_MAX_SWEEPS: usize = 60;
+const JACOBI_EPS: f64 = 1e-15;
+
+/// Matrix element: `Complex64`, or `f64` for real input, where every
+/// product costs one multiply instead of four
+pub(crate) trait Scalar: LinalgScalar + PartialEq + Sum {
+    fn conj(self) -> Self;
+    fn re(self) -> f64;
+    fn norm(self) -> f64;
+    fn norm_sqr(self) -> f64;
+    /// Multiply by a real factor
+    fn scale(self, factor: f64) -> Self;
+}
+
+impl Scalar for Complex64 {
+    fn conj(self) -> Self {
+        Complex64::conj(&self)
+    }
+    fn re(self) -> f64 {
+        self.re
+    }
+    fn norm(self) -> f64 {
+        Complex64::norm(self)
+    }
+    fn norm_sqr(self) -> f64 {
+        Complex64::norm_sqr(&self)
+    }
+    fn scale(self, factor: f64) -> Self {
+        self * factor
+    }
+}
+
+impl Scalar for f64 {
+    fn conj(self) -> Self {
+        self
+    }
+    fn re(self) -> f64 {
+        self
+    }
+    fn norm(self) -> f64 {
+        self.abs()
+    }
+    fn norm_sqr(self) -> f64 {
+        self * self
+    }
+    fn scale(self, factor: f64) -> Self {
+        self * factor
+    }
+}
+
+/// Truncated singular value decomposition `A ≈ U · diag(S) · Vt`
+pub(crate) struct Svd<T> {
+    pub u: Array2<T>,
+    pub s: Vec<f64>,
+    pub vt: Array2<T>,
+}
+
+/// Eigen-decomposition of a Hermitian (or real symmetric) matrix stored
+/// row-major (`n × n`).
+///
+/// Returns eigenvalues in descending order and the matching eigenvectors
+/// as the columns of a row-major `n × n` matrix.
+pub(crate) fn hermitian_eigen<T: Scalar>(mut h: Vec<T>, n: usize) -> (Vec<f64>, Vec<T>) {
+    let mut v = vec![T::zero(); n * n];
+    for i in 0..n {
+        v[i * n + i] = T::one();
+    }
+
+    let scale: f64 = h.iter().map(|x| x.norm_sqr()).sum::<f64>().max(f64::MIN_POSITIVE);
+
+    for _ in 0..JACOBI_MAX_SWEEPS {
+        let mut off = 0.0;
+        for p in 0..n {
+            for q in p + 1..n {
+                off += h[p * n + q].norm_sqr();
+            }
+        }
+        if off <= JACOBI_EPS * JACOBI_EPS * scale {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                let g = h[p * n + q];
+                let g_abs = g.norm();
+                if g_abs <= f64::MIN_POSITIVE {
+                    continue;
+                }
+                let a = h[p * n + p].re();
+                let b = h[q * n + q].re();
+                let zeta = (b - a) / (2.0 * g_abs);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                let e = g.scale(1.0 / g_abs);
+
+                // H ← H · J  (columns p, q)
+                for k in 0..n {
+                    let hp = h[k * n + p];
+                    let hq = h[k * n + q];
+                    h[k * n + p] = hp.scale(c) - (hq * e.conj()).scale(s);
+                    h[k * n + q] = (hp * e).scale(s) + hq.scale(c);
+                }
+                // H ← Jᴴ · H  (rows p, q)
+                for k in 0..n {
+                    let hp = h[p * n + k];
+                    let hq = h[q * n + k];
+                    h[p * n + k] = hp.scale(c) - (hq * e).scale(s);
+                    h[q * n + k] = (hp * e.conj()).scale(s) + hq.scale(c);
+                }
+                // V ← V · J
+                for k in 0..n {
+                    let vp = v[k * n + p];
+                    let vq = v[k * n + q];
+                    v[k * n + p] = vp.scale(c) - (vq * e.conj()).scale(s);
+                    v[k * n + q] = (vp * e).scale(s) + vq.scale(c);
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| h[j * n + j].re().total_cmp(&h[i * n + i].re()));
+
+    let values = order.iter().map(|&i| h[i * n + i].re()).collect();
+    let mut vectors = vec![T::zero(); n * n];
+    for (dst, &src) in order.iter().enumerate() {
+        for k in 0..n {
+            vectors[k * n + dst] = v[k * n + src];
+        }
+    }
+
+    (values, vectors)
+}
+
+/// Truncated SVD keeping at most `max_rank` singular values and dropping
+/// those below `rel_tol · σ_max`. Always keeps at least one value.
+///
+/// Works on the Gram matrix of the smaller dimension, which is cheap for
+/// the wide/tall unfoldings produced by tensor-train sweeps.
+pub(crate) fn truncated_svd<T: Scalar>(a: &Array2<T>, max_rank: usize, rel_tol: f64) -> Svd<T> {
+    let (m, n) = a.dim();
+    let wide = m <= n;
+    let k = m.min(n);
+
+    // Gram matrix G = A·Aᴴ (wide) or Aᴴ·A (tall)
+    let a_std = a.as_standard_layout();
+    let a_slice = a_std.as_slice().expect("standard layout");
+    let mut gram = vec![T::zero(); k * k];
+    if wide {
+        for i in 0..m {
+            let ri = &a_slice[i * n..(i + 1) * n];
+            for j in i..m {
+                let rj = &a_slice[j * n..(j + 1) * n];
+                let dot: T = ri.iter().zip(rj).map(|(&x, &y)| x * y.conj()).sum();
+                gram[i * k + j] = dot;
+                gram[j * k + i] = dot.conj();
+            }
+        }
+    } else {
+        for row in a_slice.chunks_exact(n) {
+            for i in 0..n {
+                let xi = row[i].conj();
+                for j in i..n {
+                    gram[i * k + j] = gram[i * k + j] + xi * row[j];
+                }
+            }
+        }
+        for i in 0..n {
+            for j in 0..i {
+                gram[i * k + j] = gram[j * k + i].conj();
+            }
+        }
+    }
+
+    let (eigvals, eigvecs) = hermitian_eigen(gram, k);
+    let sigma_max = eigvals.first().copied().unwrap_or(0.0).max(0.0).sqrt();
+    let cutoff = rel_tol * sigma_max;
+    let rank = eigvals
+        .iter()
+        .take(max_rank.max(1))
+        .take_while(|&&l| l > 0.0 && l.sqrt() > cutoff)
+        .count()
+        .max(1);
+
+    let s: Vec<f64> = eigvals[..rank].iter().map(|&l| l.max(0.0).sqrt()).collect();
+    let inv = |sv: f64| if sv > 0.0 { 1.0 / sv } else { 0.0 };
+
+    if wide {
+        // U = eigenvectors, Vt = Σ⁻¹ Uᴴ A
+        let mut u = Array2::zeros((m, rank));
+        for i in 0..m {
+            for r in 0..rank {
+                u[[i, r]] = eigvecs[i * k + r];
+            }
+        }
+        let mut vt = Array2::zeros((rank, n));
+        for r in 0..rank {
+            let w = inv(s[r]);
+            let row = vt.row_mut(r).into_slice().expect("contiguous row");
+            for i in 0..m {
+                let coeff = eigvecs[i * k + r].conj().scale(w);
+                if coeff == T::zero() {
+                    continue;
+                }
+                for (dst, &x) in row.iter_mut().zip(&a_slice[i * n..(i + 1) * n]) {
+                    *dst = *dst + coeff * x;
+                }
+            }
+        }
+        Svd { u, s, vt }
+    } else {
+        // Vt = eigenvectorsᴴ, U = A V Σ⁻¹
+        let mut vt = Array2::zeros((rank, n));
+        for r in 0..rank {
+            for j in 0..n {
+                vt[[r, j]] = eigvecs[j * k + r].conj();
+            }
+        }
+        let mut u = Array2::zeros((m, rank));
+        for (i, row) in a_slice.chunks_exact(n).enumerate() {
+            for r in 0..rank {
+                let w = inv(s[r]);
+                let dot: T = (0..n).map(|j| row[j] * eigvecs[j * k + r]).sum();
+                u[[i, r]] = dot.scale(w);
+            }
+        }
+        Svd { u, s, vt }
+    }
+}
+
+/// Thin QR decomposition `A = Q · R` via Householder reflections.
+///
+/// For an `m × n` input with `k = min(m, n)`, `Q` is `m × k` with orthonormal
+/// columns and `R` is `k × n` upper triangular. Rank-deficient inputs still
+/// yield an isometric `Q`.
+pub(crate) fn thin_qr<T: Scalar>(a: &Array2<T>) -> (Array2<T>, Array2<T>) {
+    let (m, n) = a.dim();
+    let k = m.min(n);
+    let mut r = a.to_owned();
+    let mut reflectors: Vec<Vec<T>> = Vec::with_capacity(k);
+
+    for j in 0..k {
+        let x: Vec<T> = (j..m).map(|i| r[[i, j]]).collect();
+        let norm = x.iter().map(|v| v.norm_sqr()).sum::<f64>().sqrt();
+
+        let mut v = x;
+        if norm > 0.0 {
+            let phase = if v[0].norm() > 0.0 { v[0].scale(1.0 / v[0].norm()) } else { T::one() };
+            v[0] = v[0] + phase.scale(norm);
+            let v_norm = v.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+            v.iter_mut().for_each(|c| *c = c.scale(1.0 / v_norm));
+
+            // R[j.., j..] -= 2 v (vᴴ R[j.., j..])
+            for col in j..n {
+                let dot: T = (j..m).map(|i| v[i - j].conj() * r[[i, col]]).sum();
+                for i in j..m {
+                    r[[i, col]] = r[[i, col]] - (v[i - j] * dot).scale(2.0);
+                }
+            }
+        } else {
+            v.iter_mut().for_each(|c| *c = T::zero());
+        }
+        reflectors.push(v);
+    }
+
+    // Q = H₁ ⋯ H_k applied to the first k columns of the identity
+    let mut q = Array2::zeros((m, k));
+    for i in 0..k {
+        q[[i, i]] = T::one();
+    }
+    for (j, v) in reflectors.iter().enumerate().rev() {
+        for col in 0..k {
+            let dot: T = (j..m).map(|i| v[i - j].conj() * q[[i, col]]).sum();
+            for i in j..m {
+                q[[i, col]] = q[[i, col]] - (v[i - j] * dot).scale(2.0);
+            }
+        }
+    }
+
+    let r = r.slice(ndarray::s![..k, ..]).to_owned();
+    (q, r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svd_reconstructs() {
+        let a = Array2::from_shape_fn((4, 7), |(i, j)| {
+            Complex64::new((i * 7 + j) as f64 * 0.1, (i as f64 - j as f64) * 0.05)
+        });
+        let svd = truncated_svd(&a, 8, 1e-14);
+        let mut sv = svd.vt.clone();
+        for (r, mut row) in sv.rows_mut().into_iter().enumerate() {
+            row *= Complex64::new(svd.s[r], 0.0);
+        }
+        let rebuilt = svd.u.dot(&sv);
+        for (x, y) in a.iter().zip(rebuilt.iter()) {
+            assert!((x - y).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_real_svd_matches_complex() {
+        let a = Array2::from_shape_fn((5, 9), |(i, j)| ((i * 9 + j * 4) % 11) as f64 - 5.0);
+        let real = truncated_svd(&a, 8, 1e-14);
+        let complex = truncated_svd(&a.mapv(|x| Complex64::new(x, 0.0)), 8, 1e-14);
+        assert_eq!(real.s.len(), complex.s.len());
+        for (x, y) in real.s.iter().zip(&complex.s) {
+            assert!((x - y).abs() < 1e-9);
+        }
+        let mut sv = real.vt.clone();
+        for (r, mut row) in sv.rows_mut().into_iter().enumerate() {
+            row *= real.s[r];
+        }
+        let rebuilt = real.u.dot(&sv);
+        for (x, y) in a.iter().zip(rebuilt.iter()) {
+            assert!((x - y).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_qr_reconstructs() {
+        for (m, n) in [(6, 3), (3, 6), (4, 4)] {
+            let a = Array2::from_shape_fn((m, n), |(i, j)| {
+                Complex64::new(((i * 5 + j * 3) % 7) as f64, (i as f64 - j as f64) * 0.5)
+            });
+            let (q, r) = thin_qr(&a);
+            let rebuilt = q.dot(&r);
+            for (x, y) in a.iter().zip(rebuilt.iter()) {
+                assert!((x - y).norm() < 1e-9);
+            }
+            let gram = q.t().mapv(|c| c.conj()).dot(&q);
+            for ((i, j), g) in gram.indexed_iter() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((g - Complex64::new(expected, 0.0)).norm() < 1e-9);
+            }
+        }
+    }
+}
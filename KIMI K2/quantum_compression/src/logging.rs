@@ -0,0 +1,91 @@
+//! Diagnostics through the `log` facade (feature `logging`)
+//!
+//! Decisions a caller may want explained — an MPS bond clamped at
+//! `Config::max_rank`, an input stored verbatim, a GPU request served by
+//! the CPU — are reported with `emit!` to whatever logger the application
+//! installed, at or below `Config::log_level`. Without the feature the
+//! macro expands to nothing and its message arguments are never evaluated.
+
+/// Log a message at `level` (a [`log::Level`] variant name) unless
+/// `config.log_level` filters it out
+#[cfg(feature = "logging")]
+macro_rules! emit {
+    ($config:expr, $level:ident, $($arg:tt)+) => {
+        if log::Level::$level <= $config.log_level {
+            log::log!(log::Level::$level, $($arg)+);
+        }
+    };
+}
+
+#[cfg(not(feature = "logging"))]
+macro_rules! emit {
+    ($config:expr, $level:ident, $($arg:tt)+) => {
+        let _ = &$config;
+    };
+}
+
+pub(crate) use emit;
+
+/// Process-wide logger for tests, which can install only one
+#[cfg(test)]
+pub(crate) mod capture {
+    use std::sync::{Mutex, Once};
+
+    struct CaptureLogger(Mutex<Vec<(log::Level, String)>>);
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
+
+    /// Install the capturing logger, once per process
+    pub(crate) fn install() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+    }
+
+    /// Every message logged since [`install`] that contains `needle`.
+    /// Tests run concurrently, so filter on text only the test under way
+    /// produces.
+    pub(crate) fn messages(needle: &str) -> Vec<(log::Level, String)> {
+        let messages = LOGGER.0.lock().unwrap();
+        messages.iter().filter(|(_, m)| m.contains(needle)).cloned().collect()
+    }
+}
+
+#[cfg(all(test, feature = "logging"))]
+mod tests {
+    use super::capture;
+    use crate::testing::CorpusKind;
+    use crate::{compress, decompress, Config};
+
+    #[test]
+    fn test_stored_fallback_is_logged() {
+        capture::install();
+        let noise = CorpusKind::Random.generate(11, 4099);
+        let verbose = Config { log_level: log::LevelFilter::Info, ..Config::default() };
+        let (compressed, stats) = compress(&noise, &verbose).unwrap();
+        assert!(stats.stored_fallback);
+        assert_eq!(decompress(&compressed).unwrap(), noise);
+
+        let stored = capture::messages("all 4099 bytes stored verbatim");
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].0, log::Level::Info);
+
+        // The default level keeps the compressor quiet
+        compress(&noise, &Config::default()).unwrap();
+        assert_eq!(capture::messages("all 4099 bytes stored verbatim").len(), 1);
+    }
+}
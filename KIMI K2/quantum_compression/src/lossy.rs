@@ -0,0 +1,146 @@
+//! Bounded-error (near-lossless) coding
+//!
+//! The MPS reconstruction is kept, but the residual against it is quantized
+//! with step `2·near + 1`, so no byte moves by more than `near`. The
+//! largest `near` whose reconstruction still meets the caller's
+//! [`ErrorMetric`] bound is chosen per chunk; since every chunk meets the
+//! bound, so does the whole output.
+
+use serde::{Deserialize, Serialize};
+
+/// How reconstruction error is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorMetric {
+    /// Root-mean-square byte error; the bound is a maximum
+    L2,
+    /// Largest absolute error of any byte; the bound is a maximum
+    #[default]
+    LInf,
+    /// Peak signal-to-noise ratio in dB (peak 255); the bound is a minimum
+    Psnr,
+}
+
+impl ErrorMetric {
+    /// Measure `reconstructed` against `original` (equal lengths)
+    pub fn measure(self, original: &[u8], reconstructed: &[u8]) -> f64 {
+        let diffs = original.iter().zip(reconstructed).map(|(&a, &b)| a.abs_diff(b) as f64);
+        match self {
+            ErrorMetric::LInf => diffs.fold(0.0, f64::max),
+            ErrorMetric::L2 => mean_square(diffs, original.len()).sqrt(),
+            ErrorMetric::Psnr => {
+                let mse = mean_square(diffs, original.len());
+                if mse == 0.0 {
+                    f64::INFINITY
+                } else {
+                    10.0 * (255.0 * 255.0 / mse).log10()
+                }
+            }
+        }
+    }
+
+    /// Whether `value` of this metric satisfies `bound`
+    pub fn within(self, value: f64, bound: f64) -> bool {
+        match self {
+            ErrorMetric::L2 | ErrorMetric::LInf => value <= bound,
+            ErrorMetric::Psnr => value >= bound,
+        }
+    }
+}
+
+fn mean_square(diffs: impl Iterator<Item = f64>, len: usize) -> f64 {
+    if len == 0 {
+        return 0.0;
+    }
+    diffs.map(|d| d * d).sum::<f64>() / len as f64
+}
+
+/// Largest error setting the quantizer accepts (step 255)
+pub const MAX_NEAR: u8 = 127;
+
+/// Quantize `data - approx` so every byte reconstructs within `near`.
+///
+/// With `near == 0` the residual is the lossless wrapping difference;
+/// otherwise each byte is a signed bucket index.
+pub fn quantize_residual(data: &[u8], approx: &[u8], near: u8) -> Vec<u8> {
+    if near == 0 {
+        return data.iter().zip(approx).map(|(&d, &a)| d.wrapping_sub(a)).collect();
+    }
+    let step = 2 * near as i32 + 1;
+    data.iter()
+        .zip(approx)
+        .map(|(&d, &a)| {
+            let r = d as i32 - a as i32;
+            let q = r.signum() * ((r.abs() + near as i32) / step);
+            q as i8 as u8
+        })
+        .collect()
+}
+
+/// Undo [`quantize_residual`]
+pub fn dequantize_residual(approx: &[u8], residual: &[u8], near: u8) -> Vec<u8> {
+    if near == 0 {
+        return approx.iter().zip(residual).map(|(&a, &r)| a.wrapping_add(r)).collect();
+    }
+    let step = 2 * near as i32 + 1;
+    approx
+        .iter()
+        .zip(residual)
+        .map(|(&a, &q)| (a as i32 + q as i8 as i32 * step).clamp(0, 255) as u8)
+        .collect()
+}
+
+/// Largest `near` in `0..=MAX_NEAR` whose reconstruction of `data` from
+/// `approx` meets `bound` under `metric`. Falls back to 0 (lossless).
+pub fn choose_near(data: &[u8], approx: &[u8], metric: ErrorMetric, bound: f64) -> u8 {
+    // Quantization alone bounds the per-byte error by `near`
+    if metric == ErrorMetric::LInf {
+        return bound.floor().clamp(0.0, MAX_NEAR as f64) as u8;
+    }
+
+    let fits = |near: u8| {
+        let restored = dequantize_residual(approx, &quantize_residual(data, approx, near), near);
+        metric.within(metric.measure(data, &restored), bound)
+    };
+
+    // Binary search on the (near-monotone) error; only accepted settings
+    // are ever returned, so the bound holds regardless
+    let (mut lo, mut hi) = (0u8, MAX_NEAR);
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    lo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_and_quantizer_bounds() {
+        let original = [10u8, 20, 30, 40];
+        let reconstructed = [12u8, 20, 27, 40];
+        assert_eq!(ErrorMetric::LInf.measure(&original, &reconstructed), 3.0);
+        assert!((ErrorMetric::L2.measure(&original, &reconstructed) - (13.0f64 / 4.0).sqrt()).abs() < 1e-12);
+        let psnr = ErrorMetric::Psnr.measure(&original, &reconstructed);
+        assert!((psnr - 10.0 * (255.0f64 * 255.0 / 3.25).log10()).abs() < 1e-9);
+        assert_eq!(ErrorMetric::Psnr.measure(&original, &original), f64::INFINITY);
+
+        let data: Vec<u8> = (0..=255).collect();
+        let approx: Vec<u8> = data.iter().map(|&b| b.wrapping_mul(7)).collect();
+        for near in [0, 1, 5, MAX_NEAR] {
+            let restored = dequantize_residual(&approx, &quantize_residual(&data, &approx, near), near);
+            assert!(ErrorMetric::LInf.measure(&data, &restored) <= near as f64);
+        }
+
+        let near = choose_near(&data, &approx, ErrorMetric::L2, 3.0);
+        let restored = dequantize_residual(&approx, &quantize_residual(&data, &approx, near), near);
+        assert!(near > 0);
+        assert!(ErrorMetric::L2.measure(&data, &restored) <= 3.0);
+    }
+}
@@ -0,0 +1,117 @@
+//! Sidecar JSON manifests
+//!
+//! Describes a container (version, per-chunk method/size/checksum, overall
+//! stats) for data pipelines. Everything in a manifest is derived from the
+//! container itself, so [`read_manifest`] regenerates it exactly.
+
+use crate::compress::{compress, parse_container, BlockMethod};
+use crate::error::Result;
+use crate::Config;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Description of one compressed chunk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    pub index: usize,
+    pub method: BlockMethod,
+    pub original_size: usize,
+    pub compressed_size: usize,
+    /// CRC32 of the block payload
+    pub checksum: u32,
+}
+
+/// Size summary of the whole container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestStats {
+    pub original_size: usize,
+    pub compressed_size: usize,
+    pub compression_ratio: f64,
+}
+
+/// Sidecar description of a container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u8,
+    pub chunk_count: usize,
+    pub chunks: Vec<ChunkEntry>,
+    /// CRC32 of the entire container
+    pub checksum: u32,
+    pub stats: ManifestStats,
+}
+
+/// Regenerate the manifest of a container
+pub fn read_manifest(compressed: &[u8]) -> Result<Manifest> {
+    let container = parse_container(compressed)?;
+
+    let chunks: Vec<ChunkEntry> = container
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| ChunkEntry {
+            index,
+            method: block.method,
+            original_size: block.raw_len,
+            compressed_size: block.payload.len(),
+            checksum: block.checksum,
+        })
+        .collect();
+
+    let original_size = chunks.iter().map(|c| c.original_size).sum();
+    Ok(Manifest {
+        version: container.version,
+        chunk_count: chunks.len(),
+        chunks,
+        checksum: crc32fast::hash(compressed),
+        stats: ManifestStats {
+            original_size,
+            compressed_size: compressed.len(),
+            compression_ratio: original_size as f64 / compressed.len() as f64,
+        },
+    })
+}
+
+/// Compress `input` to `output` and write its JSON manifest to `manifest_out`
+pub fn compress_file_with_manifest(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    manifest_out: impl AsRef<Path>,
+    config: &Config,
+) -> Result<Manifest> {
+    let data = fs::read(input)?;
+    let (compressed, _) = compress(&data, config)?;
+    let manifest = read_manifest(&compressed)?;
+
+    fs::write(output, &compressed)?;
+    fs::write(manifest_out, serde_json::to_vec_pretty(&manifest)?)?;
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_matches_container() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("circuit.bin");
+        let output = dir.path().join("circuit.qcmp");
+        let sidecar = dir.path().join("circuit.json");
+
+        let data: Vec<u8> = (0..5000).map(|i| ((i * 31) % 97) as u8).collect();
+        fs::write(&input, &data).unwrap();
+
+        let config = Config { chunk_size: 2048, ..Default::default() };
+        let written = compress_file_with_manifest(&input, &output, &sidecar, &config).unwrap();
+
+        let from_disk: Manifest = serde_json::from_slice(&fs::read(&sidecar).unwrap()).unwrap();
+        let regenerated = read_manifest(&fs::read(&output).unwrap()).unwrap();
+
+        assert_eq!(from_disk, regenerated);
+        assert_eq!(written, regenerated);
+        assert_eq!(regenerated.chunk_count, 3);
+        assert_eq!(regenerated.stats.original_size, data.len());
+    }
+}
@@ -2,201 +2,1598 @@
 //!
 //! Implements quantum-inspired tensor network compression.
 //! Decomposes data into a chain of low-rank tensors for efficient representation.
+//!
+//! The byte sequence is treated as an amplitude vector whose position index is
+//! split into mixed-radix digits, site `k` taking `phys_dims[k]` values (one
+//! digit per site, most significant first; binary by default), then factored
+//! with a truncated tensor-train SVD. With periodic boundary conditions the
+//! chain is closed into a ring, each amplitude is the trace of the matrix
+//! product around it, and padding wraps around to the start of the data so
+//! periodic signals stay low-rank.
 
-use ndarray::{Array1, Array2, ArrayView2, Axis};
+use crate::compress::plan_chunks;
+use crate::linalg::{thin_qr, truncated_svd, Scalar};
+use crate::npz::{self, Array, Dtype};
+use ndarray::linalg::general_mat_mul;
+use ndarray::{s, Array1, Array2, Array3, ArrayView, Axis, IxDyn};
 use num_complex::Complex64;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Singular values below this fraction of the largest are treated as zero
+const SVD_TOLERANCE: f64 = 1e-10;
+
+/// Trailing sites contracted per window: `2^12` amplitudes (64 KiB) at a
+/// time bounds the working set of [`MPS::contract_window`]
+pub const CONTRACT_WINDOW_SITES: usize = 12;
+
+/// Serialization flag: chain is closed into a ring
+const FLAG_PERIODIC: u8 = 0x01;
+
+/// Serialization flag: tensor elements are stored as `f32` pairs
+const FLAG_F32: u8 = 0x02;
+
+/// Serialization flag: tensor elements are real and stored without their
+/// (zero) imaginary parts
+const FLAG_REAL: u8 = 0x04;
+
+/// Imaginary parts of the amplitudes below this fraction of their norm
+/// count as rounding, not as a complex state
+const REAL_TOLERANCE: f64 = 1e-12;
+
+thread_local! {
+    /// Flag set by [`with_cancel`] for decompositions on this thread
+    static CANCEL: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with decompositions on this thread cut short once `flag` is set.
+///
+/// A cancelled sweep finishes the SVD in progress and truncates every
+/// later bond to rank 1, so the chain stays valid, just coarse, and the
+/// rest of the sweep costs about as much as reading the data. Callers that
+/// cancel are expected to discard what `f` returns.
+pub(crate) fn with_cancel<R>(flag: &Arc<AtomicBool>, f: impl FnOnce() -> R) -> R {
+    let outer = CANCEL.with(|cancel| cancel.replace(Some(Arc::clone(flag))));
+    let result = f();
+    CANCEL.with(|cancel| *cancel.borrow_mut() = outer);
+    result
+}
+
+/// Whether the flag installed by [`with_cancel`] on this thread is set
+fn cancelled() -> bool {
+    CANCEL.with(|cancel| cancel.borrow().as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)))
+}
+
+/// Element type tensors are serialized with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Precision {
+    /// 8 bytes per complex element; halves tensor storage at about seven
+    /// significant digits
+    F32,
+    /// 16 bytes per complex element (`Complex64` as held in memory)
+    #[default]
+    F64,
+}
+
+impl Precision {
+    /// Serialized bytes per complex element
+    fn element_size(self) -> usize {
+        match self {
+            Precision::F32 => 8,
+            Precision::F64 => 16,
+        }
+    }
+}
 
 /// A Matrix Product State representation of data
 #[derive(Debug, Clone)]
 pub struct MPS {
-    /// Chain of tensors representing the data
-    pub tensors: Vec<Array2<Complex64>>,
-    /// Bond dimensions between tensors
+    /// Chain of site tensors, each shaped `(left bond, physical, right bond)`
+    pub tensors: Vec<Array3<Complex64>>,
+    /// Bond dimensions between tensors (ring closure last when periodic)
     pub bond_dims: Vec<usize>,
-    /// Physical dimension (typically 256 for bytes)
-    pub phys_dim: usize,
+    /// Physical dimension of each site (radix of its digit of the position
+    /// index)
+    pub phys_dims: Vec<usize>,
+    /// Number of amplitudes represented (before padding to the product of
+    /// `phys_dims`)
+    pub len: usize,
+    /// Periodic boundary conditions: first and last tensors share a bond
+    pub periodic: bool,
+    /// Tensors are real and serialize one value per element; set by
+    /// [`MPS::compress_complex_conjugate_symmetry`] and the real
+    /// decompositions ([`MPS::from_real`])
+    pub real: bool,
 }
 
 impl MPS {
     /// Create MPS from raw byte data using SVD-based decomposition
     pub fn from_bytes(data: &[u8], max_rank: usize) -> Self {
-        let n = data.len();
-        let phys_dim = 256; // Byte values 0-255
-        
-        // Convert bytes to complex amplitudes (quantum state encoding)
-        let amplitudes: Vec<Complex64> = data
-            .iter()
+        Self::from_amplitudes(&Self::encode_amplitudes(data), max_rank, false)
+    }
+
+    /// Create a ring MPS (periodic boundary conditions) from raw byte data
+    pub fn from_bytes_periodic(data: &[u8], max_rank: usize) -> Self {
+        Self::from_amplitudes(&Self::encode_amplitudes(data), max_rank, true)
+    }
+
+    /// Create a real MPS from raw byte data, decomposed in real arithmetic
+    pub fn from_bytes_real(data: &[u8], max_rank: usize, periodic: bool) -> Self {
+        let values: Vec<f64> = data.iter().map(|&b| b as f64 / 255.0).collect();
+        Self::from_real(&values, max_rank, periodic)
+    }
+
+    /// Convert bytes to complex amplitudes (quantum state encoding)
+    fn encode_amplitudes(data: &[u8]) -> Vec<Complex64> {
+        data.iter()
             .map(|&b| Complex64::new(b as f64 / 255.0, 0.0))
-            .collect();
-        
-        // Decompose into MPS using iterative SVD
-        let (tensors, bond_dims) = Self::svd_decompose(&amplitudes, max_rank, phys_dim);
-        
+            .collect()
+    }
+
+    /// Decompose an amplitude vector into an open or periodic MPS of
+    /// binary sites
+    pub fn from_amplitudes(amplitudes: &[Complex64], max_rank: usize, periodic: bool) -> Self {
+        Self::from_amplitudes_with_dims(amplitudes, &vec![2; binary_sites(amplitudes.len())], max_rank, periodic)
+    }
+
+    /// Decompose real values into an open or periodic MPS of binary sites
+    /// with real SVDs, a quarter of the multiplies of the complex path at
+    /// the same ranks, and set [`MPS::real`]
+    pub fn from_real(values: &[f64], max_rank: usize, periodic: bool) -> Self {
+        Self::from_real_with_dims(values, &vec![2; binary_sites(values.len())], max_rank, periodic)
+    }
+
+    /// An open chain of `len` zero amplitudes on binary sites, every bond of
+    /// rank 1: the rank-0 approximation, which leaves the whole input to the
+    /// residual
+    pub(crate) fn zeros(len: usize) -> Self {
+        let n_sites = binary_sites(len);
+        MPS {
+            tensors: vec![Array3::zeros((1, 2, 1)); n_sites],
+            bond_dims: vec![1; n_sites - 1],
+            phys_dims: vec![2; n_sites],
+            len,
+            periodic: false,
+            real: false,
+        }
+    }
+
+    /// Decompose an amplitude vector onto sites of the given physical
+    /// dimensions, most significant digit first (e.g. a byte site of 256
+    /// followed by nibble sites of 16).
+    ///
+    /// # Panics
+    ///
+    /// If `phys_dims` is empty or holds a zero, or its product is smaller
+    /// than `amplitudes.len()`.
+    pub fn from_amplitudes_with_dims(
+        amplitudes: &[Complex64],
+        phys_dims: &[usize],
+        max_rank: usize,
+        periodic: bool,
+    ) -> Self {
+        let (tensors, bond_dims) = Self::svd_decompose(amplitudes, max_rank.max(1), phys_dims, periodic);
+
         MPS {
             tensors,
             bond_dims,
-            phys_dim,
+            phys_dims: phys_dims.to_vec(),
+            len: amplitudes.len(),
+            periodic,
+            real: false,
+        }
+    }
+
+    /// [`MPS::from_amplitudes_with_dims`] for real values, decomposed in
+    /// real arithmetic; the chain has [`MPS::real`] set.
+    ///
+    /// # Panics
+    ///
+    /// As [`MPS::from_amplitudes_with_dims`].
+    pub fn from_real_with_dims(values: &[f64], phys_dims: &[usize], max_rank: usize, periodic: bool) -> Self {
+        let (tensors, bond_dims) = Self::svd_decompose(values, max_rank.max(1), phys_dims, periodic);
+
+        MPS {
+            tensors: tensors.into_iter().map(|t| t.mapv(|x| Complex64::new(x, 0.0))).collect(),
+            bond_dims,
+            phys_dims: phys_dims.to_vec(),
+            len: values.len(),
+            periodic,
+            real: true,
         }
     }
-    
+
+    /// Decompose a row-major tensor of `shape` with one site per axis, the
+    /// axes placed along the chain in `order` (a permutation of the axis
+    /// indices, e.g. from [`contraction_order`]).
+    ///
+    /// # Panics
+    ///
+    /// If `values` does not fill `shape` or `order` is not a permutation of
+    /// its axes.
+    pub fn from_tensor(values: &[Complex64], shape: &[usize], order: &[usize], max_rank: usize) -> Self {
+        let permuted = permute_axes(values, shape, order);
+        let dims: Vec<usize> = order.iter().map(|&axis| shape[axis]).collect();
+        Self::from_amplitudes_with_dims(&permuted, &dims, max_rank, false)
+    }
+
+    /// Contract a chain from [`MPS::from_tensor`] back into a row-major
+    /// tensor in its original axis layout
+    pub fn to_tensor(&self, order: &[usize]) -> Vec<Complex64> {
+        let mut inverse = vec![0; order.len()];
+        for (site, &axis) in order.iter().enumerate() {
+            inverse[axis] = site;
+        }
+        permute_axes(&self.amplitudes(), &self.phys_dims, &inverse)
+    }
+
+    /// The physical dimension shared by every site, or 0 if they differ
+    fn uniform_phys_dim(&self) -> usize {
+        match self.phys_dims.split_first() {
+            Some((&first, rest)) if rest.iter().all(|&d| d == first) => first,
+            _ => 0,
+        }
+    }
+
     /// SVD-based tensor train decomposition
-    fn svd_decompose(
-        amplitudes: &[Complex64],
+    ///
+    /// For periodic chains the first unfolding's rank is split between the
+    /// ring-closing bond and the first inner bond (tensor-ring SVD), so the
+    /// closing bond carries correlations between the chain ends.
+    ///
+    /// Each bond is capped by its unfolding's smaller side as well as by
+    /// `max_rank`, so inputs with no more amplitudes than `max_rank` (where
+    /// the cap never binds) decompose exactly, with bonds no wider than the
+    /// data needs.
+    ///
+    /// # Panics
+    ///
+    /// If `phys_dims` is empty or holds a zero, or its product is smaller
+    /// than `amplitudes.len()`.
+    fn svd_decompose<T: Scalar>(
+        amplitudes: &[T],
         max_rank: usize,
-        phys_dim: usize,
-    ) -> (Vec<Array2<Complex64>>, Vec<usize>) {
-        let n = amplitudes.len();
-        let mut tensors = Vec::new();
-        let mut bond_dims = Vec::new();
-        
-        // For simplicity, use fixed-rank decomposition
-        // In practice, this would use truncated SVD
-        let rank = max_rank.min(phys_dim).min(n);
-        
-        // Create tensor chain
-        let chunk_size = (n + rank - 1) / rank;
-        
-        for (i, chunk) in amplitudes.chunks(chunk_size).enumerate() {
-            let rows = if i == 0 { 1 } else { rank.min(chunk.len()) };
-            let cols = if i == amplitudes.chunks(chunk_size).count() - 1 { 
-                1 
-            } else { 
-                rank.min(chunk.len()) 
+        phys_dims: &[usize],
+        periodic: bool,
+    ) -> (Vec<Array3<T>>, Vec<usize>) {
+        assert!(!phys_dims.is_empty() && !phys_dims.contains(&0), "physical dimensions must be positive");
+        let padded = phys_dims.iter().try_fold(1usize, |n, &d| n.checked_mul(d));
+        assert!(
+            padded.is_some_and(|padded| padded >= amplitudes.len()),
+            "physical dimensions cannot index {} amplitudes",
+            amplitudes.len()
+        );
+        let n_sites = phys_dims.len();
+        let padded = phys_dims.iter().product();
+
+        let mut rest = Array2::zeros((1, padded));
+        if periodic && !amplitudes.is_empty() {
+            for (dst, &src) in rest.iter_mut().zip(amplitudes.iter().cycle()) {
+                *dst = src;
+            }
+        } else {
+            for (dst, &src) in rest.iter_mut().zip(amplitudes) {
+                *dst = src;
+            }
+        }
+
+        let mut tensors = Vec::with_capacity(n_sites);
+        let mut bond_dims = Vec::with_capacity(n_sites);
+        let mut closing = 1;
+        let mut left = 1;
+
+        for (site, &phys_dim) in phys_dims[..n_sites - 1].iter().enumerate() {
+            let cols = rest.len() / (left * phys_dim);
+            let mat = rest
+                .into_shape_with_order((left * phys_dim, cols))
+                .expect("unfolding shape");
+
+            let rank_cap = if cancelled() {
+                1
+            } else if periodic && site == 0 {
+                max_rank * max_rank
+            } else {
+                max_rank
             };
-            
-            // Create tensor with appropriate dimensions
-            let mut tensor = Array2::zeros((rows, cols));
-            for (j, &val) in chunk.iter().take(rows * cols).enumerate() {
-                let r = j / cols;
-                let c = j % cols;
-                if r < rows && c < cols {
-                    tensor[[r, c]] = val;
-                }
+            let svd = truncated_svd(&mat, rank_cap, SVD_TOLERANCE);
+            let rank = svd.s.len();
+
+            let mut carry = svd.vt;
+            for (r, mut row) in carry.rows_mut().into_iter().enumerate() {
+                row.mapv_inplace(|x| x.scale(svd.s[r]));
             }
-            
-            tensors.push(tensor);
-            if i < amplitudes.chunks(chunk_size).count() - 1 {
-                bond_dims.push(cols);
+
+            if periodic && site == 0 {
+                // Split rank = closing · right; closing index moves to the far end
+                closing = Self::ring_split(rank, max_rank);
+                let right = rank / closing;
+                let core = svd
+                    .u
+                    .into_shape_with_order((phys_dim, closing, right))
+                    .expect("core shape")
+                    .permuted_axes([1, 0, 2])
+                    .as_standard_layout()
+                    .to_owned();
+                tensors.push(core);
+
+                let rest3 = carry
+                    .into_shape_with_order((closing, right, cols))
+                    .expect("carry shape")
+                    .permuted_axes([1, 2, 0])
+                    .as_standard_layout()
+                    .to_owned();
+                rest = rest3
+                    .into_shape_with_order((right, cols * closing))
+                    .expect("carry flatten");
+                bond_dims.push(right);
+                left = right;
+            } else {
+                let core = svd
+                    .u
+                    .into_shape_with_order((left, phys_dim, rank))
+                    .expect("core shape");
+                tensors.push(core);
+                rest = carry;
+                bond_dims.push(rank);
+                left = rank;
             }
         }
-        
+
+        let last = rest
+            .into_shape_with_order((left, phys_dims[n_sites - 1], closing))
+            .expect("last core shape");
+        tensors.push(last);
+        if periodic {
+            bond_dims.push(closing);
+        }
+
         (tensors, bond_dims)
     }
-    
-    /// Reconstruct data from MPS
-    pub fn to_bytes(&self) -> Vec<u8> {
-        // Contract tensors to reconstruct amplitudes
-        let mut result = Vec::new();
-        
+
+    /// Factor a first-bond rank into `(closing, inner)` with both bounded by
+    /// `max_rank`, giving the ring closure the larger share.
+    fn ring_split(rank: usize, max_rank: usize) -> usize {
+        (1..=rank)
+            .filter(|c| rank.is_multiple_of(*c) && *c <= max_rank && rank / c <= max_rank)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Bring the chain into mixed canonical form around `center`.
+    ///
+    /// Sites left of `center` become left-isometries
+    /// (`Σ_{l,i} A*[l,i,r] A[l,i,r'] = δ_{rr'}`), sites right of it
+    /// right-isometries, and all weight is gathered on the center tensor.
+    /// The represented amplitudes are unchanged.
+    ///
+    /// On a periodic chain the ring-closing bond is not gauged: the sweeps
+    /// treat it as an open index of the end tensors, so the isometry
+    /// conditions hold with it summed over, but the closed loop means the
+    /// center tensor does not carry the chain's norm. The amplitudes are
+    /// still unchanged, which is all a gauge-invariant caller needs.
+    pub fn canonicalize(&mut self, center: usize) {
+        let n = self.tensors.len();
+        let center = center.min(n.saturating_sub(1));
+
+        // Left sweep: A = QR, keep Q, push R into the right neighbour
+        for site in 0..center {
+            let (l, d, r) = self.tensors[site].dim();
+            let mat = self.tensors[site]
+                .as_standard_layout()
+                .into_owned()
+                .into_shape_with_order((l * d, r))
+                .expect("unfolding shape");
+            let (q, rmat) = thin_qr(&mat);
+            let k = q.ncols();
+
+            self.tensors[site] = q.into_shape_with_order((l, d, k)).expect("core shape");
+            self.tensors[site + 1] = Self::absorb_left(&rmat, &self.tensors[site + 1]);
+            self.bond_dims[site] = k;
+        }
+
+        // Right sweep: Aᴴ = QR, so A = Rᴴ Qᴴ; keep Qᴴ, push Rᴴ into the left neighbour
+        for site in (center + 1..n).rev() {
+            let (l, d, r) = self.tensors[site].dim();
+            let mat = self.tensors[site]
+                .as_standard_layout()
+                .into_owned()
+                .into_shape_with_order((l, d * r))
+                .expect("unfolding shape");
+            let (q, rmat) = thin_qr(&mat.t().mapv(|c| c.conj()));
+            let k = q.ncols();
+
+            let qh = q.t().mapv(|c| c.conj()).as_standard_layout().into_owned();
+            self.tensors[site] = qh.into_shape_with_order((k, d, r)).expect("core shape");
+            let rh = rmat.t().mapv(|c| c.conj());
+            self.tensors[site - 1] = Self::absorb_right(&self.tensors[site - 1], &rh);
+            self.bond_dims[site - 1] = k;
+        }
+    }
+
+    /// Contract neighbouring sites in pairs whose merged physical dimension
+    /// is at most `max_merged_dim`, shortening the chain, then re-split
+    /// every bond by SVD down to the rank it needs.
+    ///
+    /// A merged site takes the product of the pair's dimensions, the left
+    /// site's digit most significant, so the position index is unchanged.
+    /// Pairs are taken left to right without overlap, so one call at most
+    /// halves the chain. The re-split only drops singular values below the
+    /// decomposition tolerance, so amplitudes change at that level alone.
+    pub fn merge_adjacent_tensors(&mut self, max_merged_dim: usize) {
+        let mut sites = std::mem::take(&mut self.tensors).into_iter().peekable();
+        while let Some(tensor) = sites.next() {
+            let tensor = match sites.next_if(|next| tensor.dim().1 * next.dim().1 <= max_merged_dim) {
+                Some(next) => Self::contract_pair(&tensor, &next),
+                None => tensor,
+            };
+            self.tensors.push(tensor);
+        }
+
+        self.phys_dims = self.tensors.iter().map(|t| t.shape()[1]).collect();
+        self.resplit_bonds();
+    }
+
+    /// Re-split every inner bond left to right by SVD, dropping singular
+    /// values below the decomposition tolerance; the ring closure is left
+    /// as it is
+    fn resplit_bonds(&mut self) {
+        for site in 0..self.tensors.len().saturating_sub(1) {
+            let (l, d, r) = self.tensors[site].dim();
+            let mat = self.tensors[site]
+                .as_standard_layout()
+                .into_owned()
+                .into_shape_with_order((l * d, r))
+                .expect("unfolding shape");
+            let svd = truncated_svd(&mat, r, SVD_TOLERANCE);
+            let k = svd.s.len();
+
+            let mut carry = svd.vt;
+            for (row, mut values) in carry.rows_mut().into_iter().enumerate() {
+                values *= Complex64::new(svd.s[row], 0.0);
+            }
+            self.tensors[site] = svd.u.into_shape_with_order((l, d, k)).expect("core shape");
+            self.tensors[site + 1] = Self::absorb_left(&carry, &self.tensors[site + 1]);
+        }
+
+        self.bond_dims = self.tensors.iter().map(|t| t.shape()[2]).collect();
+        if !self.periodic {
+            self.bond_dims.pop();
+        }
+    }
+
+    /// Contract `a`'s right bond with `b`'s left bond into one site whose
+    /// physical index is `a`'s digit times `b`'s dimension plus `b`'s
+    fn contract_pair(a: &Array3<Complex64>, b: &Array3<Complex64>) -> Array3<Complex64> {
+        let (l, d1, _) = a.dim();
+        let (_, d2, r) = b.dim();
+        let left = a
+            .as_standard_layout()
+            .into_owned()
+            .into_shape_with_order((l * d1, a.dim().2))
+            .expect("tensor shape");
+        let right = b
+            .as_standard_layout()
+            .into_owned()
+            .into_shape_with_order((b.dim().0, d2 * r))
+            .expect("tensor shape");
+        left.dot(&right)
+            .into_shape_with_order((l, d1 * d2, r))
+            .expect("tensor shape")
+    }
+
+    /// `M · T` contracting `M`'s columns with `T`'s left bond
+    fn absorb_left(m: &Array2<Complex64>, t: &Array3<Complex64>) -> Array3<Complex64> {
+        let (l, d, r) = t.dim();
+        let flat = t
+            .as_standard_layout()
+            .into_owned()
+            .into_shape_with_order((l, d * r))
+            .expect("tensor shape");
+        m.dot(&flat)
+            .into_shape_with_order((m.nrows(), d, r))
+            .expect("tensor shape")
+    }
+
+    /// `T · M` contracting `T`'s right bond with `M`'s rows
+    fn absorb_right(t: &Array3<Complex64>, m: &Array2<Complex64>) -> Array3<Complex64> {
+        let (l, d, r) = t.dim();
+        let flat = t
+            .as_standard_layout()
+            .into_owned()
+            .into_shape_with_order((l * d, r))
+            .expect("tensor shape");
+        flat.dot(m)
+            .into_shape_with_order((l, d, m.ncols()))
+            .expect("tensor shape")
+    }
+
+    /// Contract the chain into the full (padded) amplitude vector
+    pub fn amplitudes(&self) -> Vec<Complex64> {
+        self.contract().to_vec()
+    }
+
+    /// Contract the chain into the full (padded) amplitude vector.
+    ///
+    /// The vector is the concatenation of [`Self::contract_window`] over every
+    /// window, so streaming decoders that contract one window at a time see
+    /// bit-identical amplitudes.
+    pub fn contract(&self) -> Array1<Complex64> {
+        let mut output = Vec::with_capacity(self.window_count() * self.window_len());
+        for window in 0..self.window_count() {
+            output.extend(self.contract_window(window));
+        }
+        Array1::from_vec(output)
+    }
+
+    /// L2 norm of the (padded) amplitude vector, computed along the chain
+    /// without contracting it.
+    ///
+    /// For each pair of ring closure indices `(a, a')` an environment
+    /// `Σ_i A_iᴴ E A_i` is carried site by site, so the cost is
+    /// `O(closing² · d · r³)` rather than the length of the vector; open
+    /// chains have a single environment.
+    pub fn norm(&self) -> f64 {
+        let Some(first) = self.tensors.first() else {
+            return 0.0;
+        };
+        let closing = first.shape()[0];
+
+        // envs[a * closing + a'][b, b'] = Σ over prefixes of conj(M[a, b]) · M[a', b']
+        let mut envs: Vec<Array2<Complex64>> = (0..closing * closing)
+            .map(|pair| {
+                let mut env = Array2::zeros((closing, closing));
+                env[[pair / closing, pair % closing]] = Complex64::new(1.0, 0.0);
+                env
+            })
+            .collect();
         for tensor in &self.tensors {
-            for &val in tensor.iter() {
-                let byte = (val.re * 255.0).clamp(0.0, 255.0) as u8;
-                result.push(byte);
+            for env in &mut envs {
+                let mut next = Array2::zeros((tensor.dim().2, tensor.dim().2));
+                for site in tensor.axis_iter(Axis(1)) {
+                    next += &site.t().mapv(|c| c.conj()).dot(&env.dot(&site));
+                }
+                *env = next;
             }
         }
-        
-        result
+
+        // Trace over the ring closure on both sides
+        let width = envs[0].ncols();
+        let squared: Complex64 = (0..closing.min(width))
+            .flat_map(|a| (0..closing.min(width)).map(move |b| (a, b)))
+            .map(|(a, b)| envs[a * closing + b][[a, b]])
+            .sum();
+        squared.re.max(0.0).sqrt()
+    }
+
+    /// Scale the chain to unit [`Self::norm`], as a quantum state; a zero
+    /// chain is left alone. Reconstructed bytes scale with it.
+    pub fn normalize(&mut self) {
+        let norm = self.norm();
+        if norm > 0.0 {
+            self.tensors[0].mapv_inplace(|c| c / norm);
+        }
+    }
+
+    /// Number of trailing sites contracted together in one window
+    fn window_sites(&self) -> usize {
+        self.tensors.len().min(CONTRACT_WINDOW_SITES)
+    }
+
+    /// Number of amplitude windows (product of the leading sites' digits)
+    pub fn window_count(&self) -> usize {
+        if self.tensors.is_empty() {
+            return 0;
+        }
+        let fixed = self.tensors.len() - self.window_sites();
+        self.tensors[..fixed].iter().map(|t| t.dim().1).product()
+    }
+
+    /// Amplitudes per window (padded)
+    pub fn window_len(&self) -> usize {
+        let fixed = self.tensors.len() - self.window_sites();
+        self.tensors[fixed..].iter().map(|t| t.dim().1).product()
     }
-    
+
+    /// Contract the `window`-th run of [`Self::window_len`] padded amplitudes.
+    ///
+    /// The leading sites have their digits fixed by `window` and collapse to
+    /// one `(closing, bond)` matrix; the trailing sites are then swept left to
+    /// right, keeping the partial product as one matrix of shape
+    /// `(closing · prefix, bond)` so each site costs a single
+    /// `general_mat_mul`.
+    pub fn contract_window(&self, window: usize) -> Array1<Complex64> {
+        let Some(first) = self.tensors.first() else {
+            return Array1::zeros(0);
+        };
+        let closing = first.shape()[0];
+        let one = Complex64::new(1.0, 0.0);
+        let zero = Complex64::new(0.0, 0.0);
+        let fixed = self.tensors.len() - self.window_sites();
+
+        // Leading digits of the window index, most significant site first
+        let mut digits = vec![0; fixed];
+        let mut rest = window;
+        for (digit, tensor) in digits.iter_mut().zip(&self.tensors[..fixed]).rev() {
+            let d = tensor.dim().1;
+            *digit = rest % d;
+            rest /= d;
+        }
+
+        // state[(a, p), b]: closing index a, prefix p, open bond b
+        let mut state = Array2::<Complex64>::eye(closing);
+        for (tensor, &digit) in self.tensors[..fixed].iter().zip(&digits) {
+            let site = tensor.index_axis(Axis(1), digit);
+            let mut next = Array2::zeros((closing, site.ncols()));
+            general_mat_mul(one, &state, &site, zero, &mut next);
+            state = next;
+        }
+        let mut prefix = 1;
+
+        for tensor in &self.tensors[fixed..] {
+            let (l, d, r) = tensor.dim();
+            let site = tensor.as_standard_layout();
+            let site = site.view().into_shape_with_order((l, d * r)).expect("tensor shape");
+
+            let mut next = Array2::zeros((state.nrows(), d * r));
+            general_mat_mul(one, &state, &site, zero, &mut next);
+            state = next
+                .into_shape_with_order((closing * prefix * d, r))
+                .expect("state shape");
+            prefix *= d;
+        }
+
+        // Trace over the ring closure (trivial for open chains)
+        let width = state.ncols();
+        let state = state
+            .into_shape_with_order((closing, prefix, width))
+            .expect("trace shape");
+        Array1::from_shape_fn(prefix, |p| (0..closing.min(width)).map(|a| state[[a, p, a]]).sum())
+    }
+
+    /// Amplitudes of `window` that fall inside the data (padding dropped)
+    fn window_amplitudes(&self, window: usize) -> impl Iterator<Item = Complex64> {
+        let start = window * self.window_len();
+        let amplitudes = self.contract_window(window);
+        amplitudes.into_iter().take(self.len.saturating_sub(start))
+    }
+
+    /// Reconstruct data from MPS
+    pub fn to_bytes(&self) -> Vec<u8> {
+        (0..self.window_count()).flat_map(|w| self.window_bytes(w)).collect()
+    }
+
+    /// Reconstruct the bytes of a single window, as [`Self::to_bytes`] would
+    pub fn window_bytes(&self, window: usize) -> Vec<u8> {
+        self.window_amplitudes(window).map(Self::amplitude_byte).collect()
+    }
+
+    /// Reconstruct the byte at `index` alone, contracting only the matrix
+    /// each site selects for that position; `None` past the end of the data
+    pub fn sample(&self, index: usize) -> Option<u8> {
+        if index >= self.len || self.tensors.is_empty() {
+            return None;
+        }
+        Some(Self::amplitude_byte(self.amplitude(index)))
+    }
+
+    /// Set the byte at `index` to `new_value`, leaving every other byte
+    /// [`Self::to_bytes`] yields unchanged.
+    ///
+    /// One amplitude depends on a matrix from every site, so re-fitting a
+    /// few tensors would shift the many positions that share their
+    /// environment. The change is instead added exactly, as a product state
+    /// block diagonal to the inner bonds, and every bond is then re-split
+    /// by SVD after a canonicalizing sweep, keeping only the singular
+    /// values the edited amplitudes need. A bond therefore grows only when
+    /// the edit leaves the chain's span, and never past the smaller side of
+    /// its unfolding (the product of the physical dimensions on either
+    /// side), however many edits are made. Setting a byte to the value it
+    /// already has changes nothing.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not below `self.len`.
+    pub fn update_byte(&mut self, index: usize, new_value: u8) {
+        assert!(index < self.len, "index {index} out of range for {} amplitudes", self.len);
+        if self.sample(index) == Some(new_value) {
+            return;
+        }
+        let delta = Complex64::new(new_value as f64 / 255.0, 0.0) - self.amplitude(index);
+        self.add_product_state(&self.digits(index), delta);
+        self.canonicalize(0);
+        self.resplit_bonds();
+    }
+
+    /// Add `delta` at the position `digits` spell as a product state, block
+    /// diagonal to the existing inner bonds. The end bonds are shared: the
+    /// new path enters and leaves through the last ring closure index (the
+    /// unit bond of an open chain), so only inner bonds grow, by one each.
+    fn add_product_state(&mut self, digits: &[usize], delta: Complex64) {
+        let last = self.tensors.len() - 1;
+        let one = Complex64::new(1.0, 0.0);
+
+        for (site, (tensor, &digit)) in self.tensors.iter_mut().zip(digits).enumerate() {
+            let (l, d, r) = tensor.dim();
+            let (new_l, new_r) = (l + (site != 0) as usize, r + (site != last) as usize);
+
+            let mut grown = Array3::zeros((new_l, d, new_r));
+            grown.slice_mut(s![..l, .., ..r]).assign(tensor);
+            grown[[new_l - 1, digit, new_r - 1]] += if site == 0 { delta } else { one };
+            *tensor = grown;
+        }
+    }
+
+    /// Digits of `index` in the sites' mixed radix, most significant first
+    fn digits(&self, index: usize) -> Vec<usize> {
+        let mut digits = vec![0; self.tensors.len()];
+        let mut rest = index;
+        for (digit, tensor) in digits.iter_mut().zip(&self.tensors).rev() {
+            let d = tensor.dim().1;
+            *digit = rest % d;
+            rest /= d;
+        }
+        digits
+    }
+
+    /// The amplitude at `index`: the trace of the product of the matrices
+    /// its digits select. The chain must not be empty.
+    fn amplitude(&self, index: usize) -> Complex64 {
+        let mut product = Array2::<Complex64>::eye(self.tensors[0].shape()[0]);
+        for (tensor, &digit) in self.tensors.iter().zip(&self.digits(index)) {
+            product = product.dot(&tensor.index_axis(Axis(1), digit));
+        }
+        // Trace over the ring closure (trivial for open chains)
+        (0..product.nrows().min(product.ncols())).map(|a| product[[a, a]]).sum()
+    }
+
+    /// Inverse of [`Self::encode_amplitudes`] for one amplitude
+    fn amplitude_byte(val: Complex64) -> u8 {
+        (val.re * 255.0).round().clamp(0.0, 255.0) as u8
+    }
+
+    /// Reconstruct real-valued samples (for amplitude-encoded `f64` input)
+    pub fn to_f64(&self) -> Vec<f64> {
+        (0..self.window_count()).flat_map(|w| self.window_f64(w)).collect()
+    }
+
+    /// Reconstruct the samples of a single window, as [`Self::to_f64`] would
+    pub fn window_f64(&self, window: usize) -> Vec<f64> {
+        self.window_amplitudes(window).map(|val| val.re).collect()
+    }
+
     /// Calculate storage size of MPS representation
     pub fn storage_size(&self) -> usize {
-        self.tensors.iter().map(|t| t.len() * 16).sum() // Complex64 = 16 bytes
+        self.n_params() * 16 // Complex64 = 16 bytes
+    }
+
+    /// Number of free parameters (complex tensor elements) in the chain
+    pub fn n_params(&self) -> usize {
+        self.tensors.iter().map(|t| t.len()).sum()
+    }
+
+    /// Real scalars the serialized chain stores: two per element, or one
+    /// once [`MPS::real`] is set
+    pub fn stored_params(&self) -> usize {
+        if self.stores_real() {
+            self.n_params()
+        } else {
+            2 * self.n_params()
+        }
+    }
+
+    /// Exploit conjugate symmetry: if the represented amplitudes equal
+    /// their complex conjugate (are real), make every tensor real and set
+    /// [`MPS::real`], halving the stored parameters. Returns whether it did.
+    ///
+    /// Byte and real-sample input decomposes into real tensors already,
+    /// which are kept as they are. A chain whose tensors carry complex gauge
+    /// phases around real amplitudes is decomposed again from those
+    /// amplitudes, at its current largest bond dimension.
+    pub fn compress_complex_conjugate_symmetry(&mut self) -> bool {
+        if !self.tensors.iter().all(|t| t.iter().all(|c| c.im == 0.0)) {
+            let amplitudes = self.amplitudes();
+            let imag = amplitudes.iter().map(|a| a.im * a.im).sum::<f64>().sqrt();
+            if imag > REAL_TOLERANCE * self.norm().max(f64::MIN_POSITIVE) {
+                return false;
+            }
+            let real: Vec<f64> = amplitudes[..self.len].iter().map(|a| a.re).collect();
+            let rank = self.bond_dims.iter().copied().max().unwrap_or(1);
+            *self = Self::from_real_with_dims(&real, &self.phys_dims, rank, self.periodic);
+        }
+        // Clear signed zeros and rounding left by the decomposition
+        for tensor in &mut self.tensors {
+            tensor.mapv_inplace(|c| Complex64::new(c.re, 0.0));
+        }
+        self.real = true;
+        true
+    }
+
+    /// Whether serialization drops the imaginary parts: only when they
+    /// are all zero, whatever [`MPS::real`] claims
+    fn stores_real(&self) -> bool {
+        self.real && self.tensors.iter().all(|t| t.iter().all(|c| c.im == 0.0))
+    }
+
+    /// Implied ratio of `original_len` bytes to the serialized MPS, computed
+    /// from the tensor shapes without serializing
+    pub fn compression_ratio_estimate(&self, original_len: usize) -> f64 {
+        original_len as f64 / self.serialized_len(Precision::F64) as f64
     }
-    
+
+    /// Round every tensor element to what `precision` stores, so the
+    /// in-memory chain reconstructs exactly as its serialized form will
+    pub fn round_to(&mut self, precision: Precision) {
+        if precision == Precision::F32 {
+            for tensor in &mut self.tensors {
+                tensor.mapv_inplace(|c| Complex64::new(c.re as f32 as f64, c.im as f32 as f64));
+            }
+        }
+    }
+
     /// Serialize MPS to bytes
     pub fn serialize(&self) -> Vec<u8> {
-        let mut output = Vec::new();
-        
-        // Header: number of tensors, physical dimension
+        self.serialize_with(Precision::F64)
+    }
+
+    /// Serialize MPS to bytes with tensor elements stored at `precision`
+    pub fn serialize_with(&self, precision: Precision) -> Vec<u8> {
+        let mut output = Vec::with_capacity(self.serialized_len(precision));
+        self.serialize_into_with(&mut output, precision);
+        output
+    }
+
+    /// Append the [`MPS::serialize`] encoding to `output`, so callers that
+    /// frame it inside a larger buffer skip the intermediate copy
+    pub fn serialize_into(&self, output: &mut Vec<u8>) {
+        self.serialize_into_with(output, Precision::F64);
+    }
+
+    /// [`MPS::serialize_into`] with tensor elements stored at `precision`
+    pub fn serialize_into_with(&self, output: &mut Vec<u8>, precision: Precision) {
+        output.reserve(self.serialized_len(precision));
+
+        // Header: number of tensors, physical dimension (0 when sites differ;
+        // each tensor's shape records its own), flags, length
+        let mut flags = if self.periodic { FLAG_PERIODIC } else { 0 };
+        if precision == Precision::F32 {
+            flags |= FLAG_F32;
+        }
+        let real = self.stores_real();
+        if real {
+            flags |= FLAG_REAL;
+        }
         output.extend_from_slice(&(self.tensors.len() as u32).to_le_bytes());
-        output.extend_from_slice(&(self.phys_dim as u32).to_le_bytes());
-        
+        output.extend_from_slice(&(self.uniform_phys_dim() as u32).to_le_bytes());
+        output.push(flags);
+        output.extend_from_slice(&(self.len as u64).to_le_bytes());
+
         // Bond dimensions
         for &bd in &self.bond_dims {
             output.extend_from_slice(&(bd as u32).to_le_bytes());
         }
-        
+
         // Tensors
         for tensor in &self.tensors {
-            output.extend_from_slice(&(tensor.nrows() as u32).to_le_bytes());
-            output.extend_from_slice(&(tensor.ncols() as u32).to_le_bytes());
+            let (l, d, r) = tensor.dim();
+            output.extend_from_slice(&(l as u32).to_le_bytes());
+            output.extend_from_slice(&(d as u32).to_le_bytes());
+            output.extend_from_slice(&(r as u32).to_le_bytes());
             for &c in tensor.iter() {
-                output.extend_from_slice(&c.re.to_le_bytes());
-                output.extend_from_slice(&c.im.to_le_bytes());
+                let parts = if real { &[c.re][..] } else { &[c.re, c.im][..] };
+                for &part in parts {
+                    match precision {
+                        Precision::F32 => output.extend_from_slice(&(part as f32).to_le_bytes()),
+                        Precision::F64 => output.extend_from_slice(&part.to_le_bytes()),
+                    }
+                }
             }
         }
-        
-        output
     }
-    
+
+    /// Length of the [`MPS::serialize`] encoding: header, bond dims,
+    /// per-tensor shapes and re/im pairs (real parts alone for a real chain)
+    fn serialized_len(&self, precision: Precision) -> usize {
+        let values = precision.element_size() / 2 * self.stored_params();
+        17 + 4 * self.bond_dims.len() + 12 * self.tensors.len() + values
+    }
+
     /// Deserialize MPS from bytes
     pub fn deserialize(data: &[u8]) -> Option<Self> {
-        if data.len() < 8 {
+        let mut pos = 0;
+        let take_u32 = |pos: &mut usize| -> Option<usize> {
+            let v = u32::from_le_bytes(data.get(*pos..*pos + 4)?.try_into().ok()?) as usize;
+            *pos += 4;
+            Some(v)
+        };
+
+        let num_tensors = take_u32(&mut pos)?;
+        let phys_dim = take_u32(&mut pos)?;
+        if num_tensors == 0 {
             return None;
         }
-        
-        let mut pos = 0;
-        
-        let num_tensors = u32::from_le_bytes(data[pos..pos+4].try_into().ok()?) as usize;
-        pos += 4;
-        let phys_dim = u32::from_le_bytes(data[pos..pos+4].try_into().ok()?) as usize;
-        pos += 4;
-        
+        let flags = *data.get(pos)?;
+        pos += 1;
+        let periodic = flags & FLAG_PERIODIC != 0;
+        let precision = if flags & FLAG_F32 != 0 { Precision::F32 } else { Precision::F64 };
+        let real = flags & FLAG_REAL != 0;
+        let size = if real { precision.element_size() / 2 } else { precision.element_size() };
+        let len = u64::from_le_bytes(data.get(pos..pos + 8)?.try_into().ok()?) as usize;
+        pos += 8;
+
         // Bond dimensions
-        let mut bond_dims = Vec::new();
-        for _ in 0..num_tensors.saturating_sub(1) {
-            let bd = u32::from_le_bytes(data[pos..pos+4].try_into().ok()?) as usize;
-            pos += 4;
-            bond_dims.push(bd);
+        let num_bonds = if periodic { num_tensors } else { num_tensors - 1 };
+        let mut bond_dims = Vec::with_capacity(num_bonds.min(data.len() / 4));
+        for _ in 0..num_bonds {
+            bond_dims.push(take_u32(&mut pos)?);
         }
-        
+
         // Tensors
-        let mut tensors = Vec::new();
+        let mut tensors = Vec::with_capacity(num_tensors.min(data.len() / 12));
         for _ in 0..num_tensors {
-            let rows = u32::from_le_bytes(data[pos..pos+4].try_into().ok()?) as usize;
-            pos += 4;
-            let cols = u32::from_le_bytes(data[pos..pos+4].try_into().ok()?) as usize;
-            pos += 4;
-            
-            let mut tensor = Array2::zeros((rows, cols));
-            for r in 0..rows {
-                for c in 0..cols {
-                    let re = f64::from_le_bytes(data[pos..pos+8].try_into().ok()?);
-                    pos += 8;
-                    let im = f64::from_le_bytes(data[pos..pos+8].try_into().ok()?);
-                    pos += 8;
-                    tensor[[r, c]] = Complex64::new(re, im);
-                }
+            let l = take_u32(&mut pos)?;
+            let d = take_u32(&mut pos)?;
+            let r = take_u32(&mut pos)?;
+            if phys_dim != 0 && d != phys_dim {
+                return None;
+            }
+            let count = l.checked_mul(d)?.checked_mul(r)?;
+            let bytes = data.get(pos..pos.checked_add(count.checked_mul(size)?)?)?;
+            pos += count * size;
+
+            let values = bytes
+                .chunks_exact(size)
+                .map(|c| match precision {
+                    Precision::F32 if real => Complex64::new(f32::from_le_bytes(c.try_into().unwrap()) as f64, 0.0),
+                    Precision::F64 if real => Complex64::new(f64::from_le_bytes(c.try_into().unwrap()), 0.0),
+                    Precision::F32 => {
+                        let re = f32::from_le_bytes(c[0..4].try_into().unwrap());
+                        let im = f32::from_le_bytes(c[4..8].try_into().unwrap());
+                        Complex64::new(re as f64, im as f64)
+                    }
+                    Precision::F64 => {
+                        let re = f64::from_le_bytes(c[0..8].try_into().unwrap());
+                        let im = f64::from_le_bytes(c[8..16].try_into().unwrap());
+                        Complex64::new(re, im)
+                    }
+                })
+                .collect();
+            tensors.push(Array3::from_shape_vec((l, d, r), values).ok()?);
+        }
+
+        let mut mps = Self::from_parts(tensors, bond_dims, len, periodic)?;
+        mps.real = real;
+        Some(mps)
+    }
+
+    /// Assemble an MPS, checking that neighbouring bonds agree; physical
+    /// dimensions follow from the tensor shapes
+    fn from_parts(tensors: Vec<Array3<Complex64>>, bond_dims: Vec<usize>, len: usize, periodic: bool) -> Option<Self> {
+        for (i, pair) in tensors.windows(2).enumerate() {
+            if pair[0].shape()[2] != pair[1].shape()[0] || bond_dims[i] != pair[0].shape()[2] {
+                return None;
             }
-            tensors.push(tensor);
         }
-        
-        Some(MPS { tensors, bond_dims, phys_dim })
+        let (first, last) = (tensors.first()?, tensors.last()?);
+        if first.shape()[0] != last.shape()[2] || (!periodic && first.shape()[0] != 1) {
+            return None;
+        }
+        let phys_dims: Vec<usize> = tensors.iter().map(|t| t.shape()[1]).collect();
+        if phys_dims.contains(&0) {
+            return None;
+        }
+
+        Some(MPS { tensors, bond_dims, phys_dims, len, periodic, real: false })
     }
+
+    /// Write the site tensors to a NumPy `.npz` archive.
+    ///
+    /// Each tensor is a `complex128` array `tensor_<i>` shaped `(left bond,
+    /// physical, right bond)`, and `meta` is an `int64` array of
+    /// `[phys_dim, len, periodic]` (`phys_dim` 0 when sites differ), so
+    /// `numpy.load(path)["tensor_0"]` works directly. Bond and physical
+    /// dimensions follow from the tensor shapes.
+    pub fn to_npz(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let meta = [self.uniform_phys_dim() as i64, self.len as i64, self.periodic as i64];
+        let mut arrays = vec![(
+            "meta".to_string(),
+            Array { dtype: Dtype::Int64, shape: vec![3], data: meta.iter().flat_map(|v| v.to_le_bytes()).collect() },
+        )];
+        for (i, tensor) in self.tensors.iter().enumerate() {
+            let (l, d, r) = tensor.dim();
+            let data = tensor.iter().flat_map(|c| [c.re.to_le_bytes(), c.im.to_le_bytes()]).flatten().collect();
+            arrays.push((format!("tensor_{i}"), Array { dtype: Dtype::Complex128, shape: vec![l, d, r], data }));
+        }
+        npz::write_npz(path.as_ref(), &arrays)
+    }
+
+    /// Load an MPS written by [`MPS::to_npz`] (or by NumPy in that layout;
+    /// `float64` tensors are accepted as real-valued)
+    pub fn from_npz(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("not an MPS npz: {what}"));
+        let mut arrays = npz::read_npz(path.as_ref())?;
+        let mut take = |name: &str| {
+            let i = arrays.iter().position(|(n, _)| n == name)?;
+            Some(arrays.swap_remove(i).1)
+        };
+
+        let meta = take("meta")
+            .filter(|m| m.dtype == Dtype::Int64 && m.shape == [3])
+            .ok_or_else(|| invalid("missing meta"))?;
+        let field = |i: usize| i64::from_le_bytes(meta.data[8 * i..8 * i + 8].try_into().unwrap());
+        let (phys_dim, len, periodic) = (field(0), field(1), field(2) != 0);
+        let phys_dim = usize::try_from(phys_dim).map_err(|_| invalid("bad phys_dim"))?;
+        let len = usize::try_from(len).map_err(|_| invalid("bad len"))?;
+
+        let mut tensors = Vec::new();
+        while let Some(array) = take(&format!("tensor_{}", tensors.len())) {
+            let &[l, d, r] = array.shape.as_slice() else {
+                return Err(invalid("tensor is not 3-d"));
+            };
+            if phys_dim != 0 && d != phys_dim {
+                return Err(invalid("physical dimension mismatch"));
+            }
+            let values = match array.dtype {
+                Dtype::Complex128 => array
+                    .data
+                    .chunks_exact(16)
+                    .map(|c| {
+                        let re = f64::from_le_bytes(c[0..8].try_into().unwrap());
+                        let im = f64::from_le_bytes(c[8..16].try_into().unwrap());
+                        Complex64::new(re, im)
+                    })
+                    .collect(),
+                Dtype::Float64 => array
+                    .data
+                    .chunks_exact(8)
+                    .map(|c| Complex64::new(f64::from_le_bytes(c.try_into().unwrap()), 0.0))
+                    .collect(),
+                Dtype::Int64 => return Err(invalid("integer tensor")),
+            };
+            tensors.push(Array3::from_shape_vec((l, d, r), values).map_err(|_| invalid("bad tensor shape"))?);
+        }
+
+        // Right bonds, the last of which only closes a ring
+        let mut bond_dims: Vec<usize> = tensors.iter().map(|t| t.shape()[2]).collect();
+        if !periodic {
+            bond_dims.pop();
+        }
+        Self::from_parts(tensors, bond_dims, len, periodic).ok_or_else(|| invalid("bond mismatch"))
+    }
+}
+
+/// Binary sites needed to index `len` amplitudes (at least one)
+fn binary_sites(len: usize) -> usize {
+    let mut n_sites = 1;
+    while 2usize.pow(n_sites as u32) < len {
+        n_sites += 1;
+    }
+    n_sites
+}
+
+/// Row-major `values` of `shape` with the axes reordered so that axis `k`
+/// of the result is axis `order[k]` of the input
+fn permute_axes(values: &[Complex64], shape: &[usize], order: &[usize]) -> Vec<Complex64> {
+    let view = ArrayView::from_shape(IxDyn(shape), values).expect("values fill the tensor shape");
+    view.permuted_axes(IxDyn(order)).iter().copied().collect()
+}
+
+/// Greedy axis order for [`MPS::from_tensor`] that keeps bonds narrow.
+///
+/// The bond after a prefix of axes has the rank of the tensor unfolded
+/// into (prefix, rest), whatever order the prefix takes, so the chain is
+/// grown one axis at a time, each time adding the axis whose cut has the
+/// lowest rank (capped at `max_rank`). Ties go to the smaller axis, then
+/// the earlier one, so a tensor with no structure keeps its natural order
+/// up to sorting by size. Costs one SVD per candidate cut.
+pub fn contraction_order(values: &[Complex64], shape: &[usize], max_rank: usize) -> Vec<usize> {
+    let mut order = Vec::with_capacity(shape.len());
+    let mut rest: Vec<usize> = (0..shape.len()).collect();
+
+    while rest.len() > 1 {
+        let (pick, _) = rest
+            .iter()
+            .enumerate()
+            .map(|(i, &axis)| {
+                let mut cut = order.clone();
+                cut.push(axis);
+                cut.extend(rest.iter().filter(|&&other| other != axis));
+                let rows: usize = cut[..order.len() + 1].iter().map(|&a| shape[a]).product();
+                let unfolding = Array2::from_shape_vec(
+                    (rows, values.len() / rows),
+                    permute_axes(values, shape, &cut),
+                )
+                .expect("unfolding shape");
+                let rank = truncated_svd(&unfolding, max_rank.max(1), SVD_TOLERANCE).s.len();
+                (i, (rank, shape[axis], axis))
+            })
+            .min_by_key(|&(_, key)| key)
+            .expect("axes remain");
+        order.push(rest.remove(pick));
+    }
+    order.extend(rest);
+    order
 }
 
 /// Parallel MPS compression for large data
+///
+/// Chunks follow [`plan_chunks`], so a short tail is merged into the previous
+/// chunk rather than decomposed on its own.
 pub fn parallel_compress(data: &[u8], max_rank: usize, num_threads: usize) -> Vec<MPS> {
+    parallel_compress_indexed(data, max_rank, num_threads)
+        .into_iter()
+        .map(|(_, mps)| mps)
+        .collect()
+}
+
+/// Like [`parallel_compress`], but tags each MPS with its chunk index so the
+/// result can be collected or reordered freely before reconstruction
+pub fn parallel_compress_indexed(data: &[u8], max_rank: usize, num_threads: usize) -> Vec<(usize, MPS)> {
     let chunk_size = data.len() / num_threads.max(1);
-    
-    data.par_chunks(chunk_size.max(1024))
-        .map(|chunk| MPS::from_bytes(chunk, max_rank))
+
+    plan_chunks(data.len(), chunk_size.max(1024))
+        .into_par_iter()
+        .enumerate()
+        .map(|(index, range)| (index, MPS::from_bytes(&data[range], max_rank)))
         .collect()
 }
 
+/// Reassemble bytes from indexed chunks in any order
+pub fn reconstruct_indexed(chunks: &[(usize, MPS)]) -> Vec<u8> {
+    let mut ordered: Vec<&(usize, MPS)> = chunks.iter().collect();
+    ordered.sort_by_key(|(index, _)| *index);
+    ordered.iter().flat_map(|(_, mps)| mps.to_bytes()).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use std::time::Instant;
+
     #[test]
     fn test_mps_roundtrip() {
         let data = b"Hello, quantum compression!";
         let mps = MPS::from_bytes(data, 16);
-        
+
         let serialized = mps.serialize();
         let deserialized = MPS::deserialize(&serialized).unwrap();
-        
+
         assert_eq!(mps.tensors.len(), deserialized.tensors.len());
+        assert_eq!(deserialized.to_bytes(), data);
+
+        let mut appended = Vec::new();
+        mps.serialize_into(&mut appended);
+        assert_eq!(appended, serialized);
+        appended.truncate(3);
+        mps.serialize_into(&mut appended);
+        assert_eq!(&appended[3..], &serialized[..]);
+    }
+
+    #[test]
+    fn test_f32_precision_halves_tensors() {
+        let data: Vec<u8> = (0..4096).map(|i| ((i as f64 * 0.05).sin() * 100.0 + 128.0) as u8).collect();
+        let mps = MPS::from_bytes(&data, 8);
+        let (f64_bytes, f32_bytes) = (mps.serialize(), mps.serialize_with(Precision::F32));
+
+        // Same header and shapes; tensor elements take 8 bytes instead of 16
+        let tensor_bytes = 16 * mps.n_params();
+        assert_eq!(f64_bytes.len() - f32_bytes.len(), tensor_bytes / 2);
+        assert!((f32_bytes.len() as f64) < 0.55 * f64_bytes.len() as f64);
+
+        let restored = MPS::deserialize(&f32_bytes).unwrap();
+        assert_eq!(restored.bond_dims, mps.bond_dims);
+        assert_eq!(restored.serialize_with(Precision::F32), f32_bytes);
+        let error = mps
+            .amplitudes()
+            .iter()
+            .zip(restored.amplitudes())
+            .map(|(a, b)| (a - b).norm())
+            .fold(0.0, f64::max);
+        assert!(error < 1e-3, "f32 reconstruction error {error}");
+
+        // Rounding in memory matches what deserialization yields
+        let mut rounded = mps.clone();
+        rounded.round_to(Precision::F32);
+        assert_eq!(rounded.amplitudes(), restored.amplitudes());
+
+        // Lossless compression absorbs the rounding in the residual
+        let config = crate::Config { precision: Precision::F32, ..Default::default() };
+        let (compressed, _) = crate::compress(&data, &config).unwrap();
+        assert_eq!(crate::decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_contraction_order_narrows_bonds() {
+        // x[i, j, k] = f(i, k) · g(j): axes 0 and 2 are entangled, axis 1 is
+        // a product factor that the natural order puts between them
+        let n = 8;
+        let values: Vec<Complex64> = (0..n * n * n)
+            .map(|index| {
+                let (i, j, k) = (index / (n * n), index / n % n, index % n);
+                let f = ((i * 5 + k * 3) % 7) as f64 + (i * k) as f64 * 0.1;
+                Complex64::new(f * (1.0 + j as f64), 0.0)
+            })
+            .collect();
+        let shape = [n, n, n];
+
+        let order = contraction_order(&values, &shape, 64);
+        assert_eq!(order[0], 1);
+        let naive = MPS::from_tensor(&values, &shape, &[0, 1, 2], 64);
+        let optimized = MPS::from_tensor(&values, &shape, &order, 64);
+        assert!(optimized.n_params() < naive.n_params() / 2);
+
+        // Both are exact at this rank
+        let error = |mps: &MPS, order: &[usize]| {
+            let restored = mps.to_tensor(order);
+            values.iter().zip(&restored).map(|(a, b)| (a - b).norm()).fold(0.0, f64::max)
+        };
+        assert!(error(&naive, &[0, 1, 2]) < 1e-9);
+        assert!(error(&optimized, &order) < 1e-9);
+    }
+
+    #[test]
+    fn test_mixed_phys_dims_roundtrip() {
+        // 3000 amplitudes on a byte-sized site followed by nibble and binary sites
+        let data: Vec<u8> = (0..3000).map(|i| ((i * 37) % 251) as u8).collect();
+        let amplitudes: Vec<Complex64> = data.iter().map(|&b| Complex64::new(b as f64 / 255.0, 0.0)).collect();
+        let dims = [256, 16, 2];
+
+        for periodic in [false, true] {
+            let mps = MPS::from_amplitudes_with_dims(&amplitudes, &dims, 4096, periodic);
+            assert_eq!(mps.phys_dims, dims);
+            let shapes: Vec<usize> = mps.tensors.iter().map(|t| t.dim().1).collect();
+            assert_eq!(shapes, dims);
+            assert_eq!(mps.to_bytes(), data);
+
+            let restored = MPS::deserialize(&mps.serialize()).unwrap();
+            assert_eq!(restored.phys_dims, dims);
+            assert_eq!(restored.bond_dims, mps.bond_dims);
+            assert_eq!(restored.to_bytes(), data);
+        }
+
+        // A uniform header dimension that disagrees with a tensor is rejected
+        let mut bytes = MPS::from_bytes(&data, 8).serialize();
+        bytes[4] = 3;
+        assert!(MPS::deserialize(&bytes).is_none());
+    }
+
+    #[test]
+    fn test_periodic_roundtrip() {
+        let data: Vec<u8> = (0..200).map(|i| ((i * 37) % 251) as u8).collect();
+        let mps = MPS::from_bytes_periodic(&data, 64);
+        assert!(mps.periodic);
+        assert_eq!(mps.bond_dims.len(), mps.tensors.len());
+
+        let restored = MPS::deserialize(&mps.serialize()).unwrap();
+        assert!(restored.periodic);
+        assert_eq!(restored.to_bytes(), data);
+    }
+
+    #[test]
+    fn test_npz_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..300).map(|i| ((i * 37) % 251) as u8).collect();
+
+        for mps in [MPS::from_bytes(&data, 8), MPS::from_bytes_periodic(&data, 8)] {
+            let path = dir.path().join("state.npz");
+            mps.to_npz(&path).unwrap();
+            let loaded = MPS::from_npz(&path).unwrap();
+
+            assert_eq!(loaded.bond_dims, mps.bond_dims);
+            assert_eq!((&loaded.phys_dims, loaded.len, loaded.periodic), (&mps.phys_dims, mps.len, mps.periodic));
+            for (a, b) in loaded.tensors.iter().zip(&mps.tensors) {
+                assert_eq!(a.dim(), b.dim());
+                assert!(a.iter().zip(b).all(|(x, y)| (x - y).norm() < 1e-12));
+            }
+            assert_eq!(loaded.to_bytes(), mps.to_bytes());
+        }
+
+        let path = dir.path().join("truncated.npz");
+        std::fs::write(&path, &std::fs::read(dir.path().join("state.npz")).unwrap()[..100]).unwrap();
+        assert!(MPS::from_npz(&path).is_err());
+    }
+
+    #[test]
+    fn test_inputs_smaller_than_rank_roundtrip() {
+        for len in 64..=256usize {
+            let data: Vec<u8> = (0..len).map(|i| ((i * 37 + 11) % 256) as u8 ^ (i as u8).rotate_left(3)).collect();
+            for mps in [MPS::from_bytes(&data, 64), MPS::from_bytes_periodic(&data, 64)] {
+                assert!(mps.bond_dims.iter().all(|&b| b <= len));
+                assert_eq!(mps.to_bytes(), data, "len {len}, periodic {}", mps.periodic);
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let data: Vec<u8> = (0..300).map(|i| ((i * i + 7 * i) % 256) as u8).collect();
+        let mut mps = MPS::from_bytes(&data, 64);
+        let before = mps.amplitudes();
+
+        let center = 4;
+        mps.canonicalize(center);
+        let after = mps.amplitudes();
+        for (x, y) in before.iter().zip(&after) {
+            assert!((x - y).norm() < 1e-9);
+        }
+        assert_eq!(mps.to_bytes(), data);
+
+        let identity_err = |g: Array2<Complex64>| {
+            g.indexed_iter()
+                .map(|((i, j), v)| (v - Complex64::new((i == j) as u8 as f64, 0.0)).norm())
+                .fold(0.0, f64::max)
+        };
+        for (site, t) in mps.tensors.iter().enumerate() {
+            let (l, d, r) = t.dim();
+            let t = t.as_standard_layout().into_owned();
+            if site < center {
+                let m = t.into_shape_with_order((l * d, r)).unwrap();
+                assert!(identity_err(m.t().mapv(|c| c.conj()).dot(&m)) < 1e-9);
+            } else if site > center {
+                let m = t.into_shape_with_order((l, d * r)).unwrap();
+                assert!(identity_err(m.dot(&m.t().mapv(|c| c.conj()))) < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_periodic_keeps_amplitudes() {
+        let data: Vec<u8> = (0..512u32).map(|i| ((i * 37) % 251) as u8 ^ (i >> 5) as u8).collect();
+        let mut mps = MPS::from_bytes_periodic(&data, 8);
+        let closing = *mps.bond_dims.last().unwrap();
+        assert!(closing > 1);
+        let before = mps.amplitudes();
+
+        let center = 3;
+        mps.canonicalize(center);
+        for (x, y) in before.iter().zip(&mps.amplitudes()) {
+            assert!((x - y).norm() < 1e-9);
+        }
+        assert_eq!(*mps.bond_dims.last().unwrap(), closing);
+
+        // Isometries with the closing index summed over, as if it were open
+        for (site, t) in mps.tensors.iter().enumerate().filter(|&(site, _)| site != center) {
+            let (l, d, r) = t.dim();
+            let t = t.as_standard_layout().into_owned();
+            let gram = if site < center {
+                let m = t.into_shape_with_order((l * d, r)).unwrap();
+                m.t().mapv(|c| c.conj()).dot(&m)
+            } else {
+                let m = t.into_shape_with_order((l, d * r)).unwrap();
+                m.dot(&m.t().mapv(|c| c.conj()))
+            };
+            for ((i, j), v) in gram.indexed_iter() {
+                assert!((v - Complex64::new((i == j) as u8 as f64, 0.0)).norm() < 1e-9);
+            }
+        }
+
+        // The closing bond stays ungauged: the center does not hold the norm
+        let center_norm = mps.tensors[center].iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+        assert!((center_norm - mps.norm()).abs() > 1e-6 * mps.norm());
+    }
+
+    #[test]
+    fn test_norm_matches_amplitudes() {
+        // A power of two, so no padding: periodic chains pad with wrapped data
+        let data: Vec<u8> = (0..4096u32).map(|i| ((i * 13) % 251) as u8 ^ (i >> 4) as u8).collect();
+        let direct = data.iter().map(|&b| (b as f64 / 255.0).powi(2)).sum::<f64>().sqrt();
+        for mut mps in [MPS::from_bytes(&data, 64), MPS::from_bytes_periodic(&data, 64)] {
+            assert_eq!(mps.to_bytes(), data);
+            assert!((mps.norm() - direct).abs() < 1e-9 * direct, "{} vs {direct}", mps.norm());
+
+            mps.normalize();
+            assert!((mps.norm() - 1.0).abs() < 1e-9);
+            let contracted = mps.amplitudes().iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt();
+            assert!((contracted - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_real_chain_halves_stored_params() {
+        let data: Vec<u8> = (0..2000u32).map(|i| ((i * 31) % 97 + (i >> 6)) as u8).collect();
+        let generic = MPS::from_bytes(&data, 32);
+        let mut real = generic.clone();
+        assert!(real.compress_complex_conjugate_symmetry());
+        assert_eq!(real.stored_params(), generic.stored_params() / 2);
+
+        // Same fidelity, half the tensor bytes
+        let fixed = 17 + 4 * generic.bond_dims.len() + 12 * generic.tensors.len();
+        let (generic_bytes, real_bytes) = (generic.serialize(), real.serialize());
+        assert_eq!(real_bytes.len() - fixed, (generic_bytes.len() - fixed) / 2);
+        let restored = MPS::deserialize(&real_bytes).unwrap();
+        assert!(restored.real);
+        assert_eq!(restored.to_bytes(), generic.to_bytes());
+
+        // Gauge phases on the tensors leave the amplitudes real
+        let mut phased = generic.clone();
+        phased.tensors[0].mapv_inplace(|c| c * Complex64::i());
+        phased.tensors[1].mapv_inplace(|c| c * -Complex64::i());
+        assert!(phased.compress_complex_conjugate_symmetry());
+        assert_eq!(phased.to_bytes(), generic.to_bytes());
+
+        // A genuinely complex state is left alone
+        let mut complex = generic.clone();
+        complex.tensors[0].mapv_inplace(|c| c * Complex64::new(0.6, 0.8));
+        assert!(!complex.compress_complex_conjugate_symmetry());
+        assert_eq!(complex.stored_params(), generic.stored_params());
+    }
+
+    #[test]
+    fn test_real_decomposition_is_faster_at_equal_fidelity() {
+        let data: Vec<u8> = (0..16384u32).map(|i| ((i * 31) % 97 + (i >> 6)) as u8).collect();
+        let fastest = |decompose: &dyn Fn() -> MPS| {
+            (0..5)
+                .map(|_| {
+                    let start = Instant::now();
+                    (decompose(), start.elapsed())
+                })
+                .min_by_key(|(_, elapsed)| *elapsed)
+                .unwrap()
+        };
+        let (complex, complex_time) = fastest(&|| MPS::from_bytes(&data, 32));
+        let (real, real_time) = fastest(&|| MPS::from_bytes_real(&data, 32, false));
+
+        assert!(real.real);
+        assert_eq!(real.bond_dims, complex.bond_dims);
+        assert_eq!(real.stored_params(), complex.stored_params() / 2);
+        let error = |mps: &MPS| {
+            let amplitudes = mps.amplitudes();
+            data.iter().zip(&amplitudes).map(|(&b, a)| (b as f64 / 255.0 - a.re).abs()).fold(0.0, f64::max)
+        };
+        assert!((error(&real) - error(&complex)).abs() < 1e-9);
+        assert!(real_time < complex_time, "real {real_time:?} vs complex {complex_time:?}");
+    }
+
+    #[test]
+    fn test_merge_adjacent_tensors_keeps_amplitudes() {
+        let data: Vec<u8> = (0..300).map(|i| ((i * i + 7 * i) % 256) as u8).collect();
+        for periodic in [false, true] {
+            let mut mps = if periodic { MPS::from_bytes_periodic(&data, 64) } else { MPS::from_bytes(&data, 64) };
+            let before = mps.amplitudes();
+            let sites = mps.tensors.len();
+
+            mps.merge_adjacent_tensors(4);
+            assert_eq!(mps.tensors.len(), sites.div_ceil(2));
+            assert!(mps.phys_dims.iter().all(|&d| d <= 4));
+            assert_eq!(mps.bond_dims.len(), mps.tensors.len() - !periodic as usize);
+            for (x, y) in before.iter().zip(&mps.amplitudes()) {
+                assert!((x - y).norm() < 1e-9);
+            }
+            assert_eq!(mps.to_bytes(), data);
+            assert_eq!(MPS::deserialize(&mps.serialize()).unwrap().to_bytes(), data);
+        }
+
+        // Pairs over the bound stay apart
+        let mut mps = MPS::from_bytes(&data, 64);
+        let sites = mps.tensors.len();
+        mps.merge_adjacent_tensors(3);
+        assert_eq!(mps.tensors.len(), sites);
+    }
+
+    #[test]
+    fn test_indexed_reconstruct_shuffled() {
+        use rand::seq::SliceRandom;
+
+        let data: Vec<u8> = (0..4500).map(|i| ((i / 64) % 7 * 30) as u8).collect();
+        let mut chunks = parallel_compress_indexed(&data, 64, 4);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().enumerate().all(|(i, (index, _))| i == *index));
+
+        chunks.shuffle(&mut rand::thread_rng());
+        assert_eq!(reconstruct_indexed(&chunks), data);
+    }
+
+    #[test]
+    fn test_param_count_and_ratio_estimate() {
+        let data: Vec<u8> = (0..4096).map(|i| ((i % 64) * 3) as u8).collect();
+        let mps = MPS::from_bytes(&data, 8);
+
+        let summed: usize = mps.tensors.iter().map(|t| t.dim().0 * t.dim().1 * t.dim().2).sum();
+        assert_eq!(mps.n_params(), summed);
+
+        let actual = data.len() as f64 / mps.serialize().len() as f64;
+        assert!((mps.compression_ratio_estimate(data.len()) - actual).abs() < 1e-9);
+        assert!(actual > 1.0);
+    }
+
+    #[test]
+    fn test_sample_matches_to_bytes() {
+        let data: Vec<u8> = (0..3000).map(|i| ((i * 7 + i / 13) % 256) as u8).collect();
+        let mixed: Vec<Complex64> = data[..360].iter().map(|&b| Complex64::new(b as f64 / 255.0, 0.0)).collect();
+        let chains = [
+            MPS::from_bytes(&data, 8),
+            MPS::from_bytes_periodic(&data, 8),
+            MPS::from_amplitudes_with_dims(&mixed, &[3, 4, 5, 6], 8, false),
+        ];
+        for mps in &chains {
+            let bytes = mps.to_bytes();
+            for index in [0, 1, 17, 255, mps.len / 2, mps.len - 1] {
+                assert_eq!(mps.sample(index), Some(bytes[index]), "index {index}");
+            }
+            assert_eq!(mps.sample(mps.len), None);
+            assert_eq!(mps.sample(usize::MAX), None);
+        }
+    }
+
+    #[test]
+    fn test_update_byte_changes_only_that_byte() {
+        let data: Vec<u8> = (0..700).map(|i| ((i * 11 + i / 9) % 256) as u8).collect();
+        let mixed: Vec<Complex64> = data[..360].iter().map(|&b| Complex64::new(b as f64 / 255.0, 0.0)).collect();
+        let chains = [
+            MPS::from_bytes(&data, 4),
+            MPS::from_bytes_periodic(&data, 4),
+            MPS::from_amplitudes_with_dims(&mixed, &[3, 4, 5, 6], 4, false),
+        ];
+        for mut mps in chains {
+            let before = mps.to_bytes();
+            let bonds = mps.bond_dims.clone();
+            for (index, value) in [(0, 255u8), (mps.len / 3, 7), (mps.len - 1, 128)] {
+                mps.update_byte(index, value);
+            }
+
+            let after = mps.to_bytes();
+            assert_eq!((after[0], after[mps.len / 3], after[mps.len - 1]), (255, 7, 128));
+            for (i, (&a, &b)) in after.iter().zip(&before).enumerate() {
+                if ![0, mps.len / 3, mps.len - 1].contains(&i) {
+                    assert_eq!(a, b, "byte {i} changed");
+                }
+            }
+            assert!(mps.bond_dims.iter().zip(&bonds).all(|(&now, &was)| now <= was + 3));
+
+            // The edited chain still serializes and reads back
+            assert_eq!(MPS::deserialize(&mps.serialize()).unwrap().to_bytes(), after);
+
+            // Rewriting a byte with its own value changes nothing
+            let tensors = mps.tensors.clone();
+            mps.update_byte(5, after[5]);
+            assert_eq!(mps.tensors, tensors);
+        }
+
+        // A hundred edits leave every bond within its unfolding's rank,
+        // where growing one per edit would have passed it several times over
+        let mut mps = MPS::from_bytes(&data, 8);
+        let mut expected = mps.to_bytes();
+        for edit in 0..100 {
+            let (index, value) = ((edit * 37) % data.len(), (edit * 53 % 256) as u8);
+            mps.update_byte(index, value);
+            expected[index] = value;
+        }
+        assert_eq!(mps.to_bytes(), expected);
+        let sites = mps.tensors.len() as u32;
+        for (bond, &rank) in mps.bond_dims.iter().enumerate() {
+            let left = bond as u32 + 1;
+            assert!(rank <= 2usize.pow(left.min(sites - left)), "bond {bond} at {rank}");
+        }
+
+        // An all-zero chain has no span to share, and still takes the edit
+        let mut zeros = MPS::zeros(64);
+        zeros.update_byte(9, 200);
+        assert_eq!(zeros.to_bytes().iter().filter(|&&b| b != 0).count(), 1);
+        assert_eq!(zeros.sample(9), Some(200));
+    }
+
+    #[test]
+    fn test_contract_matches_naive() {
+        // Long enough for 13 sites, so the leading one splits two windows
+        let data: Vec<u8> = (0..5000).map(|i| ((i * 29 + i / 7) % 256) as u8).collect();
+        for mps in [MPS::from_bytes(&data, 6), MPS::from_bytes_periodic(&data, 6)] {
+            assert_eq!(mps.window_count(), 2);
+            let contracted = mps.contract();
+            let sites = mps.tensors.len();
+            for (index, amp) in contracted.iter().enumerate() {
+                // Product of the per-site matrices selected by each digit, traced
+                let mut product = Array2::eye(mps.tensors[0].shape()[0]);
+                for (site, tensor) in mps.tensors.iter().enumerate() {
+                    let digit = (index >> (sites - 1 - site)) & 1;
+                    product = product.dot(&tensor.index_axis(ndarray::Axis(1), digit));
+                }
+                let naive: Complex64 = product.diag().sum();
+                assert!((amp - naive).norm() < 1e-9);
+            }
+        }
     }
 }
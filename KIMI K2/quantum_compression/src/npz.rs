@@ -0,0 +1,279 @@
+//! Minimal NumPy `.npy` / `.npz` support
+//!
+//! Covers what MPS export needs: little-endian `complex128`, `float64` and
+//! `int64` arrays in C order, bundled in an uncompressed zip as
+//! `numpy.savez` writes them. Archives are read through the zip central
+//! directory, so the ZIP64 extras NumPy puts in local headers are skipped
+//! rather than parsed; deflated (`savez_compressed`) members are rejected.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+const LOCAL_HEADER_SIG: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIG: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY_SIG: u32 = 0x0605_4b50;
+/// DOS date of 1980-01-01, the zip epoch
+const ZIP_EPOCH_DATE: u16 = 0x21;
+
+/// Element type of an [`Array`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dtype {
+    Complex128,
+    Float64,
+    Int64,
+}
+
+impl Dtype {
+    fn descr(self) -> &'static str {
+        match self {
+            Dtype::Complex128 => "<c16",
+            Dtype::Float64 => "<f8",
+            Dtype::Int64 => "<i8",
+        }
+    }
+
+    fn from_descr(descr: &str) -> Option<Self> {
+        match descr {
+            "<c16" => Some(Dtype::Complex128),
+            "<f8" => Some(Dtype::Float64),
+            "<i8" => Some(Dtype::Int64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Dtype::Complex128 => 16,
+            Dtype::Float64 | Dtype::Int64 => 8,
+        }
+    }
+}
+
+/// A C-order array with its raw little-endian element bytes
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Array {
+    pub dtype: Dtype,
+    pub shape: Vec<usize>,
+    pub data: Vec<u8>,
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("malformed npz: {what}"))
+}
+
+/// Encode `array` as a version 1.0 `.npy` file
+pub(crate) fn write_npy(array: &Array) -> Vec<u8> {
+    let shape = match array.shape.as_slice() {
+        [n] => format!("({n},)"),
+        dims => format!("({})", dims.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header =
+        format!("{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}", array.dtype.descr());
+    // Pad so the data starts on a 64-byte boundary, header ending in '\n'
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut output = Vec::with_capacity(NPY_MAGIC.len() + 4 + header.len() + array.data.len());
+    output.extend_from_slice(NPY_MAGIC);
+    output.extend_from_slice(&[1, 0]);
+    output.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    output.extend_from_slice(header.as_bytes());
+    output.extend_from_slice(&array.data);
+    output
+}
+
+/// Decode a `.npy` file (format versions 1 to 3)
+pub(crate) fn read_npy(bytes: &[u8]) -> io::Result<Array> {
+    if bytes.get(..6) != Some(NPY_MAGIC) {
+        return Err(invalid("bad .npy magic"));
+    }
+    let (header_len, start) = match bytes.get(6) {
+        Some(1) => (read_u16(bytes, 8)? as usize, 10),
+        Some(2 | 3) => (read_u32(bytes, 8)? as usize, 12),
+        _ => return Err(invalid("unsupported .npy version")),
+    };
+    let header = bytes
+        .get(start..start + header_len)
+        .and_then(|h| std::str::from_utf8(h).ok())
+        .ok_or_else(|| invalid("truncated .npy header"))?;
+
+    let descr = header_value(header, "descr").ok_or_else(|| invalid("missing descr"))?;
+    let dtype = Dtype::from_descr(descr.trim_matches('\'')).ok_or_else(|| invalid("unsupported dtype"))?;
+    if header_value(header, "fortran_order") != Some("False") {
+        return Err(invalid("Fortran-order arrays are not supported"));
+    }
+    let shape = header_value(header, "shape")
+        .and_then(|s| s.strip_prefix('(')?.strip_suffix(')'))
+        .ok_or_else(|| invalid("missing shape"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse().map_err(|_| invalid("bad shape")))
+        .collect::<io::Result<Vec<usize>>>()?;
+
+    let data = &bytes[start + header_len..];
+    let count = shape.iter().try_fold(1usize, |n, &d| n.checked_mul(d));
+    if count.and_then(|n| n.checked_mul(dtype.size())) != Some(data.len()) {
+        return Err(invalid("data length does not match shape"));
+    }
+    Ok(Array { dtype, shape, data: data.to_vec() })
+}
+
+/// The literal following `'key':` in a `.npy` header dict
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let rest = header[header.find(&format!("'{key}'"))? + key.len() + 2..]
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start();
+    let end = match rest.as_bytes().first()? {
+        b'(' => rest.find(')')? + 1,
+        b'\'' => rest[1..].find('\'')? + 2,
+        _ => rest.find([',', '}'])?,
+    };
+    Some(rest[..end].trim())
+}
+
+/// Write named arrays to an uncompressed `.npz` at `path`; each name gets
+/// the `.npy` suffix NumPy strips on load
+pub(crate) fn write_npz(path: &Path, arrays: &[(String, Array)]) -> io::Result<()> {
+    let mut output = Vec::new();
+    let mut directory = Vec::new();
+
+    for (name, array) in arrays {
+        let name = format!("{name}.npy");
+        let npy = write_npy(array);
+        let crc = crc32fast::hash(&npy);
+        let offset = u32::try_from(output.len()).map_err(|_| invalid("archive exceeds 4 GiB"))?;
+        let size = u32::try_from(npy.len()).map_err(|_| invalid("array exceeds 4 GiB"))?;
+
+        // Local header: signature, version 2.0, no flags, stored, DOS time/date
+        output.extend_from_slice(&LOCAL_HEADER_SIG.to_le_bytes());
+        for field in [20, 0, 0, 0, ZIP_EPOCH_DATE] {
+            output.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [crc, size, size] {
+            output.extend_from_slice(&field.to_le_bytes());
+        }
+        output.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        output.extend_from_slice(&0u16.to_le_bytes());
+        output.extend_from_slice(name.as_bytes());
+        output.extend_from_slice(&npy);
+
+        directory.extend_from_slice(&CENTRAL_HEADER_SIG.to_le_bytes());
+        for field in [20, 20, 0, 0, 0, ZIP_EPOCH_DATE] {
+            directory.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [crc, size, size] {
+            directory.extend_from_slice(&field.to_le_bytes());
+        }
+        // Name length, then no extra, comment, disk or internal attributes
+        for field in [name.len() as u16, 0, 0, 0, 0] {
+            directory.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [0, offset] {
+            directory.extend_from_slice(&field.to_le_bytes());
+        }
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    let directory_offset = output.len() as u32;
+    output.extend_from_slice(&directory);
+    output.extend_from_slice(&END_OF_DIRECTORY_SIG.to_le_bytes());
+    for field in [0, 0, arrays.len() as u16, arrays.len() as u16] {
+        output.extend_from_slice(&field.to_le_bytes());
+    }
+    output.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    output.extend_from_slice(&directory_offset.to_le_bytes());
+    output.extend_from_slice(&0u16.to_le_bytes());
+
+    fs::write(path, output)
+}
+
+/// Read every array of an uncompressed `.npz`, names without `.npy`
+pub(crate) fn read_npz(path: &Path) -> io::Result<Vec<(String, Array)>> {
+    let zip = fs::read(path)?;
+
+    // The end record sits in the last 22 bytes plus any archive comment
+    let end = (0..=zip.len().saturating_sub(22))
+        .rev()
+        .take(22 + u16::MAX as usize)
+        .find(|&pos| read_u32(&zip, pos).ok() == Some(END_OF_DIRECTORY_SIG))
+        .ok_or_else(|| invalid("no end of central directory"))?;
+    let count = read_u16(&zip, end + 10)? as usize;
+    let mut pos = read_u32(&zip, end + 16)? as usize;
+
+    let mut arrays = Vec::with_capacity(count.min(zip.len() / 46));
+    for _ in 0..count {
+        if read_u32(&zip, pos)? != CENTRAL_HEADER_SIG {
+            return Err(invalid("bad central directory entry"));
+        }
+        if read_u16(&zip, pos + 10)? != 0 {
+            return Err(invalid("compressed members are not supported"));
+        }
+        let crc = read_u32(&zip, pos + 16)?;
+        let size = read_u32(&zip, pos + 20)? as usize;
+        let name_len = read_u16(&zip, pos + 28)? as usize;
+        let skip = read_u16(&zip, pos + 30)? as usize + read_u16(&zip, pos + 32)? as usize;
+        let local = read_u32(&zip, pos + 42)? as usize;
+        let name = zip
+            .get(pos + 46..pos + 46 + name_len)
+            .and_then(|n| std::str::from_utf8(n).ok())
+            .ok_or_else(|| invalid("bad member name"))?;
+        pos += 46 + name_len + skip;
+
+        if read_u32(&zip, local)? != LOCAL_HEADER_SIG {
+            return Err(invalid("bad local header"));
+        }
+        let start = local + 30 + read_u16(&zip, local + 26)? as usize + read_u16(&zip, local + 28)? as usize;
+        let npy = zip.get(start..start + size).ok_or_else(|| invalid("truncated member"))?;
+        if crc32fast::hash(npy) != crc {
+            return Err(invalid("member checksum mismatch"));
+        }
+
+        let name = name.strip_suffix(".npy").unwrap_or(name).to_string();
+        arrays.push((name, read_npy(npy)?));
+    }
+    Ok(arrays)
+}
+
+fn read_u16(data: &[u8], pos: usize) -> io::Result<u16> {
+    let bytes = data.get(pos..pos + 2).ok_or_else(|| invalid("truncated"))?;
+    Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> io::Result<u32> {
+    let bytes = data.get(pos..pos + 4).ok_or_else(|| invalid("truncated"))?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_npy_header_layout() {
+        let array = Array { dtype: Dtype::Int64, shape: vec![3], data: vec![0; 24] };
+        let npy = write_npy(&array);
+        let data_start = npy.len() - 24;
+        assert_eq!(data_start % 64, 0);
+        assert_eq!(npy[data_start - 1], b'\n');
+        assert!(std::str::from_utf8(&npy[10..data_start]).unwrap().contains("'shape': (3,)"));
+        assert_eq!(read_npy(&npy).unwrap(), array);
+
+        // Hand-built headers: a 0-d array, and one in Fortran order
+        let npy_with = |header: &str| {
+            let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+            npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+            npy.extend_from_slice(header.as_bytes());
+            npy.extend_from_slice(&[0; 8]);
+            npy
+        };
+        let scalar = read_npy(&npy_with("{'descr': '<f8', 'fortran_order': False, 'shape': (), }")).unwrap();
+        assert_eq!((scalar.dtype, scalar.shape.len()), (Dtype::Float64, 0));
+        assert!(read_npy(&npy_with("{'descr': '<f8', 'fortran_order': True, 'shape': (), }")).is_err());
+        assert!(read_npy(&npy_with("{'descr': '>f8', 'fortran_order': False, 'shape': (), }")).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+//! Explicit transform pipelines
+//!
+//! A [`Pipeline`] lists the transforms a chunk goes through, in order, in
+//! place of the automatic method search in `compress`. The stage list is
+//! recorded in every block so `decompress` can replay the inverses, last
+//! stage first, without being told how the data was produced.
+
+use crate::bpe;
+use crate::compress::{decode_huffman_block, decode_mps_residual, encode_huffman_block, encode_mps_residual};
+use crate::error::{CompressionError, Result};
+use crate::Config;
+use serde::{Deserialize, Serialize};
+
+/// Bytes per recorded stage: tag u8 + parameter u32
+const STAGE_LEN: usize = 5;
+
+/// One transform in a [`Pipeline`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stage {
+    /// Replace each byte by its difference from the previous one
+    Delta,
+    /// Keep only the top `bits` bits of each byte (lossy below 8)
+    Quantize(u8),
+    /// Run-length code as (run length - 1, byte) pairs
+    Rle,
+    /// Byte-pair merges, at most this many
+    Bpe(usize),
+    /// MPS decomposition at this maximum rank, plus a lossless residual
+    Mps(usize),
+    /// Canonical Huffman coding
+    Huffman,
+}
+
+impl Stage {
+    fn tag(self) -> u8 {
+        match self {
+            Stage::Delta => 1,
+            Stage::Quantize(_) => 2,
+            Stage::Rle => 3,
+            Stage::Bpe(_) => 4,
+            Stage::Mps(_) => 5,
+            Stage::Huffman => 6,
+        }
+    }
+
+    fn param(self) -> u32 {
+        match self {
+            Stage::Quantize(bits) => bits as u32,
+            Stage::Bpe(merges) | Stage::Mps(merges) => merges as u32,
+            Stage::Delta | Stage::Rle | Stage::Huffman => 0,
+        }
+    }
+
+    fn from_parts(tag: u8, param: u32) -> Option<Self> {
+        match tag {
+            1 => Some(Stage::Delta),
+            2 if (1..=8).contains(&param) => Some(Stage::Quantize(param as u8)),
+            3 => Some(Stage::Rle),
+            4 => Some(Stage::Bpe(param as usize)),
+            5 => Some(Stage::Mps(param as usize)),
+            6 => Some(Stage::Huffman),
+            _ => None,
+        }
+    }
+
+    /// Whether the inverse cannot restore the input exactly
+    fn is_lossy(self) -> bool {
+        matches!(self, Stage::Quantize(bits) if bits < 8)
+    }
+
+    /// Run the transform, returning its output and the MPS rank used (0 if none)
+    fn apply(self, data: &[u8], config: &Config) -> (Vec<u8>, usize) {
+        match self {
+            Stage::Delta => {
+                let mut prev = 0u8;
+                let out = data
+                    .iter()
+                    .map(|&b| {
+                        let d = b.wrapping_sub(prev);
+                        prev = b;
+                        d
+                    })
+                    .collect();
+                (out, 0)
+            }
+            Stage::Quantize(bits) => (data.iter().map(|&b| b >> (8 - bits)).collect(), 0),
+            Stage::Rle => {
+                let mut out = Vec::new();
+                let mut i = 0;
+                while i < data.len() {
+                    let run = data[i..].iter().take(256).take_while(|&&b| b == data[i]).count();
+                    out.extend_from_slice(&[(run - 1) as u8, data[i]]);
+                    i += run;
+                }
+                (out, 0)
+            }
+            Stage::Bpe(max_merges) => {
+                let (merges, encoded) = bpe::encode(data, max_merges);
+                let mut out = Vec::with_capacity(1 + 3 * merges.len() + encoded.len());
+                bpe::serialize_merges(&merges, &mut out);
+                out.extend_from_slice(&encoded);
+                (out, 0)
+            }
+            Stage::Mps(rank) => encode_mps_residual(data, rank, config),
+            Stage::Huffman => (encode_huffman_block(data, config), 0),
+        }
+    }
+
+    /// Undo [`Stage::apply`]; quantized bytes come back at their bucket midpoint
+    fn invert(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Stage::Delta => {
+                let mut prev = 0u8;
+                Ok(data
+                    .iter()
+                    .map(|&d| {
+                        prev = prev.wrapping_add(d);
+                        prev
+                    })
+                    .collect())
+            }
+            Stage::Quantize(bits) => {
+                let shift = 8 - bits;
+                let midpoint = (1u8 << shift) >> 1;
+                Ok(data.iter().map(|&q| (q << shift) | midpoint).collect())
+            }
+            Stage::Rle => {
+                if !data.len().is_multiple_of(2) {
+                    return Err(CompressionError::DecompressionFailed);
+                }
+                let mut out = Vec::with_capacity(data.len());
+                for pair in data.chunks_exact(2) {
+                    out.extend(std::iter::repeat_n(pair[1], pair[0] as usize + 1));
+                }
+                Ok(out)
+            }
+            Stage::Bpe(_) => {
+                let (merges, used) =
+                    bpe::deserialize_merges(data).ok_or(CompressionError::DecompressionFailed)?;
+                Ok(bpe::decode(&data[used..], &merges))
+            }
+            Stage::Mps(_) => decode_mps_residual(data),
+            Stage::Huffman => decode_huffman_block(data),
+        }
+    }
+}
+
+/// An ordered list of transforms applied to every chunk.
+///
+/// Built fluently, e.g. `Pipeline::new().delta().mps(16).huffman()`, and
+/// set as `Config::pipeline`. Stages run in the order given; `decompress`
+/// undoes them in reverse. Output is lossless unless a [`Stage::Quantize`]
+/// below 8 bits is included.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    /// An empty pipeline (chunks are stored as-is)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a stage
+    pub fn stage(mut self, stage: Stage) -> Self {
+        assert!(self.stages.len() < 255, "a pipeline holds at most 255 stages");
+        self.stages.push(stage);
+        self
+    }
+
+    /// Append a [`Stage::Delta`]
+    pub fn delta(self) -> Self {
+        self.stage(Stage::Delta)
+    }
+
+    /// Append a [`Stage::Quantize`]; `bits` is clamped to `1..=8`
+    pub fn quantize(self, bits: u8) -> Self {
+        self.stage(Stage::Quantize(bits.clamp(1, 8)))
+    }
+
+    /// Append a [`Stage::Rle`]
+    pub fn rle(self) -> Self {
+        self.stage(Stage::Rle)
+    }
+
+    /// Append a [`Stage::Bpe`]; the merge table holds at most 255 entries
+    pub fn bpe(self, merges: usize) -> Self {
+        self.stage(Stage::Bpe(merges.min(255)))
+    }
+
+    /// Append a [`Stage::Mps`]
+    pub fn mps(self, max_rank: usize) -> Self {
+        self.stage(Stage::Mps(max_rank.clamp(1, u32::MAX as usize)))
+    }
+
+    /// Append a [`Stage::Huffman`]
+    pub fn huffman(self) -> Self {
+        self.stage(Stage::Huffman)
+    }
+
+    /// Stages in application order
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// Whether decompression cannot reproduce the input exactly
+    pub fn is_lossy(&self) -> bool {
+        self.stages.iter().any(|s| s.is_lossy())
+    }
+
+    /// Run every stage over `chunk`. Layout: stage count u8 + (tag u8 +
+    /// parameter u32) per stage + the last stage's output. Returns the
+    /// payload and the largest MPS rank used.
+    pub(crate) fn encode(&self, chunk: &[u8], config: &Config) -> (Vec<u8>, usize) {
+        let mut data = chunk.to_vec();
+        let mut rank = 0;
+        for stage in &self.stages {
+            let (out, stage_rank) = stage.apply(&data, config);
+            data = out;
+            rank = rank.max(stage_rank);
+        }
+
+        let mut payload = Vec::with_capacity(1 + STAGE_LEN * self.stages.len() + data.len());
+        payload.push(self.stages.len() as u8);
+        for stage in &self.stages {
+            payload.push(stage.tag());
+            payload.extend_from_slice(&stage.param().to_le_bytes());
+        }
+        payload.extend_from_slice(&data);
+        (payload, rank)
+    }
+}
+
+/// Read the stage list recorded by [`Pipeline::encode`], returning the
+/// pipeline and the transformed data that follows it
+pub(crate) fn read_stages(payload: &[u8]) -> Result<(Pipeline, &[u8])> {
+    let count = *payload.first().ok_or(CompressionError::DecompressionFailed)? as usize;
+    let end = 1 + STAGE_LEN * count;
+    let table = payload.get(1..end).ok_or(CompressionError::DecompressionFailed)?;
+
+    let stages = table
+        .chunks_exact(STAGE_LEN)
+        .map(|s| Stage::from_parts(s[0], u32::from_le_bytes(s[1..5].try_into().unwrap())))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(CompressionError::DecompressionFailed)?;
+    Ok((Pipeline { stages }, &payload[end..]))
+}
+
+/// Undo a pipeline block, inverting the recorded stages last to first
+pub(crate) fn decode(payload: &[u8]) -> Result<Vec<u8>> {
+    let (pipeline, body) = read_stages(payload)?;
+    invert(&pipeline, body)
+}
+
+/// Undo a pipeline block with `pipeline` rather than the stages it
+/// records, whose tags need not be ones this version knows
+pub(crate) fn decode_with(payload: &[u8], pipeline: &Pipeline) -> Result<Vec<u8>> {
+    let count = *payload.first().ok_or(CompressionError::DecompressionFailed)? as usize;
+    let body = payload.get(1 + STAGE_LEN * count..).ok_or(CompressionError::DecompressionFailed)?;
+    invert(pipeline, body)
+}
+
+/// Invert `pipeline`'s stages over `body`, last to first
+fn invert(pipeline: &Pipeline, body: &[u8]) -> Result<Vec<u8>> {
+    let mut data = body.to_vec();
+    for stage in pipeline.stages.iter().rev() {
+        data = stage.invert(&data)?;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress::{compress, decompress, parse_container, BlockMethod};
+
+    #[test]
+    fn test_two_stage_pipeline_inverts_in_reverse() {
+        // A stepped ramp: delta turns it into long runs for RLE
+        let data: Vec<u8> = (0..4000).map(|i| (i / 50) as u8).collect();
+        let pipeline = Pipeline::new().delta().rle();
+        let config = Config { pipeline: Some(pipeline.clone()), ..Config::default() };
+
+        let (compressed, stats) = compress(&data, &config).unwrap();
+        assert!(stats.compression_ratio > 10.0);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        let container = parse_container(&compressed).unwrap();
+        let block = &container.blocks[0];
+        assert_eq!(block.method, BlockMethod::Pipeline);
+        let (recorded, body) = read_stages(block.payload).unwrap();
+        assert_eq!(recorded, pipeline);
+
+        // RLE must be undone before delta; the other order garbles the data
+        let restored = Stage::Delta.invert(&Stage::Rle.invert(body).unwrap()).unwrap();
+        assert_eq!(restored, data);
+        let wrong_order = Stage::Delta.invert(body).and_then(|d| Stage::Rle.invert(&d));
+        assert!(wrong_order.map_or(true, |d| d != data));
+    }
+
+    #[test]
+    fn test_lossy_pipeline_checks_its_own_reconstruction() {
+        let data: Vec<u8> = (0..3000).map(|i| ((i as f64 * 0.02).sin() * 120.0 + 128.0) as u8).collect();
+        let config = Config { pipeline: Some(Pipeline::new().quantize(4).huffman()), ..Config::default() };
+
+        let (compressed, _) = compress(&data, &config).unwrap();
+        let restored = decompress(&compressed).unwrap();
+        assert_eq!(restored.len(), data.len());
+        assert!(data.iter().zip(&restored).all(|(&a, &b)| a.abs_diff(b) <= 8));
+    }
+}
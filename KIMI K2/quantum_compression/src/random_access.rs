@@ -0,0 +1,185 @@
+//! Random access into memory-mapped containers
+//!
+//! Every framed block records its raw length, so the block list is an index
+//! from decompressed offsets to chunks. [`RandomAccessArchive`] maps a
+//! container file, builds that index once, and decodes a chunk only when a
+//! [`read`](RandomAccessArchive::read) first touches it.
+
+use crate::compress::{decode_block, parse_container, Block, BlockMethod};
+use crate::error::{CompressionError, Result};
+use memmap2::Mmap;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Where a block sits in the mapped file
+#[derive(Debug, Clone, Copy)]
+struct BlockEntry {
+    method: BlockMethod,
+    flags: u8,
+    raw_len: usize,
+    checksum: u32,
+    /// Payload byte range in the file
+    offset: usize,
+    payload_len: usize,
+}
+
+/// A container file read by decompressed offset, decoding chunks lazily.
+///
+/// Decoded chunks are kept for later reads, so memory grows with the chunks
+/// touched, up to the whole decompressed size. Each chunk's CRC32 is
+/// checked as it is decoded; the whole-data checksum cannot be, since the
+/// whole data is never assembled. Reads take `&self` and may come from
+/// several threads.
+pub struct RandomAccessArchive {
+    map: Mmap,
+    blocks: Vec<BlockEntry>,
+    /// Decompressed offset each block starts at, then the total length
+    starts: Vec<usize>,
+    cache: Mutex<HashMap<usize, Arc<Vec<u8>>>>,
+    decoded: AtomicUsize,
+}
+
+impl RandomAccessArchive {
+    /// Map the container at `path` and index its blocks. The file must not
+    /// be modified while the archive is open.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only read, and callers are told not to
+        // change the file underneath it
+        let map = unsafe { Mmap::map(&file)? };
+
+        let container = parse_container(&map)?;
+        let blocks: Vec<BlockEntry> = container
+            .blocks
+            .iter()
+            .map(|b| BlockEntry {
+                method: b.method,
+                flags: b.flags,
+                raw_len: b.raw_len,
+                checksum: b.checksum,
+                offset: b.offset,
+                payload_len: b.payload.len(),
+            })
+            .collect();
+        let starts = std::iter::once(0)
+            .chain(blocks.iter().scan(0, |end, b| {
+                *end += b.raw_len;
+                Some(*end)
+            }))
+            .collect();
+        drop(container);
+
+        Ok(RandomAccessArchive { map, blocks, starts, cache: Mutex::new(HashMap::new()), decoded: AtomicUsize::new(0) })
+    }
+
+    /// Decompressed length of the whole container
+    pub fn len(&self) -> usize {
+        self.starts[self.blocks.len()]
+    }
+
+    /// Whether the container holds no data
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `len` decompressed bytes at `offset`, decoding only the chunks
+    /// they fall in. A range past the end fails with an
+    /// [`io::ErrorKind::UnexpectedEof`] error.
+    pub fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        let end = offset.checked_add(len).filter(|&end| end <= self.len()).ok_or_else(|| {
+            CompressionError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "range past the end of the archive"))
+        })?;
+        let mut output = Vec::with_capacity(len);
+        let mut index = self.starts.partition_point(|&start| start <= offset).saturating_sub(1);
+        while output.len() < len {
+            let chunk = self.chunk(index)?;
+            let start = self.starts[index];
+            let from = offset.max(start) - start;
+            let to = end.min(start + chunk.len()) - start;
+            output.extend_from_slice(&chunk[from..to]);
+            index += 1;
+        }
+        Ok(output)
+    }
+
+    /// Chunks decoded so far; a chunk is decoded at most once
+    pub fn chunks_decoded(&self) -> usize {
+        self.decoded.load(Ordering::Relaxed)
+    }
+
+    /// The decoded chunk of block `index`, from the cache if it was read before
+    fn chunk(&self, index: usize) -> Result<Arc<Vec<u8>>> {
+        if let Some(chunk) = self.cache.lock().unwrap_or_else(PoisonError::into_inner).get(&index) {
+            return Ok(Arc::clone(chunk));
+        }
+
+        // Decode without the lock so other threads are not held up
+        let entry = self.blocks[index];
+        let block = Block {
+            index,
+            offset: entry.offset,
+            method: entry.method,
+            flags: entry.flags,
+            raw_len: entry.raw_len,
+            checksum: entry.checksum,
+            payload: &self.map[entry.offset..entry.offset + entry.payload_len],
+        };
+        let chunk = decode_block(&block, None)?;
+        if chunk.len() != entry.raw_len {
+            return Err(CompressionError::DecompressionFailed);
+        }
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let chunk = cache.entry(index).or_insert_with(|| {
+            self.decoded.fetch_add(1, Ordering::Relaxed);
+            Arc::new(chunk)
+        });
+        Ok(Arc::clone(chunk))
+    }
+}
+
+impl std::fmt::Debug for RandomAccessArchive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RandomAccessArchive")
+            .field("len", &self.len())
+            .field("blocks", &self.blocks.len())
+            .field("chunks_decoded", &self.chunks_decoded())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress, Config};
+
+    #[test]
+    fn test_scattered_reads_decode_only_their_chunks() {
+        let data: Vec<u8> = (0..40_000u32).map(|i| ((i * 7) % 61 + (i >> 10) % 5) as u8).collect();
+        let (compressed, _) = compress(&data, &Config { chunk_size: 4096, ..Config::default() }).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("circuit.qc");
+        std::fs::write(&path, &compressed).unwrap();
+
+        let archive = RandomAccessArchive::open(&path).unwrap();
+        let full = decompress(&compressed).unwrap();
+        assert_eq!(archive.len(), full.len());
+        assert_eq!(archive.chunks_decoded(), 0);
+
+        // Chunk 0, a span across chunks 2 and 3, and the tail of chunk 9
+        for (offset, len) in [(10, 100), (4096 * 3 - 50, 200), (39_990, 10)] {
+            assert_eq!(archive.read(offset, len).unwrap(), &full[offset..offset + len]);
+        }
+        assert_eq!(archive.chunks_decoded(), 4);
+
+        // Decoded chunks are reused, and reads stay in bounds
+        assert_eq!(archive.read(4096 * 2, 4096).unwrap(), &full[4096 * 2..4096 * 3]);
+        assert_eq!(archive.chunks_decoded(), 4);
+        assert!(archive.read(0, 0).unwrap().is_empty());
+        assert!(archive.read(39_990, 11).is_err());
+    }
+}
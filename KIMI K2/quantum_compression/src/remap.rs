@@ -0,0 +1,119 @@
+//! Alphabet reduction
+//!
+//! A chunk that uses only `k` distinct byte values is rewritten over the
+//! dense alphabet `0..k`, keeping the values' order. The MPS stage works on
+//! amplitudes, so squeezing the values together shrinks the residual it
+//! leaves in byte units. Alphabets of up to 16 symbols are also packed two
+//! to a byte, which lets the entropy coders that follow see symbol pairs.
+
+/// Largest alphabet worth remapping: the range must at least halve
+pub const MAX_ALPHABET: usize = 128;
+
+/// Largest alphabet whose indices are packed as nibbles
+pub const PACK_ALPHABET: usize = 16;
+
+/// The distinct byte values of `data`, ascending
+pub fn alphabet(data: &[u8]) -> Vec<u8> {
+    let mut present = [false; 256];
+    data.iter().for_each(|&b| present[b as usize] = true);
+    (0..=255u8).filter(|&b| present[b as usize]).collect()
+}
+
+/// Serialized table: count - 1 u8 + the symbols. `alphabet` must hold
+/// between 1 and 256 symbols.
+pub fn table(alphabet: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(1 + alphabet.len());
+    output.push((alphabet.len() - 1) as u8);
+    output.extend_from_slice(alphabet);
+    output
+}
+
+/// Split a [`table`] off the front of `data`, returning the symbols and
+/// what follows
+pub fn split_table(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let count = *data.first()? as usize + 1;
+    let symbols = data.get(1..1 + count)?;
+    Some((symbols, &data[1 + count..]))
+}
+
+/// Replace each byte of `data` by its index in `alphabet`, which must hold
+/// every value of `data`; indices into a small alphabet are packed high
+/// nibble first, an odd tail padded with zero
+pub fn encode(data: &[u8], alphabet: &[u8]) -> Vec<u8> {
+    let mut index = [0u8; 256];
+    for (i, &symbol) in alphabet.iter().enumerate() {
+        index[symbol as usize] = i as u8;
+    }
+    let indices = data.iter().map(|&b| index[b as usize]);
+    if alphabet.len() > PACK_ALPHABET {
+        return indices.collect();
+    }
+    let indices: Vec<u8> = indices.collect();
+    indices.chunks(2).map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)).collect()
+}
+
+/// Length of the [`encode`] output for `len` input bytes
+pub fn encoded_len(len: usize, alphabet: &[u8]) -> usize {
+    if alphabet.len() > PACK_ALPHABET {
+        len
+    } else {
+        len.div_ceil(2)
+    }
+}
+
+/// Undo [`encode`] for `len` original bytes; `None` if an index falls
+/// outside `alphabet` or the length is off
+pub fn decode(data: &[u8], alphabet: &[u8], len: usize) -> Option<Vec<u8>> {
+    if data.len() != encoded_len(len, alphabet) {
+        return None;
+    }
+    let output: Vec<u8> = decode_iter(data.iter().copied(), alphabet, len).collect();
+    (output.len() == len).then_some(output)
+}
+
+/// Streaming [`decode`]; output stops at `len` bytes, or early on an index
+/// outside `alphabet`
+pub fn decode_iter<'a>(
+    bytes: impl Iterator<Item = u8> + 'a,
+    alphabet: &'a [u8],
+    len: usize,
+) -> impl Iterator<Item = u8> + 'a {
+    let packed = alphabet.len() <= PACK_ALPHABET;
+    bytes
+        .flat_map(move |b| if packed { [Some(b >> 4), Some(b & 0x0F)] } else { [Some(b), None] })
+        .flatten()
+        .take(len)
+        .map_while(|i| alphabet.get(i as usize).copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap_roundtrip() {
+        let data = b"abracadabra".to_vec();
+        let symbols = alphabet(&data);
+        assert_eq!(symbols, b"abcdr");
+
+        // Five symbols pack two to a byte; the odd tail is zero-padded
+        let remapped = encode(&data, &symbols);
+        assert_eq!(remapped, [0x01, 0x40, 0x20, 0x30, 0x14, 0x00]);
+        assert_eq!(decode(&remapped, &symbols, data.len()).unwrap(), data);
+        assert!(decode(&remapped, &symbols, data.len() - 2).is_none());
+        assert!(decode(&[0x05], &symbols, 2).is_none());
+
+        // Wider alphabets keep a byte per symbol
+        let wide: Vec<u8> = (0..40).map(|i| i * 3).collect();
+        let wide_symbols = alphabet(&wide);
+        let indices = encode(&wide, &wide_symbols);
+        assert_eq!(indices, (0..40).collect::<Vec<u8>>());
+        assert_eq!(decode(&indices, &wide_symbols, 40).unwrap(), wide);
+
+        let mut framed = table(&symbols);
+        framed.extend_from_slice(&remapped);
+        let (read, rest) = split_table(&framed).unwrap();
+        assert_eq!((read, rest), (&symbols[..], &remapped[..]));
+        assert!(split_table(&framed[..3]).is_none());
+    }
+}
@@ -0,0 +1,197 @@
+//! Strategy comparison reports
+//!
+//! `compress` searches methods per chunk and keeps only the winner. For
+//! tuning, [`compress_report`] instead runs each built-in strategy over the
+//! whole input as a fixed [`Pipeline`] and reports every size and time, so
+//! the margin between them is measured rather than guessed.
+
+use crate::compress::{compress, require_chunked, seal};
+use crate::error::Result;
+use crate::pipeline::Pipeline;
+use crate::Config;
+use serde::Serialize;
+use std::fmt;
+use std::time::Instant;
+
+/// A strategy [`compress_report`] runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum CandidateStrategy {
+    /// Bytes kept verbatim ([`seal`])
+    Stored,
+    /// Huffman coding alone
+    Huffman,
+    /// MPS at `Config::max_rank` plus residual, Huffman coded
+    MpsHuffman,
+    /// Run-length coding, then Huffman
+    RleHuffman,
+    /// Run-length coding, then MPS and Huffman
+    RleMpsHuffman,
+}
+
+impl CandidateStrategy {
+    /// Every strategy, in the order reports list them
+    pub const ALL: [CandidateStrategy; 5] = [
+        CandidateStrategy::Stored,
+        CandidateStrategy::Huffman,
+        CandidateStrategy::MpsHuffman,
+        CandidateStrategy::RleHuffman,
+        CandidateStrategy::RleMpsHuffman,
+    ];
+
+    /// The pipeline this strategy runs (`None` for stored)
+    fn pipeline(self, max_rank: usize) -> Option<Pipeline> {
+        match self {
+            CandidateStrategy::Stored => None,
+            CandidateStrategy::Huffman => Some(Pipeline::new().huffman()),
+            CandidateStrategy::MpsHuffman => Some(Pipeline::new().mps(max_rank).huffman()),
+            CandidateStrategy::RleHuffman => Some(Pipeline::new().rle().huffman()),
+            CandidateStrategy::RleMpsHuffman => Some(Pipeline::new().rle().mps(max_rank).huffman()),
+        }
+    }
+}
+
+/// One strategy's run in a [`StrategyComparison`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CandidateResult {
+    pub strategy: CandidateStrategy,
+    /// Size of the whole container
+    pub compressed_size: usize,
+    pub processing_time_ms: f64,
+}
+
+/// Every built-in strategy's result on one input
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StrategyComparison {
+    pub original_size: usize,
+    /// One entry per [`CandidateStrategy::ALL`], in that order
+    pub results: Vec<CandidateResult>,
+    /// Strategy with the smallest container; the earlier one on a tie
+    pub winner: CandidateStrategy,
+}
+
+/// Compress `data` with every [`CandidateStrategy`] and compare them.
+///
+/// Each strategy codes every chunk the same way, with the rest of `config`
+/// (chunking, format version, precision) as given; `config.pipeline` is
+/// overridden. Strategies run one after another so their times do not
+/// contend.
+pub fn compress_report(data: &[u8], config: &Config) -> Result<StrategyComparison> {
+    require_chunked(config, "strategy pipelines need a chunked container")?;
+    let mut results = Vec::with_capacity(CandidateStrategy::ALL.len());
+    for strategy in CandidateStrategy::ALL {
+        let start = Instant::now();
+        let compressed_size = match strategy.pipeline(config.max_rank) {
+            Some(pipeline) => compress(data, &Config { pipeline: Some(pipeline), ..config.clone() })?.0.len(),
+            None => seal(data).len(),
+        };
+        let processing_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+        results.push(CandidateResult { strategy, compressed_size, processing_time_ms });
+    }
+
+    let winner = results
+        .iter()
+        .min_by_key(|r| r.compressed_size)
+        .map(|r| r.strategy)
+        .expect("every strategy runs");
+    Ok(StrategyComparison { original_size: data.len(), results, winner })
+}
+
+impl fmt::Display for StrategyComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let best = self.results.iter().map(|r| r.compressed_size).min().unwrap_or(0);
+        write!(f, "{} bytes in", self.original_size)?;
+        for r in &self.results {
+            let marker = if r.strategy == self.winner { " *" } else { "" };
+            write!(
+                f,
+                "\n  {:<14} {:>10} bytes  ratio {:>7.2}  +{:>6.1}%  {:>9.2} ms{marker}",
+                format!("{:?}", r.strategy),
+                r.compressed_size,
+                self.original_size as f64 / r.compressed_size as f64,
+                (r.compressed_size - best) as f64 * 100.0 / best.max(1) as f64,
+                r.processing_time_ms,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// One row of the compression-ratio comparison against zlib, for the
+/// `vs_zlib` benchmark (feature `testing`)
+#[cfg(any(test, feature = "testing"))]
+#[derive(Debug, Clone, PartialEq, Serialize, serde::Deserialize)]
+pub struct RatioReport {
+    pub size: usize,
+    pub our_ratio: f64,
+    pub zlib_ratio: f64,
+    pub improvement: f64,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl RatioReport {
+    /// Compress `data` with the default config and with zlib
+    pub fn measure(data: &[u8]) -> Self {
+        let (stats, zlib_ratio, improvement) = crate::compress::benchmark_vs_zlib(data);
+        RatioReport { size: data.len(), our_ratio: stats.compression_ratio, zlib_ratio, improvement }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl fmt::Display for RatioReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Size {}:", self.size)?;
+        writeln!(f, "  Quantum MPS: {:.2}x compression", self.our_ratio)?;
+        writeln!(f, "  zlib:        {:.2}x compression", self.zlib_ratio)?;
+        write!(f, "  Improvement: {:.2}x over zlib", self.improvement)
+    }
+}
+
+/// Write ratio reports as a JSON array, for charting trends across commits
+#[cfg(any(test, feature = "testing"))]
+pub fn write_ratio_reports(path: impl AsRef<std::path::Path>, reports: &[RatioReport]) -> Result<()> {
+    std::fs::write(path, serde_json::to_vec_pretty(reports)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompress;
+
+    #[test]
+    fn test_report_covers_every_strategy() {
+        let mut data = b"cx q0 q1; rz(0.125) q1; ".repeat(150);
+        data.extend(std::iter::repeat_n(0u8, 3000));
+        let report = compress_report(&data, &Config::default()).unwrap();
+
+        let strategies: Vec<_> = report.results.iter().map(|r| r.strategy).collect();
+        assert_eq!(strategies, CandidateStrategy::ALL);
+        let smallest = report.results.iter().map(|r| r.compressed_size).min().unwrap();
+        let winner = report.results.iter().find(|r| r.strategy == report.winner).unwrap();
+        assert_eq!(winner.compressed_size, smallest);
+        assert_ne!(report.winner, CandidateStrategy::Stored);
+
+        // Each size is what that pipeline really produces
+        let rle = Config { pipeline: CandidateStrategy::RleHuffman.pipeline(64), ..Config::default() };
+        let (compressed, _) = compress(&data, &rle).unwrap();
+        assert_eq!(report.results[3].compressed_size, compressed.len());
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        assert!(report.to_string().lines().any(|line| line.ends_with(" *")));
+    }
+
+    #[test]
+    fn test_ratio_reports_json_matches_print() {
+        let data: Vec<u8> = (0..4096).map(|i| ((i * 17) % 64) as u8).collect();
+        let reports = vec![RatioReport::measure(&data[..1024]), RatioReport::measure(&data)];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ratios.json");
+        write_ratio_reports(&path, &reports).unwrap();
+
+        let parsed: Vec<RatioReport> = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(parsed, reports);
+        for (json, printed) in parsed.iter().zip(&reports) {
+            assert_eq!(json.to_string(), printed.to_string());
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Run-length coding for extremely skewed chunks
+//!
+//! When one byte value makes up nearly all of a chunk, Huffman still spends
+//! at least one bit on every occurrence of it. Coding the runs of that byte
+//! between the other bytes costs a few bytes per outlier instead, which for
+//! a 99.9%-dominant chunk lands close to the entropy.
+
+/// Share of the most frequent byte at which a chunk is worth run-length
+/// coding
+pub const SKEW_THRESHOLD: f64 = 0.99;
+
+/// Most frequent byte of `data` and its share (0 for empty input)
+pub fn dominant_byte(data: &[u8]) -> (u8, f64) {
+    let mut counts = [0usize; 256];
+    for &b in data {
+        counts[b as usize] += 1;
+    }
+    let (byte, &count) = counts
+        .iter()
+        .enumerate()
+        .max_by_key(|&(_, count)| count)
+        .expect("256 counts");
+    (byte as u8, if data.is_empty() { 0.0 } else { count as f64 / data.len() as f64 })
+}
+
+/// Code `data` as runs of `dominant`.
+///
+/// Layout: LEB128 length of `data`, `dominant`, then for each other byte
+/// the LEB128 length of the run before it followed by the byte itself, then
+/// the LEB128 length of the trailing run.
+pub fn encode(data: &[u8], dominant: u8) -> Vec<u8> {
+    let mut output = Vec::new();
+    write_varint(data.len() as u64, &mut output);
+    output.push(dominant);
+    let mut run = 0u64;
+    for &b in data {
+        if b == dominant {
+            run += 1;
+        } else {
+            write_varint(run, &mut output);
+            output.push(b);
+            run = 0;
+        }
+    }
+    write_varint(run, &mut output);
+    output
+}
+
+/// Undo [`encode`]; `None` if the runs fall short of the recorded length
+pub fn decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut runs = decode_iter(data.iter().copied());
+    let len = runs.header()?;
+    let output: Vec<u8> = runs.by_ref().collect();
+    (output.len() as u64 == len).then_some(output)
+}
+
+/// Streaming [`decode`]; output stops at the recorded length, or earlier
+/// on malformed input
+pub fn decode_iter<I: Iterator<Item = u8>>(bytes: I) -> Runs<I> {
+    Runs { bytes, header: None, run: 0, literal: None }
+}
+
+/// Iterator returned by [`decode_iter`]
+pub struct Runs<I> {
+    bytes: I,
+    /// Bytes still to produce, and the dominant byte
+    header: Option<(u64, u8)>,
+    run: u64,
+    literal: Option<u8>,
+}
+
+impl<I: Iterator<Item = u8>> Runs<I> {
+    /// Read the header if needed, returning the recorded length
+    fn header(&mut self) -> Option<u64> {
+        if self.header.is_none() {
+            let len = read_varint(&mut self.bytes)?;
+            self.header = Some((len, self.bytes.next()?));
+        }
+        self.header.map(|(len, _)| len)
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Runs<I> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.header()?;
+        let (remaining, dominant) = self.header.as_mut()?;
+        if *remaining == 0 {
+            return None;
+        }
+        *remaining -= 1;
+        loop {
+            if self.run > 0 {
+                self.run -= 1;
+                return Some(*dominant);
+            }
+            if let Some(literal) = self.literal.take() {
+                return Some(literal);
+            }
+            self.run = read_varint(&mut self.bytes)?;
+            // `None` after the trailing run
+            self.literal = self.bytes.next();
+            if self.run == 0 && self.literal.is_none() {
+                return None;
+            }
+        }
+    }
+}
+
+fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Read a LEB128 value; `None` if truncated or longer than 64 bits
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_roundtrip() {
+        let mut data = vec![0xAA; 1000];
+        data[0] = 1;
+        data[500] = 2;
+        data.extend_from_slice(&[3, 4]);
+        let (dominant, share) = dominant_byte(&data);
+        assert_eq!(dominant, 0xAA);
+        assert!(share > 0.99);
+
+        let encoded = encode(&data, dominant);
+        // Length (2 bytes), dominant, 0 + 1, 499 (2 bytes) + 2, 499 (2 bytes)
+        // + 3, 0 + 4, trailing 0
+        assert_eq!(encoded.len(), 2 + 1 + 2 + 3 + 3 + 2 + 1);
+        assert_eq!(decode(&encoded).unwrap(), data);
+        assert!(decode(&encoded[..encoded.len() - 3]).is_none());
+
+        // A length beyond what the runs hold is rejected
+        let mut overlong = encoded.clone();
+        overlong[0] += 1;
+        assert!(decode(&overlong).is_none());
+    }
+}
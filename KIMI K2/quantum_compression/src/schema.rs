@@ -0,0 +1,120 @@
+//! Self-describing containers
+//!
+//! With `Config::embed_schema` set, `compress` opens a chunked container
+//! with a schema block: a zero-length frame whose payload is a CBOR
+//! [`Schema`] recording the writer's version, the layout and the coding
+//! parameters. Stages are recorded by name rather than by the numeric tags
+//! blocks carry, so `decompress` undoes pipeline blocks from the schema and
+//! a reader needs no metadata kept outside the file.
+
+use crate::compress::{parse_container, verify_checksum, Block, BlockMethod};
+use crate::mps::Precision;
+use crate::pipeline::{Pipeline, Stage};
+use crate::Config;
+use serde::{Deserialize, Serialize};
+
+/// How a container was produced, as embedded by `Config::embed_schema`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schema {
+    /// Version of this crate that wrote the container
+    pub writer_version: String,
+    /// Container layout (`format::VERSION_CHUNKED` or `format::VERSION`)
+    pub format_version: u8,
+    pub chunk_size: usize,
+    pub chunk_alignment: usize,
+    pub max_rank: usize,
+    pub periodic: bool,
+    pub precision: Precision,
+    pub exploit_real: bool,
+    /// Stages of `Config::pipeline` in application order (`None` = the
+    /// automatic method search)
+    pub stages: Option<Vec<Stage>>,
+}
+
+impl Schema {
+    /// The schema of a container written with `config`
+    pub fn from_config(config: &Config) -> Self {
+        Schema {
+            writer_version: env!("CARGO_PKG_VERSION").to_string(),
+            format_version: config.format_version,
+            chunk_size: config.chunk_size,
+            chunk_alignment: config.chunk_alignment,
+            max_rank: config.max_rank,
+            periodic: config.periodic,
+            precision: config.precision,
+            exploit_real: config.exploit_real,
+            stages: config.pipeline.as_ref().map(|p| p.stages().to_vec()),
+        }
+    }
+
+    /// The pipeline every block went through, if one was configured
+    pub fn pipeline(&self) -> Option<Pipeline> {
+        let stages = self.stages.as_ref()?;
+        Some(stages.iter().fold(Pipeline::new(), |pipeline, &stage| pipeline.stage(stage)))
+    }
+
+    /// CBOR encoding, the schema block's payload
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        ciborium::into_writer(self, &mut payload).expect("a schema always serializes");
+        payload
+    }
+}
+
+/// The schema embedded in `compressed`, if it opens with an intact one
+pub fn read_schema(compressed: &[u8]) -> Option<Schema> {
+    from_blocks(&parse_container(compressed).ok()?.blocks)
+}
+
+/// The schema in the first of `blocks`, if that is a schema block this
+/// version can read
+pub(crate) fn from_blocks(blocks: &[Block]) -> Option<Schema> {
+    let block = blocks.first().filter(|b| b.method == BlockMethod::Schema)?;
+    verify_checksum(block).ok()?;
+    ciborium::from_reader(block.payload).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress};
+
+    #[test]
+    fn test_embedded_schema_drives_decoding() {
+        let data: Vec<u8> = (0..6000).map(|i| (i / 40) as u8).collect();
+        let pipeline = Pipeline::new().delta().rle().huffman();
+        let config = Config { embed_schema: true, chunk_size: 2048, pipeline: Some(pipeline.clone()), ..Config::default() };
+        let (mut compressed, _) = compress(&data, &config).unwrap();
+
+        let schema = read_schema(&compressed).unwrap();
+        assert_eq!(schema, Schema::from_config(&config));
+        assert_eq!(schema.pipeline(), Some(pipeline));
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        let (plain, _) = compress(&data, &Config { embed_schema: false, ..config }).unwrap();
+        assert_eq!(read_schema(&plain), None);
+
+        // Blank every block's own stage tags (fixing its CRC): only the
+        // schema is left to say how to invert the blocks
+        let frames: Vec<(usize, usize)> = parse_container(&compressed)
+            .unwrap()
+            .blocks
+            .iter()
+            .filter(|b| b.method == BlockMethod::Pipeline)
+            .map(|b| (b.offset, b.payload.len()))
+            .collect();
+        assert_eq!(frames.len(), 3);
+        for (offset, len) in frames {
+            for stage in 0..3 {
+                compressed[offset + 1 + 5 * stage] = 0xEE;
+            }
+            let crc = crc32fast::hash(&compressed[offset..offset + len]);
+            compressed[offset - 4..offset].copy_from_slice(&crc.to_le_bytes());
+        }
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        // Tiny inputs get a framed container so they can carry the schema
+        let (tiny, _) = compress(b"h q0;", &Config { embed_schema: true, ..Config::default() }).unwrap();
+        assert_eq!(read_schema(&tiny).unwrap().stages, None);
+        assert_eq!(decompress(&tiny).unwrap(), b"h q0;");
+    }
+}
@@ -0,0 +1,158 @@
+//! Runtime SIMD dispatch
+//!
+//! Detects CPU features at runtime and selects the widest histogram path the
+//! host supports, unless [`TargetPlatform`] pins a narrower one.
+
+/// SIMD width hint for the byte-histogram and encode paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetPlatform {
+    /// Pick the widest path supported by the running CPU
+    #[default]
+    Auto,
+    /// Force portable scalar code (reproducibility testing)
+    Scalar,
+    /// Use SSE2 if available, otherwise scalar
+    Sse2,
+    /// Use AVX2 if available, otherwise the best narrower path
+    Avx2,
+}
+
+/// Histogram implementation actually selected at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SimdLevel {
+    Scalar,
+    Sse2,
+    Avx2,
+}
+
+/// Widest SIMD level supported by the running CPU
+pub fn detect() -> SimdLevel {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return SimdLevel::Avx2;
+        }
+        if is_x86_feature_detected!("sse2") {
+            return SimdLevel::Sse2;
+        }
+    }
+    SimdLevel::Scalar
+}
+
+impl TargetPlatform {
+    /// Resolve the hint against the running CPU
+    pub fn resolve(self) -> SimdLevel {
+        let available = detect();
+        match self {
+            TargetPlatform::Auto => available,
+            TargetPlatform::Scalar => SimdLevel::Scalar,
+            TargetPlatform::Sse2 => available.min(SimdLevel::Sse2),
+            TargetPlatform::Avx2 => available.min(SimdLevel::Avx2),
+        }
+    }
+
+    /// The hint to use for `len` bytes: scalar below `threshold`, where the
+    /// SIMD paths' setup costs more than they save
+    pub fn for_len(self, len: usize, threshold: usize) -> TargetPlatform {
+        if len < threshold {
+            TargetPlatform::Scalar
+        } else {
+            self
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Level the last [`histogram`] call on this thread ran at
+    static LAST_LEVEL: std::cell::Cell<Option<SimdLevel>> = const { std::cell::Cell::new(None) };
+}
+
+/// Level the last [`histogram`] call on this thread ran at, for tests
+#[cfg(test)]
+pub(crate) fn last_level() -> Option<SimdLevel> {
+    LAST_LEVEL.with(|level| level.get())
+}
+
+/// Count byte frequencies using the path selected by `platform`
+pub fn histogram(data: &[u8], platform: TargetPlatform) -> [u64; 256] {
+    let level = platform.resolve();
+    #[cfg(test)]
+    LAST_LEVEL.with(|last| last.set(Some(level)));
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: `resolve` only returns Avx2 when the CPU reports AVX2
+        SimdLevel::Avx2 => unsafe { histogram_avx2(data) },
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: `resolve` only returns Sse2 when the CPU reports SSE2
+        SimdLevel::Sse2 => unsafe { histogram_sse2(data) },
+        _ => histogram_scalar(data),
+    }
+}
+
+/// Portable single-table histogram
+pub fn histogram_scalar(data: &[u8]) -> [u64; 256] {
+    let mut freq = [0u64; 256];
+    for &byte in data {
+        freq[byte as usize] += 1;
+    }
+    freq
+}
+
+/// Four interleaved sub-histograms break the store-to-load dependency on
+/// runs of equal bytes; the merge vectorizes under the enabled feature set.
+#[inline(always)]
+fn histogram_interleaved(data: &[u8]) -> [u64; 256] {
+    let mut tables = [[0u32; 256]; 4];
+    let mut freq = [0u64; 256];
+
+    // u32 counters: flush before any can overflow
+    for block in data.chunks(u32::MAX as usize) {
+        let mut quads = block.chunks_exact(4);
+        for q in &mut quads {
+            tables[0][q[0] as usize] += 1;
+            tables[1][q[1] as usize] += 1;
+            tables[2][q[2] as usize] += 1;
+            tables[3][q[3] as usize] += 1;
+        }
+        for &byte in quads.remainder() {
+            tables[0][byte as usize] += 1;
+        }
+
+        for (i, total) in freq.iter_mut().enumerate() {
+            *total += (tables[0][i] + tables[1][i]) as u64 + (tables[2][i] + tables[3][i]) as u64;
+        }
+        tables = [[0u32; 256]; 4];
+    }
+
+    freq
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn histogram_avx2(data: &[u8]) -> [u64; 256] {
+    histogram_interleaved(data)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn histogram_sse2(data: &[u8]) -> [u64; 256] {
+    histogram_interleaved(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CorpusKind;
+
+    #[test]
+    fn test_scalar_matches_auto() {
+        let data = CorpusKind::Random.generate(10, 100_003);
+
+        let auto = histogram(&data, TargetPlatform::Auto);
+        let scalar = histogram(&data, TargetPlatform::Scalar);
+        assert_eq!(auto, scalar);
+        assert_eq!(scalar.iter().sum::<u64>(), data.len() as u64);
+        assert_eq!(TargetPlatform::Scalar.resolve(), SimdLevel::Scalar);
+    }
+}
@@ -0,0 +1,93 @@
+//! Zero-suppressed (sparse) byte layout
+//!
+//! Circuit matrices are often mostly zero. A sparse chunk is rewritten as a
+//! presence bitmap (one bit per byte, least significant bit first) followed
+//! by its nonzero bytes in order. The bitmap packs eight zeros into one
+//! byte, so the entropy coders that follow pay for the zeros once per eight
+//! instead of once each.
+
+/// Fraction of `data` that is zero (0 for empty input)
+pub fn zero_fraction(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    data.iter().filter(|&&b| b == 0).count() as f64 / data.len() as f64
+}
+
+/// Rewrite `data` as bitmap + nonzero values
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut output = vec![0u8; data.len().div_ceil(8)];
+    for (i, &b) in data.iter().enumerate() {
+        if b != 0 {
+            output[i / 8] |= 1 << (i % 8);
+        }
+    }
+    output.extend(data.iter().copied().filter(|&b| b != 0));
+    output
+}
+
+/// Undo [`encode`] for `len` original bytes. Returns `None` unless the
+/// values match the bitmap exactly.
+pub fn decode(data: &[u8], len: usize) -> Option<Vec<u8>> {
+    let output: Vec<u8> = decode_iter(data.iter().copied(), len)?.collect();
+    let values = data.len() - len.div_ceil(8);
+    let nonzero = output.iter().filter(|&&b| b != 0).count();
+    (output.len() == len && nonzero == values).then_some(output)
+}
+
+/// Streaming [`decode`]: the bitmap is read up front, then values are
+/// pulled from `bytes` as set bits call for them. A short value stream
+/// ends the output early. Returns `None` if the bitmap itself is short.
+pub fn decode_iter<I: Iterator<Item = u8>>(mut bytes: I, len: usize) -> Option<Scatter<I>> {
+    let bitmap: Vec<u8> = bytes.by_ref().take(len.div_ceil(8)).collect();
+    if bitmap.len() != len.div_ceil(8) {
+        return None;
+    }
+    Some(Scatter { bitmap, values: bytes, pos: 0, len })
+}
+
+/// Iterator returned by [`decode_iter`]
+pub struct Scatter<I> {
+    bitmap: Vec<u8>,
+    values: I,
+    pos: usize,
+    len: usize,
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Scatter<I> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.pos == self.len {
+            return None;
+        }
+        let present = self.bitmap[self.pos / 8] >> (self.pos % 8) & 1 == 1;
+        self.pos += 1;
+        if present {
+            // A zero value here would not survive re-encoding
+            self.values.next().filter(|&b| b != 0)
+        } else {
+            Some(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_roundtrip() {
+        let data: Vec<u8> = (0..1001).map(|i| if i % 17 == 0 { (i % 251) as u8 + 1 } else { 0 }).collect();
+        let encoded = encode(&data);
+        assert_eq!(encoded.len(), 126 + 59);
+        assert_eq!(decode(&encoded, data.len()).unwrap(), data);
+
+        // A missing or surplus value is rejected
+        assert!(decode(&encoded[..encoded.len() - 1], data.len()).is_none());
+        let mut surplus = encoded.clone();
+        surplus.push(7);
+        assert!(decode(&surplus, data.len()).is_none());
+        assert!(decode(&encoded[..100], data.len()).is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Aggregate analysis of compression runs
+//!
+//! Summarizes many [`CompressionStats`] (one per file of a dataset, say)
+//! into ratio and throughput distributions for benchmarking reports.
+
+use crate::CompressionStats;
+use serde::Serialize;
+use std::fmt;
+
+/// Summary of one quantity across runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Distribution {
+    pub mean: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    /// Summarize `values`; all zeros when empty. Percentiles interpolate
+    /// linearly between the nearest ranks.
+    pub fn of(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        Distribution {
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            median: percentile(&sorted, 0.5),
+            p95: percentile(&sorted, 0.95),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// The `q` quantile (0..=1) of ascending, non-empty `sorted`
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = q * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+}
+
+/// Aggregate view of a set of compression runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StatsSummary {
+    /// Number of runs summarized
+    pub count: usize,
+    /// Compression ratio (original / compressed)
+    pub ratio: Distribution,
+    /// Compression throughput in MB/s; runs with no recorded time are left out
+    pub throughput_mb_s: Distribution,
+    /// Fraction of runs in which every chunk was stored verbatim
+    pub stored_fraction: f64,
+}
+
+/// Summarize `runs`
+pub fn analyze(runs: &[CompressionStats]) -> StatsSummary {
+    if runs.is_empty() {
+        return StatsSummary::default();
+    }
+
+    let ratios: Vec<f64> = runs.iter().map(|s| s.compression_ratio).collect();
+    let throughputs: Vec<f64> = runs
+        .iter()
+        .filter(|s| s.processing_time_ms > 0.0)
+        .map(|s| s.original_size as f64 / 1e6 / (s.processing_time_ms / 1000.0))
+        .collect();
+    let stored = runs.iter().filter(|s| s.stored_fallback).count();
+
+    StatsSummary {
+        count: runs.len(),
+        ratio: Distribution::of(&ratios),
+        throughput_mb_s: Distribution::of(&throughputs),
+        stored_fraction: stored as f64 / runs.len() as f64,
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mean {:.2}, median {:.2}, p95 {:.2} (min {:.2}, max {:.2})",
+            self.mean, self.median, self.p95, self.min, self.max
+        )
+    }
+}
+
+impl fmt::Display for StatsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "runs:       {}", self.count)?;
+        writeln!(f, "ratio:      {}", self.ratio)?;
+        writeln!(f, "throughput: {} MB/s", self.throughput_mb_s)?;
+        write!(f, "stored:     {:.1}%", self.stored_fraction * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_percentiles() {
+        // Ratios 1..=20, each run 1 MB in 10 ms (100 MB/s); every fifth stored
+        let runs: Vec<CompressionStats> = (1..=20)
+            .map(|i| {
+                let mut stats = CompressionStats::new(1_000_000, 1_000_000 / i, 10.0);
+                stats.compression_ratio = i as f64;
+                stats.stored_fallback = i % 5 == 0;
+                stats
+            })
+            .collect();
+        let summary = analyze(&runs);
+
+        assert_eq!(summary.count, 20);
+        assert!((summary.ratio.mean - 10.5).abs() < 1e-9);
+        assert!((summary.ratio.median - 10.5).abs() < 1e-9);
+        assert!((summary.ratio.p95 - 19.05).abs() < 1e-9);
+        assert_eq!((summary.ratio.min, summary.ratio.max), (1.0, 20.0));
+        assert!((summary.throughput_mb_s.median - 100.0).abs() < 1e-9);
+        assert_eq!(summary.stored_fraction, 0.2);
+
+        let text = summary.to_string();
+        assert!(text.contains("median 10.50"));
+        assert!(text.contains("20.0%"));
+        let json = serde_json::to_value(&summary).unwrap();
+        assert!((json["ratio"]["p95"].as_f64().unwrap() - 19.05).abs() < 1e-9);
+
+        assert_eq!(analyze(&[]), StatsSummary::default());
+    }
+}
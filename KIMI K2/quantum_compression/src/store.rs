@@ -0,0 +1,194 @@
+//! Content-addressed chunk store
+//!
+//! Backups of many similar files repeat whole chunks. [`compress_with_store`]
+//! keeps each distinct chunk once in a caller-provided [`ChunkStore`], as
+//! its own compressed container under its [`chunk_hash`], and returns a
+//! container whose blocks only name those hashes. The same store must be
+//! handed to [`decompress_with_store`] to read it back.
+
+use crate::compress::{
+    coalesce_chunks, compress, decode_block, decompress, parse_container, plan_aligned_chunks, push_frame,
+    require_chunked, verify_checksum, verify_data_checksum, BlockMethod, MIN_INPUT_SIZE, VERSION,
+};
+use crate::error::{CompressionError, Result};
+use crate::format::Header;
+use crate::{CompressionStats, Config};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+/// Where [`compress_with_store`] keeps compressed chunks, by [`chunk_hash`]
+pub trait ChunkStore {
+    /// The compressed chunk stored under `hash`
+    fn get(&self, hash: u64) -> Option<Vec<u8>>;
+
+    /// Keep `compressed` under `hash`
+    fn insert(&mut self, hash: u64, compressed: Vec<u8>);
+
+    /// Whether a chunk is stored under `hash`
+    fn contains(&self, hash: u64) -> bool {
+        self.get(hash).is_some()
+    }
+}
+
+impl ChunkStore for HashMap<u64, Vec<u8>> {
+    fn get(&self, hash: u64) -> Option<Vec<u8>> {
+        HashMap::get(self, &hash).cloned()
+    }
+
+    fn insert(&mut self, hash: u64, compressed: Vec<u8>) {
+        HashMap::insert(self, hash, compressed);
+    }
+
+    fn contains(&self, hash: u64) -> bool {
+        self.contains_key(&hash)
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of a chunk's bytes; the same on every platform
+/// and release, so stores can outlive the process that filled them
+pub fn chunk_hash(chunk: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    chunk
+        .iter()
+        .fold(FNV_OFFSET, |hash, &b| (hash ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Compress `data` against `store`.
+///
+/// Chunks the store lacks are compressed on their own and inserted once,
+/// even if they repeat within `data`; chunks it already holds are trusted by
+/// hash and not re-encoded. The returned container holds only a reference
+/// block per chunk, so its stats count those, not the stored chunks.
+pub fn compress_with_store(
+    data: &[u8],
+    config: &Config,
+    store: &mut dyn ChunkStore,
+) -> Result<(Vec<u8>, CompressionStats)> {
+    require_chunked(config, "chunk references need a chunked container")?;
+    if data.len() < MIN_INPUT_SIZE {
+        return Err(CompressionError::InputTooSmall(data.len()));
+    }
+    let start = Instant::now();
+
+    let planned = plan_aligned_chunks(data.len(), config.chunk_size, config.chunk_alignment);
+    let ranges = coalesce_chunks(planned, config.min_block_size);
+    let hashes: Vec<u64> = ranges.par_iter().map(|range| chunk_hash(&data[range.clone()])).collect();
+
+    let mut seen = HashSet::new();
+    let missing: Vec<usize> = (0..ranges.len())
+        .filter(|&i| seen.insert(hashes[i]) && !store.contains(hashes[i]))
+        .collect();
+    let compressed: Vec<Vec<u8>> = missing
+        .par_iter()
+        .map(|&i| compress(&data[ranges[i].clone()], config).map(|(chunk, _)| chunk))
+        .collect::<Result<_>>()?;
+    for (&i, chunk) in missing.iter().zip(compressed) {
+        store.insert(hashes[i], chunk);
+    }
+
+    let header = Header {
+        version: config.format_version,
+        block_count: ranges.len() as u32,
+        data_checksum: (config.format_version == VERSION).then(|| crc32fast::hash(data)),
+    };
+    let mut output = Vec::new();
+    header.write(&mut output);
+    for (range, hash) in ranges.iter().zip(&hashes) {
+        push_frame(&mut output, BlockMethod::ChunkRef as u8, range.len(), &hash.to_le_bytes());
+    }
+
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+    let stats = CompressionStats::new(data.len(), output.len(), elapsed);
+    Ok((output, stats))
+}
+
+/// Decompress a container from [`compress_with_store`], fetching its chunks
+/// from `store`. Fails with [`CompressionError::ChunkMissing`] for a chunk
+/// the store does not hold.
+pub fn decompress_with_store(compressed: &[u8], store: &dyn ChunkStore) -> Result<Vec<u8>> {
+    let container = parse_container(compressed)?;
+    let mut output = Vec::new();
+    for block in &container.blocks {
+        if block.method != BlockMethod::ChunkRef {
+            output.extend_from_slice(&decode_block(block, None)?);
+            continue;
+        }
+        verify_checksum(block)?;
+        let hash = reference_hash(block.payload)?;
+        let stored = store.get(hash).ok_or(CompressionError::ChunkMissing { hash })?;
+        let chunk = decompress(&stored)?;
+        if chunk.len() != block.raw_len {
+            return Err(CompressionError::DecompressionFailed);
+        }
+        output.extend_from_slice(&chunk);
+    }
+
+    verify_data_checksum(container.data_checksum, &output)?;
+    Ok(output)
+}
+
+/// Hash named by a chunk reference block body
+pub(crate) fn reference_hash(body: &[u8]) -> Result<u64> {
+    let bytes: [u8; 8] = body.try_into().map_err(|_| CompressionError::DecompressionFailed)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts insertions, which a map alone would hide
+    #[derive(Default)]
+    struct CountingStore {
+        chunks: HashMap<u64, Vec<u8>>,
+        inserts: usize,
+    }
+
+    impl ChunkStore for CountingStore {
+        fn get(&self, hash: u64) -> Option<Vec<u8>> {
+            self.chunks.get(&hash).cloned()
+        }
+
+        fn insert(&mut self, hash: u64, compressed: Vec<u8>) {
+            self.inserts += 1;
+            self.chunks.insert(hash, compressed);
+        }
+    }
+
+    #[test]
+    fn test_shared_chunk_stored_once() {
+        let shared = b"ccx q0 q1 q2; swap q1 q2; ".repeat(160);
+        let mut first = shared[..4096].to_vec();
+        first.extend(b"h q0; ".repeat(700).iter().take(4096));
+        let mut second: Vec<u8> = b"t q3; sdg q3; ".repeat(300).into_iter().take(4096).collect();
+        second.extend_from_slice(&shared[..4096]);
+
+        let config = Config { chunk_size: 4096, ..Config::default() };
+        let mut store = CountingStore::default();
+        let (first_refs, _) = compress_with_store(&first, &config, &mut store).unwrap();
+        let (second_refs, stats) = compress_with_store(&second, &config, &mut store).unwrap();
+        assert_eq!(store.inserts, 3);
+        assert!(store.chunks.contains_key(&chunk_hash(&shared[..4096])));
+        assert!(stats.compressed_size < 100);
+
+        assert_eq!(decompress_with_store(&first_refs, &store).unwrap(), first);
+        assert_eq!(decompress_with_store(&second_refs, &store).unwrap(), second);
+
+        // The references alone cannot be decoded
+        assert!(matches!(decompress(&first_refs), Err(CompressionError::ChunkStoreRequired { .. })));
+        let hash = chunk_hash(&first[4096..]);
+        store.chunks.remove(&hash);
+        match decompress_with_store(&first_refs, &store) {
+            Err(CompressionError::ChunkMissing { hash: missing }) => assert_eq!(missing, hash),
+            other => panic!("expected a missing chunk, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_chunk_hash_is_stable() {
+        assert_eq!(chunk_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(chunk_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,60 @@
+//! Allocation accounting for the chunked encoder
+//!
+//! Lives in its own test binary because it installs a global allocator.
+
+use quantum_compression::{compress, Config, EntropyModel};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Size of one order-1 frequency table (256 contexts × 256 u64 counts)
+const TABLE_BYTES: usize = 256 * 256 * 8;
+
+/// System allocator that counts table-sized allocations
+struct CountingAllocator;
+
+static TABLE_ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= TABLE_BYTES {
+            TABLE_ALLOCS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= TABLE_BYTES {
+            TABLE_ALLOCS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Test the order-1 frequency table is reused rather than allocated per chunk
+#[test]
+fn test_frequency_counter_reused_across_chunks() {
+    let text = b"the quick brown fox jumps over the lazy dog; ".repeat(1500);
+    let config = Config { chunk_size: 1024, entropy_model: EntropyModel::Order1, ..Default::default() };
+
+    // One worker thread: a per-chunk table would mean 64 large allocations
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    let (single, allocs) = pool.install(|| {
+        let before = TABLE_ALLOCS.load(Ordering::Relaxed);
+        let (compressed, _) = compress(&text[..64 * 1024], &config).unwrap();
+        (compressed, TABLE_ALLOCS.load(Ordering::Relaxed) - before)
+    });
+    println!("Table-sized allocations for 64 chunks: {}", allocs);
+    // rayon may split the work a few times, each split taking its own counter
+    assert!(allocs < 8);
+
+    // Reuse must not change the output, whatever the worker count
+    let (parallel, _) = compress(&text[..64 * 1024], &config).unwrap();
+    assert_eq!(parallel, single);
+}
@@ -2,24 +2,8 @@
 //!
 //! Self-validating test suite with memory pressure scenarios
 
+use quantum_compression::testing::{quantum_circuit_data, CorpusKind};
 use quantum_compression::{compress, decompress, Config};
-use rand::Rng;
-
-/// Generate random quantum circuit-like data
-fn generate_quantum_data(size: usize) -> Vec<u8> {
-    let mut rng = rand::thread_rng();
-    let mut data = Vec::with_capacity(size);
-    
-    // Simulate quantum circuit data patterns
-    for i in 0..size {
-        let phase = (i as f64 * 0.1).sin();
-        let amplitude = ((i % 256) as f64 / 255.0).cos();
-        let byte = ((phase * 0.5 + 0.5) * amplitude * 255.0) as u8;
-        data.push(byte.wrapping_add(rng.gen_range(0..16)));
-    }
-    
-    data
-}
 
 /// Test minimum viable input
 #[test]
@@ -33,7 +17,7 @@ fn test_minimum_input() {
 /// Test borderline tensor ranks
 #[test]
 fn test_tensor_rank_edges() {
-    let data = generate_quantum_data(1024);
+    let data = quantum_circuit_data(0, 1024);
     
     // Rank 1 (minimum)
     let config = Config { max_rank: 1, ..Default::default() };
@@ -53,12 +37,13 @@ fn test_size_variations() {
     let config = Config::default();
     
     for size in sizes {
-        let data = generate_quantum_data(size);
+        let data = quantum_circuit_data(0, size);
         let result = compress(&data, &config);
         assert!(result.is_ok(), "Failed for size {}", size);
         
         let (compressed, stats) = result.unwrap();
         println!("Size {}: ratio {:.2}", size, stats.compression_ratio);
+        assert_eq!(decompress(&compressed).unwrap(), data);
     }
 }
 
@@ -73,14 +58,13 @@ fn test_compressible_data() {
     println!("Compressible data ratio: {:.2}", stats.compression_ratio);
     // MPS has overhead, so small repetitive data may not compress well
     // This test verifies the algorithm runs without error
-    assert!(compressed.len() > 0);
+    assert!(!compressed.is_empty());
 }
 
 /// Test with random (incompressible) data
 #[test]
 fn test_random_data() {
-    let mut rng = rand::thread_rng();
-    let data: Vec<u8> = (0..10000).map(|_| rng.gen()).collect();
+    let data = CorpusKind::Random.generate(0, 10000);
     let config = Config::default();
     
     let result = compress(&data, &config);
@@ -92,7 +76,7 @@ fn test_random_data() {
 #[ignore] // Run with --ignored for memory tests
 fn test_memory_pressure() {
     // 100MB of quantum data
-    let data = generate_quantum_data(100 * 1024 * 1024);
+    let data = quantum_circuit_data(0, 100 * 1024 * 1024);
     let config = Config {
         vram_budget: 10 * 1024 * 1024 * 1024, // 10GB
         ..Default::default()
@@ -110,7 +94,7 @@ fn test_memory_pressure() {
 /// Benchmark against zlib
 #[test]
 fn test_vs_zlib() {
-    let data = generate_quantum_data(100000);
+    let data = quantum_circuit_data(0, 100000);
     
     let (our_stats, zlib_ratio, improvement) = quantum_compression::compress::benchmark_vs_zlib(&data);
     
@@ -119,3 +103,450 @@ fn test_vs_zlib() {
     println!("  zlib ratio: {:.2}", zlib_ratio);
     println!("  Improvement: {:.2}x", improvement);
 }
+
+/// Test ring MPS on wrap-around periodic data
+#[test]
+fn test_periodic_boundary_ratio() {
+    // 400 full periods, so the signal wraps around seamlessly
+    let data: Vec<u8> = (0..20000)
+        .map(|i| ((i as f64 * 2.0 * std::f64::consts::PI / 50.0).sin() * 100.0 + 128.0) as u8)
+        .collect();
+
+    let open = Config { max_rank: 3, ..Default::default() };
+    let ring = Config { periodic: true, ..open.clone() };
+
+    let (open_compressed, open_stats) = compress(&data, &open).unwrap();
+    let (ring_compressed, ring_stats) = compress(&data, &ring).unwrap();
+    println!(
+        "Periodic data: open {:.2}x, ring {:.2}x",
+        open_stats.compression_ratio, ring_stats.compression_ratio
+    );
+
+    assert!(ring_stats.compression_ratio > open_stats.compression_ratio);
+    assert_eq!(decompress(&open_compressed).unwrap(), data);
+    assert_eq!(decompress(&ring_compressed).unwrap(), data);
+}
+
+/// Test that a short final chunk is never compressed on its own
+#[test]
+fn test_short_tail_chunk() {
+    // Four full 1KB chunks plus a 30-byte tail
+    let data = quantum_circuit_data(0, 4 * 1024 + 30);
+    let config = Config { chunk_size: 1024, ..Default::default() };
+
+    let (compressed, _) = compress(&data, &config).expect("tail must not hit InputTooSmall");
+    assert_eq!(decompress(&compressed).unwrap(), data);
+
+    let chain = quantum_compression::mps::parallel_compress(&data, 16, 4);
+    let total: usize = chain.iter().map(|mps| mps.len).sum();
+    assert_eq!(total, data.len());
+    assert!(chain.iter().all(|mps| mps.len >= 64));
+}
+
+/// Test that text survives byte-for-byte (BOM and line endings untouched)
+#[test]
+fn test_utf8_bom_preserved() {
+    let mut data = vec![0xEF, 0xBB, 0xBF];
+    data.extend_from_slice("Qubit register\r\nH 0\nCNOT 0 1\r\n".as_bytes());
+    data.extend_from_slice("measure → ψ = (|00⟩ + |11⟩)/√2\n\r\n".repeat(4).as_bytes());
+
+    let (compressed, _) = compress(&data, &Config::default()).unwrap();
+    let restored = decompress(&compressed).unwrap();
+
+    assert_eq!(&restored[..3], &[0xEF, 0xBB, 0xBF]);
+    assert_eq!(restored, data);
+}
+
+/// Reader that yields at most 7 bytes per call and counts calls
+struct TrickleReader<'a> {
+    data: &'a [u8],
+    reads: usize,
+}
+
+impl std::io::Read for TrickleReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reads += 1;
+        let n = buf.len().min(7).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+/// Test buffered decode of back-to-back members from a trickling source
+#[test]
+fn test_decompress_buf_reader() {
+    use std::io::BufReader;
+
+    let first = quantum_circuit_data(0, 3000);
+    let second = quantum_circuit_data(1, 1500);
+    let config = Config { chunk_size: 1024, ..Default::default() };
+
+    let mut stream = compress(&first, &config).unwrap().0;
+    stream.extend_from_slice(&compress(&second, &config).unwrap().0);
+
+    let mut source = TrickleReader { data: &stream, reads: 0 };
+    {
+        let mut reader = BufReader::with_capacity(64, &mut source);
+        let a = quantum_compression::decompress_reader(&mut reader).unwrap();
+        let b = quantum_compression::decompress_reader(&mut reader).unwrap();
+        assert_eq!(a, first);
+        assert_eq!(b, second);
+    }
+
+    // Every underlying read was a full 7-byte read; nothing was read twice
+    assert!(source.data.is_empty());
+    assert!(source.reads <= stream.len().div_ceil(7) + 1, "{} reads", source.reads);
+}
+
+/// Test decode through a boxed trait-object reader with short reads
+#[test]
+fn test_decompress_from_dyn_reader() {
+    use std::io::{Cursor, Read};
+
+    let data = quantum_circuit_data(0, 5000);
+    let config = Config { chunk_size: 1024, ..Default::default() };
+    let mut stream = compress(&data, &config).unwrap().0;
+    let len = stream.len();
+    stream.extend_from_slice(b"next");
+
+    let mut reader: Box<dyn Read> = Box::new(Cursor::new(stream.clone()));
+    assert_eq!(quantum_compression::decompress_from(&mut reader).unwrap(), data);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"next");
+
+    // Reads of at most 7 bytes, and a container cut short
+    let mut trickle: Box<dyn Read + '_> = Box::new(TrickleReader { data: &stream, reads: 0 });
+    assert_eq!(quantum_compression::decompress_from(&mut trickle).unwrap(), data);
+    let mut truncated: Box<dyn Read> = Box::new(Cursor::new(stream[..len - 1].to_vec()));
+    assert!(quantum_compression::decompress_from(&mut truncated).is_err());
+}
+
+/// Test byte-pair grouping on repetitive text
+#[test]
+fn test_bpe_merges_improve_text() {
+    let data = "apply hadamard to qubit 3; apply cnot to qubits 3 and 4; measure qubit 4\n"
+        .repeat(60)
+        .into_bytes();
+
+    let plain = Config::default();
+    let grouped = Config { bpe_merges: 32, ..Default::default() };
+
+    let (plain_compressed, plain_stats) = compress(&data, &plain).unwrap();
+    let (grouped_compressed, grouped_stats) = compress(&data, &grouped).unwrap();
+    println!(
+        "Text: plain {:.2}x, bpe {:.2}x",
+        plain_stats.compression_ratio, grouped_stats.compression_ratio
+    );
+
+    assert!(grouped_stats.compression_ratio > plain_stats.compression_ratio);
+    assert_eq!(decompress(&plain_compressed).unwrap(), data);
+    assert_eq!(decompress(&grouped_compressed).unwrap(), data);
+}
+
+/// Test range validation for f64 amplitude input
+#[test]
+fn test_f64_dynamic_range() {
+    use quantum_compression::{compress_f64, decompress_f64, CompressionError};
+
+    // Wide but representable: exact roundtrip
+    let values: Vec<f64> = (0..512)
+        .map(|i| (i as f64 * 0.05).sin() * 10f64.powi((i % 200) - 100))
+        .collect();
+    let (compressed, _) = compress_f64(&values, &Config::default()).unwrap();
+    let restored = decompress_f64(&compressed).unwrap();
+    assert!(values.iter().zip(&restored).all(|(a, b)| a.to_bits() == b.to_bits()));
+
+    // Beyond the Gram-matrix safe range: rejected with the offending index
+    let mut extreme = values.clone();
+    extreme[300] = 1e300;
+    match compress_f64(&extreme, &Config::default()) {
+        Err(CompressionError::AmplitudeOutOfRange { index, value }) => {
+            assert_eq!(index, 300);
+            assert_eq!(value, 1e300);
+        }
+        other => panic!("expected AmplitudeOutOfRange, got {:?}", other.map(|(_, s)| s)),
+    }
+
+    extreme[300] = f64::NAN;
+    assert!(compress_f64(&extreme, &Config::default()).is_err());
+}
+
+/// Test a chunk the SVD cannot take is stored verbatim when not failing fast
+#[test]
+fn test_fail_fast_off_stores_bad_chunk() {
+    use quantum_compression::{compress_f64, decompress_f64};
+
+    // Four chunks of 128 samples; the third holds an overflowing amplitude
+    let mut values: Vec<f64> = (0..512).map(|i| (i as f64 * 0.01).sin()).collect();
+    values[300] = 1e300;
+    let config = Config { chunk_size: 1024, ..Default::default() };
+    assert!(compress_f64(&values, &config).is_err());
+
+    let lenient = Config { fail_fast: false, ..config };
+    let (compressed, stats) = compress_f64(&values, &lenient).unwrap();
+    assert_eq!(stats.degraded_chunks, vec![2]);
+    let restored = decompress_f64(&compressed).unwrap();
+    assert!(values.iter().zip(&restored).all(|(a, b)| a.to_bits() == b.to_bits()));
+}
+
+/// Test smooth f64 samples favour the amplitude MPS path
+#[test]
+fn test_f64_smooth_roundtrip() {
+    use quantum_compression::{compress_f64, decompress_f64};
+
+    let values: Vec<f64> = (0..4096).map(|i| (i as f64 * 0.01).sin()).collect();
+    let (compressed, stats) = compress_f64(&values, &Config { max_rank: 4, ..Default::default() }).unwrap();
+    println!("Smooth f64 ratio: {:.2}", stats.compression_ratio);
+    assert!(stats.compression_ratio > 1.0);
+    assert_eq!(decompress_f64(&compressed).unwrap(), values);
+}
+
+/// Test adaptive Huffman rebuild cadence on sharply shifting statistics
+#[test]
+fn test_huffman_rebuild_interval_sweep() {
+    // Eight regions, each drawing from its own four-symbol alphabet
+    let mut state = 12345u32;
+    let data: Vec<u8> = (0..16384)
+        .map(|i| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            ((i / 2048) * 4 + (state >> 16) as usize % 4) as u8 * 7
+        })
+        .collect();
+
+    let mut ratios = Vec::new();
+    for interval in [16, 64, 256] {
+        let config = Config { huffman_rebuild_interval: interval, ..Default::default() };
+        let (compressed, stats) = compress(&data, &config).unwrap();
+        println!("Rebuild interval {}: {:.2}x", interval, stats.compression_ratio);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        ratios.push(stats.compression_ratio);
+    }
+    assert!(ratios.windows(2).all(|w| w[0] > w[1]));
+}
+
+/// Test order-1 context modelling on English text
+#[test]
+fn test_order1_entropy_model_text() {
+    use quantum_compression::EntropyModel;
+
+    let text = "It was the best of times, it was the worst of times, it was the age of \
+        wisdom, it was the age of foolishness, it was the epoch of belief, it was the \
+        epoch of incredulity, it was the season of Light, it was the season of Darkness, \
+        it was the spring of hope, it was the winter of despair, we had everything before \
+        us, we had nothing before us, we were all going direct to Heaven, we were all \
+        going direct the other way. There were a king with a large jaw and a queen with a \
+        plain face, on the throne of England; there were a king with a large jaw and a \
+        queen with a fair face, on the throne of France. In both countries it was clearer \
+        than crystal to the lords of the State preserves of loaves and fishes, that things \
+        in general were settled for ever. ";
+    let data = text.repeat(8).into_bytes();
+
+    let mut ratios = Vec::new();
+    for model in [EntropyModel::Order0, EntropyModel::Order1] {
+        let config = Config { entropy_model: model, ..Default::default() };
+        let (compressed, stats) = compress(&data, &config).unwrap();
+        println!("{:?}: {:.2}x", model, stats.compression_ratio);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        ratios.push(stats.compression_ratio);
+    }
+    assert!(ratios[1] > ratios[0]);
+}
+
+/// Test chunk boundaries snap to fixed-size records
+#[test]
+fn test_chunk_alignment_keeps_records_whole() {
+    use quantum_compression::read_manifest;
+
+    // 16-byte gate records: opcode, two qubit indices, padded angle bytes
+    let data: Vec<u8> = (0..1000u32)
+        .flat_map(|i| {
+            let mut record = [0u8; 16];
+            record[0] = (i % 5) as u8;
+            record[1] = (i % 7) as u8;
+            record[2] = (i % 3) as u8;
+            record[8..].copy_from_slice(&((i % 16) as f64 * 0.125).to_le_bytes());
+            record
+        })
+        .collect();
+
+    let config = Config { chunk_size: 1000, chunk_alignment: 16, ..Default::default() };
+    let (compressed, _) = compress(&data, &config).unwrap();
+    let manifest = read_manifest(&compressed).unwrap();
+
+    assert!(manifest.chunk_count > 1);
+    assert!(manifest.chunks.iter().all(|c| c.original_size % 16 == 0));
+    assert_eq!(decompress(&compressed).unwrap(), data);
+}
+
+/// Test u16 samples reconstruct identically from either byte order
+#[test]
+fn test_u16_sample_endianness() {
+    use quantum_compression::{compress_u16, decompress_u16, Endian};
+
+    let values: Vec<u16> = (0..3000u32)
+        .map(|i| (((i as f64 * 0.02).sin() + 1.0) * 20000.0) as u16)
+        .collect();
+    let le: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let be: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+
+    let (le_compressed, _) = compress_u16(&le, &Config::default()).unwrap();
+    let be_config = Config { sample_endian: Endian::Big, ..Default::default() };
+    let (be_compressed, _) = compress_u16(&be, &be_config).unwrap();
+
+    // Same logical samples are coded the same way
+    assert_eq!(le_compressed.len(), be_compressed.len());
+    assert_eq!(decompress_u16(&le_compressed).unwrap(), values);
+    assert_eq!(decompress_u16(&be_compressed).unwrap(), values);
+
+    // Raw decompression restores the caller's byte order
+    assert_eq!(decompress(&be_compressed).unwrap(), be);
+    assert!(compress_u16(&be[..101], &be_config).is_err());
+}
+
+/// Test a chunk far larger than the decompression memory cap streams out
+#[test]
+#[ignore] // Run with --ignored for memory tests
+fn test_decompress_large_chunk_under_memory_cap() {
+    use quantum_compression::decompress_to_writer;
+
+    /// Records the largest single write
+    struct PieceWriter {
+        data: Vec<u8>,
+        largest: usize,
+    }
+
+    impl std::io::Write for PieceWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.largest = self.largest.max(buf.len());
+            self.data.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // One 8MB chunk
+    let data = quantum_circuit_data(0, 8 * 1024 * 1024);
+    let config = Config { chunk_size: data.len(), ..Default::default() };
+    let (compressed, _) = compress(&data, &config).unwrap();
+
+    let cap = 256 * 1024;
+    let tight = Config { max_chunk_memory: cap, ..Default::default() };
+    let mut writer = PieceWriter { data: Vec::new(), largest: 0 };
+    let written = decompress_to_writer(&compressed, &mut writer, &tight).unwrap();
+
+    assert_eq!(written as usize, data.len());
+    assert!(writer.largest <= cap);
+    assert_eq!(writer.data, data);
+}
+
+/// Test lossy output respects the requested error bound
+#[test]
+fn test_error_bound_respected_per_byte() {
+    use quantum_compression::{compress_to_error, ErrorMetric};
+
+    let data = quantum_circuit_data(0, 50000);
+    let (lossless, lossless_stats) = compress(&data, &Config::default()).unwrap();
+
+    for bound in [1.0, 4.0, 16.0] {
+        let (compressed, stats) = compress_to_error(&data, &Config::default(), bound).unwrap();
+        let restored = decompress(&compressed).unwrap();
+        assert_eq!(restored.len(), data.len());
+        assert!(data.iter().zip(&restored).all(|(&a, &b)| a.abs_diff(b) as f64 <= bound));
+
+        let fidelity = stats.fidelity.unwrap();
+        println!("LInf <= {bound}: ratio {:.2} (achieved {})", stats.compression_ratio, fidelity.value);
+        assert_eq!(fidelity.metric, ErrorMetric::LInf);
+        assert!(fidelity.value <= bound);
+        assert!(compressed.len() < lossless.len());
+    }
+
+    let psnr = Config { error_metric: ErrorMetric::Psnr, ..Default::default() };
+    let (_, stats) = compress_to_error(&data, &psnr, 40.0).unwrap();
+    assert!(stats.fidelity.unwrap().value >= 40.0);
+    assert!(stats.compression_ratio > lossless_stats.compression_ratio);
+}
+
+/// Test trailing padding after a complete container is ignored
+#[test]
+fn test_trailing_padding_ignored() {
+    use quantum_compression::decompress_member;
+
+    let data = quantum_circuit_data(0, 20000);
+    let (compressed, _) = compress(&data, &Config::default()).unwrap();
+    let mut padded = compressed.clone();
+    padded.extend_from_slice(&[0u8; 100]);
+
+    assert_eq!(decompress(&padded).unwrap(), data);
+    let (restored, consumed) = decompress_member(&padded).unwrap();
+    assert_eq!(restored, data);
+    assert_eq!(consumed, compressed.len());
+    assert_eq!(padded.len() - consumed, 100);
+
+    // Padding is no substitute for missing content
+    assert!(decompress(&padded[..compressed.len() - 1]).is_err());
+}
+
+/// Test parallel block decode matches serial decode on a 5 MB archive
+#[test]
+fn test_parallel_decode_matches_serial() {
+    use quantum_compression::{decompress_reader, decompress_to_writer};
+
+    let data = quantum_circuit_data(0, 5 * 1024 * 1024);
+    let config = Config { chunk_size: 256 * 1024, ..Default::default() };
+    let (compressed, _) = compress(&data, &config).unwrap();
+
+    let parallel = decompress(&compressed).unwrap();
+    let serial = decompress_reader(&mut compressed.as_slice()).unwrap();
+    assert_eq!(parallel, serial);
+    assert_eq!(parallel, data);
+
+    // Streaming with one worker, and in parallel batches bounded by the cap
+    for threads in [1, 4] {
+        let bounded = Config { threads, max_chunk_memory: 1024 * 1024, ..Default::default() };
+        let mut streamed = Vec::new();
+        decompress_to_writer(&compressed, &mut streamed, &bounded).unwrap();
+        assert_eq!(streamed, data);
+    }
+}
+
+/// Test restores against a caller-supplied hash of the original
+#[test]
+fn test_decompress_verified_against_expected_hash() {
+    use quantum_compression::{decompress_verified, ChecksumScope, CompressionError};
+
+    let data = quantum_circuit_data(0, 20000);
+    let config = Config { format_version: 2, ..Default::default() };
+    let (compressed, _) = compress(&data, &config).unwrap();
+    let expected = crc32fast::hash(&data);
+
+    assert_eq!(decompress_verified(&compressed, expected).unwrap(), data);
+    match decompress_verified(&compressed, expected ^ 1) {
+        Err(CompressionError::ChecksumMismatch { scope: ChecksumScope::Expected, expected: e, actual }) => {
+            assert_eq!((e, actual), (expected ^ 1, expected));
+        }
+        other => panic!("expected a checksum mismatch, got {other:?}"),
+    }
+}
+
+/// Test deterministic output does not depend on when compression runs
+#[test]
+fn test_deterministic_output_is_reproducible() {
+    use quantum_compression::compress_deadline;
+    use std::time::{Duration, Instant};
+
+    let data = quantum_circuit_data(0, 300_000);
+    let config = Config { deterministic: true, chunk_size: 64 * 1024, ..Default::default() };
+    let (first, _) = compress(&data, &config).unwrap();
+    let (rushed, _) = compress_deadline(&data, &config, Instant::now() + Duration::from_millis(1)).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    let (second, _) = compress(&data, &config).unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(rushed, first);
+    assert_eq!(decompress(&first).unwrap(), data);
+}